@@ -0,0 +1,53 @@
+// Adopting processes that were started outside harissa
+//
+// The launch spec of an adopted process is read from `/proc/<pid>`: its command line, working
+// directory and environment, and its user when harissa runs as root. `reload` relaunches it
+// through harissa from that spec, after which its output goes to the usual log files.
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use crate::app_spec::AppSpec;
+use crate::credentials;
+use crate::error::HarissaError;
+use crate::process_identity;
+
+/// Capture the spec that starts a running process the same way again
+pub fn capture_spec(pid: u32) -> Result<AppSpec, HarissaError> {
+    let proc_dir = PathBuf::from(format!("/proc/{}", pid));
+    let metadata = fs::metadata(&proc_dir)
+        .map_err(|_| HarissaError::NotFound(format!("No process with PID {}", pid)))?;
+
+    let cmdline = fs::read(proc_dir.join("cmdline"))
+        .map_err(|e| HarissaError::io(&format!("Failed to read the command line of process {}", pid), e))?;
+    let argv = split_nul(&cmdline);
+    if argv.is_empty() {
+        return Err(HarissaError::Failed(format!("Process {} has no command line; kernel threads and zombies cannot be adopted", pid)));
+    }
+
+    let cwd = fs::read_link(proc_dir.join("cwd"))
+        .map_err(|e| HarissaError::io(&format!("Failed to read the working directory of process {}", pid), e))?;
+    if cwd.to_string_lossy().ends_with(process_identity::DELETED_SUFFIX) {
+        return Err(HarissaError::Failed(format!("The working directory of process {} was deleted, so it could not be started again", pid)));
+    }
+    let environ = fs::read(proc_dir.join("environ"))
+        .map_err(|e| HarissaError::io(&format!("Failed to read the environment of process {}", pid), e))?;
+    let env = split_nul(&environ).into_iter()
+        .map(|variable| match variable.split_once('=') {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None => (variable, String::new()),
+        })
+        .collect();
+
+    // Root relaunches the process as the user it runs as; other users can only adopt their own
+    let user = (credentials::is_root() && metadata.uid() != 0).then(|| metadata.uid().to_string());
+
+    Ok(AppSpec { argv, cwd: Some(cwd), env, user, ..AppSpec::default() })
+}
+
+/// The NUL-separated strings of a /proc file like cmdline or environ
+fn split_nul(contents: &[u8]) -> Vec<String> {
+    contents.split(|byte| *byte == 0)
+        .filter(|part| !part.is_empty())
+        .map(|part| String::from_utf8_lossy(part).into_owned())
+        .collect()
+}
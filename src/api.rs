@@ -0,0 +1,273 @@
+// Methods of the control API, shared by the Unix socket server
+//
+// Parameters and results are JSON values. Methods mirror the CLI commands and are
+// implemented with the `Harissa` client.
+use std::path::{Path, PathBuf};
+use serde_json::{Value, json};
+use crate::app_ids;
+use crate::app_spec::AppSpec;
+use crate::app_status::AppStatus;
+use crate::client::{AppRef, Harissa, StopOutcome, StoppedApp};
+use crate::error::HarissaError;
+use crate::logs::{LogStream, LogSubscription};
+use crate::namespace;
+use crate::supervisor::CronSchedule;
+
+const DEFAULT_LOG_LINES: usize = 10;
+
+/// An error returned to API clients, with a JSON-RPC error code
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub code: i64,
+    pub message: String,
+    /// Category of the error, as in the CLI's JSON errors (e.g. "not_found")
+    pub kind: &'static str,
+}
+
+impl ApiError {
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// The command itself failed, e.g. the app does not exist
+    pub const COMMAND_FAILED: i64 = -32000;
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        ApiError { code: Self::INVALID_PARAMS, message: message.into(), kind: "usage" }
+    }
+
+    pub fn command_failed(message: impl Into<String>) -> Self {
+        ApiError { code: Self::COMMAND_FAILED, message: message.into(), kind: "failed" }
+    }
+}
+
+impl From<HarissaError> for ApiError {
+    fn from(error: HarissaError) -> Self {
+        let code = match error {
+            HarissaError::Usage(_) => Self::INVALID_PARAMS,
+            _ => Self::COMMAND_FAILED,
+        };
+        ApiError { code, message: error.message().to_string(), kind: error.kind() }
+    }
+}
+
+/// Run a method that answers with a single result
+pub fn call(harissa_dir: &Path, method: &str, params: &Value) -> Result<Value, ApiError> {
+    let harissa = client(harissa_dir, params)?;
+    match method {
+        "list" => list(&harissa),
+        "start" => start(&harissa, params),
+        // `kill` is the name this method had before applications could be stopped and started again
+        "stop" | "kill" => stop(&harissa, params),
+        "delete" => delete(&harissa, params),
+        "reload" => reload(&harissa, params),
+        _ => Err(ApiError { code: ApiError::METHOD_NOT_FOUND, message: format!("Unknown method: {}", method), kind: "usage" }),
+    }
+}
+
+/// The client for `{"namespace": ...}`, or for every application without it
+fn client(harissa_dir: &Path, params: &Value) -> Result<Harissa, ApiError> {
+    let harissa = Harissa::with_dir(harissa_dir);
+    match params.get("namespace") {
+        Some(namespace) => {
+            let namespace = namespace.as_str().ok_or_else(|| ApiError::invalid_params("\"namespace\" must be a string"))?;
+            Ok(harissa.in_namespace(namespace)?)
+        },
+        None => Ok(harissa),
+    }
+}
+
+/// JSON representation of an application's status
+pub fn status_json(app: &AppStatus) -> Value {
+    json!({
+        "id": app.id,
+        "name": app.name,
+        "namespace": namespace::split(&app.name).0,
+        "pid": app.pid.parse::<u32>().ok(),
+        "command": app.command,
+        "state": app.state.as_str(),
+        "healthy": app.is_healthy(),
+        "cpu_percent": app.cpu_percent,
+        "memory_bytes": app.memory_bytes,
+        "uptime_seconds": app.uptime.as_secs(),
+        "restarts": app.restarts,
+    })
+}
+
+fn list(harissa: &Harissa) -> Result<Value, ApiError> {
+    let apps = harissa.list()?;
+    Ok(Value::Array(apps.iter().map(status_json).collect()))
+}
+
+/// Start an application from `{"command": [...], "name", "cwd", "env": {...}, "options": {...}, "force"}`.
+/// Options use the keys of spec files, e.g. `{"memory-max": "512M", "after": "db"}`.
+/// With `"force": true` a running application with the same name is replaced.
+/// Without a command, the stopped applications given like for stop are started again.
+fn start(harissa: &Harissa, params: &Value) -> Result<Value, ApiError> {
+    if params.get("command").is_none() && let Some(target) = app_ref(params)? {
+        return start_stopped(harissa, &target);
+    }
+
+    let argv: Vec<String> = params.get("command")
+        .and_then(Value::as_array)
+        .map(|command| command.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default();
+    if argv.is_empty() {
+        return Err(ApiError::invalid_params("start requires a non-empty \"command\" array"));
+    }
+
+    let name = params.get("name").and_then(Value::as_str).unwrap_or(&argv[0]).to_string();
+
+    let mut spec = AppSpec {
+        argv,
+        cwd: params.get("cwd").and_then(Value::as_str).map(PathBuf::from),
+        ..AppSpec::default()
+    };
+
+    if let Some(env) = params.get("env") {
+        let env = env.as_object().ok_or_else(|| ApiError::invalid_params("\"env\" must be an object"))?;
+        spec.env = env.iter().map(|(key, value)| (key.clone(), value_string(value))).collect();
+        spec.explicit_env = env.keys().cloned().collect();
+    }
+
+    if let Some(options) = params.get("options") {
+        let options = options.as_object().ok_or_else(|| ApiError::invalid_params("\"options\" must be an object"))?;
+        for (key, value) in options {
+            // A list sets a repeatable option such as "after" several times
+            let values = match value {
+                Value::Array(values) => values.iter().map(value_string).collect(),
+                value => vec![value_string(value)],
+            };
+            for value in values {
+                spec.set(key, &value).map_err(ApiError::invalid_params)?;
+            }
+        }
+    }
+
+    if let Some(cron) = &spec.cron_restart {
+        CronSchedule::parse(cron).map_err(ApiError::invalid_params)?;
+    }
+
+    let force = params.get("force").and_then(Value::as_bool).unwrap_or(false);
+    let app = if force { harissa.replace(&name, &spec)? } else { harissa.start(&name, &spec)? };
+
+    Ok(json!({ "id": app.id, "name": app.name, "pid": app.pid }))
+}
+
+fn start_stopped(harissa: &Harissa, target: &AppRef) -> Result<Value, ApiError> {
+    let started = harissa.start_stopped(target)?;
+
+    let failures: Vec<(String, &HarissaError)> = started.iter()
+        .filter_map(|app| app.result.as_ref().err().map(|e| (format!("Failed to start {}: {}", app.name, e), e)))
+        .collect();
+    failures_to_error(&failures)?;
+
+    let apps = started.iter()
+        .filter_map(|app| app.result.as_ref().ok())
+        .map(|app| json!({ "id": app.id, "name": app.name, "pid": app.pid }))
+        .collect();
+    Ok(Value::Array(apps))
+}
+
+/// Stop the apps given by `{"id"}`, `{"name"}`, `{"pid"}` or `{"tag"}`, keeping them to be started again
+fn stop(harissa: &Harissa, params: &Value) -> Result<Value, ApiError> {
+    let stopped = harissa.stop(&required_app_ref(params)?)?;
+    stop_failures_to_error(&stopped)?;
+    Ok(json!({ "ok": true }))
+}
+
+/// Stop the apps given like for stop and forget them, stopped ones included
+fn delete(harissa: &Harissa, params: &Value) -> Result<Value, ApiError> {
+    let deleted = harissa.delete(&required_app_ref(params)?)?;
+    stop_failures_to_error(&deleted)?;
+    Ok(json!({ "ok": true }))
+}
+
+fn stop_failures_to_error(stopped: &[StoppedApp]) -> Result<(), ApiError> {
+    let failures: Vec<(String, &HarissaError)> = stopped.iter()
+        .filter_map(|app| match &app.outcome {
+            StopOutcome::Failed(error) => Some((format!("Failed to kill process {} ({}): {}", app.name, app.pid, error), error)),
+            _ => None,
+        })
+        .collect();
+    failures_to_error(&failures)
+}
+
+/// Reload the apps given like for stop, or all applications without parameters
+fn reload(harissa: &Harissa, params: &Value) -> Result<Value, ApiError> {
+    let reloaded = harissa.reload(app_ref(params)?.as_ref())?;
+
+    let failures: Vec<(String, &HarissaError)> = reloaded.iter()
+        .filter_map(|app| app.result.as_ref().err().map(|e| (format!("Failed to restart {}: {}", app.name, e), e)))
+        .collect();
+    failures_to_error(&failures)?;
+
+    Ok(json!({ "ok": true }))
+}
+
+/// One error for the apps a command failed on, with the category of the first failure
+fn failures_to_error(failures: &[(String, &HarissaError)]) -> Result<(), ApiError> {
+    let Some((_, first)) = failures.first() else {
+        return Ok(());
+    };
+
+    let messages: Vec<&str> = failures.iter().map(|(message, _)| message.as_str()).collect();
+    Err(ApiError { kind: first.kind(), ..ApiError::command_failed(messages.join("\n")) })
+}
+
+/// The apps identified by `{"id": ...}`, `{"pid": ...}`, `{"tag": ...}` or `{"name": ...}`, if any.
+/// Like on the command line, a name may also be an ID, a pattern, a `/regex/` or `all`.
+fn app_ref(params: &Value) -> Result<Option<AppRef>, ApiError> {
+    let number = |key: &str, value: &Value| value_string(value).parse::<u32>()
+        .map_err(|_| ApiError::invalid_params(format!("\"{}\" must be a number", key)));
+
+    match (params.get("id"), params.get("name"), params.get("pid")) {
+        (Some(id), _, _) => Ok(Some(AppRef::Id(number("id", id)?))),
+        (None, Some(name), _) => Ok(Some(AppRef::parse(&value_string(name)))),
+        (None, None, Some(pid)) => Ok(Some(AppRef::Pid(number("pid", pid)?))),
+        (None, None, None) => Ok(params.get("tag").map(|tag| AppRef::Tag(value_string(tag)))),
+    }
+}
+
+fn required_app_ref(params: &Value) -> Result<AppRef, ApiError> {
+    app_ref(params)?.ok_or_else(|| ApiError::invalid_params("expected \"id\", \"name\", \"pid\" or \"tag\""))
+}
+
+fn value_string(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// The log subscription for `{"name", "stream": "out" | "err" | "both", "lines": N, "namespace"}`
+pub fn log_subscription(harissa_dir: &Path, params: &Value) -> Result<LogSubscription, ApiError> {
+    let name = params.get("name").and_then(Value::as_str)
+        .ok_or_else(|| ApiError::invalid_params("expected the \"name\" of an application"))?;
+    let harissa = client(harissa_dir, params)?;
+
+    // Logs outlive the app, so only IDs of tracked apps are looked up and anything else is a name
+    let app_ids = app_ids::load(harissa_dir)?;
+    let name = match AppRef::parse(name) {
+        AppRef::Id(id) if let Some(name) = app_ids.name_of(id) => name.to_string(),
+        _ => harissa.qualify(name),
+    };
+    let stream = LogStream::parse(params.get("stream").and_then(Value::as_str).unwrap_or("both"))
+        .map_err(ApiError::invalid_params)?;
+    let lines = params.get("lines").and_then(Value::as_u64).map(|lines| lines as usize).unwrap_or(DEFAULT_LOG_LINES);
+
+    LogSubscription::new(harissa_dir, &name, stream, lines).map_err(|e| HarissaError::NotFound(e).into())
+}
+
+/// A JSON-RPC 2.0 success response
+pub fn response(id: &Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+/// A JSON-RPC 2.0 error response
+pub fn error_response(id: &Value, error: &ApiError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": error.code, "message": error.message, "data": { "kind": error.kind } } })
+}
+
+/// A JSON-RPC 2.0 notification carrying one log line
+pub fn log_notification(name: &str, stream: &str, line: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "method": "log", "params": { "name": name, "stream": stream, "line": line } })
+}
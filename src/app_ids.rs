@@ -0,0 +1,107 @@
+// Stable numeric IDs of tracked applications, which unlike PIDs survive reloads
+//
+// Stored in `~/.harissa_apps/app_ids.tsv`: a `next<TAB>N` line with the next ID to hand out,
+// then one `id<TAB>name` line per application. IDs are never reused.
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::error::HarissaError;
+
+const IDS_FILE: &str = "app_ids.tsv";
+
+/// The IDs of all tracked applications
+#[derive(Debug, Clone, Default)]
+pub struct AppIds {
+    next: u32,
+    ids: Vec<(u32, String)>,
+}
+
+impl AppIds {
+    pub fn id_of(&self, name: &str) -> Option<u32> {
+        self.ids.iter().find(|(_, app)| app == name).map(|(id, _)| *id)
+    }
+
+    pub fn name_of(&self, id: u32) -> Option<&str> {
+        self.ids.iter().find(|(app_id, _)| *app_id == id).map(|(_, name)| name.as_str())
+    }
+}
+
+fn ids_path(harissa_dir: &Path) -> PathBuf {
+    harissa_dir.join(IDS_FILE)
+}
+
+/// Read the IDs; a missing file means no app has an ID yet
+pub fn load(harissa_dir: &Path) -> Result<AppIds, HarissaError> {
+    let path = ids_path(harissa_dir);
+    if !path.exists() {
+        return Ok(AppIds { next: 1, ids: Vec::new() });
+    }
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| HarissaError::io(&format!("Failed to read {}", path.display()), e))?;
+
+    let mut app_ids = AppIds { next: 1, ids: Vec::new() };
+    for line in contents.lines() {
+        let Some((key, value)) = line.split_once('\t') else {
+            continue;
+        };
+        if key == "next" {
+            app_ids.next = value.parse().unwrap_or(1);
+        } else if let Ok(id) = key.parse() {
+            app_ids.ids.push((id, value.to_string()));
+        }
+    }
+
+    // Never hand out an ID that is in use, even if the counter was edited
+    app_ids.next = app_ids.ids.iter().map(|(id, _)| id + 1).fold(app_ids.next, u32::max);
+    Ok(app_ids)
+}
+
+fn save(harissa_dir: &Path, app_ids: &AppIds) -> Result<(), HarissaError> {
+    let mut contents = format!("next\t{}\n", app_ids.next);
+    for (id, name) in &app_ids.ids {
+        contents.push_str(&format!("{}\t{}\n", id, name));
+    }
+
+    let path = ids_path(harissa_dir);
+    fs::write(&path, contents)
+        .map_err(|e| HarissaError::io(&format!("Failed to write {}", path.display()), e))
+}
+
+/// The ID of an application, giving it a new one if it has none
+pub fn assign(harissa_dir: &Path, name: &str) -> Result<u32, HarissaError> {
+    let mut app_ids = load(harissa_dir)?;
+    if let Some(id) = app_ids.id_of(name) {
+        return Ok(id);
+    }
+
+    let id = app_ids.next;
+    app_ids.next += 1;
+    app_ids.ids.push((id, name.to_string()));
+    save(harissa_dir, &app_ids)?;
+
+    Ok(id)
+}
+
+/// Give an application back the ID it had before it was stopped, unless it has one again
+pub fn restore(harissa_dir: &Path, name: &str, id: u32) -> Result<(), HarissaError> {
+    let mut app_ids = load(harissa_dir)?;
+    if app_ids.id_of(name).is_some() || app_ids.name_of(id).is_some() {
+        return Ok(());
+    }
+
+    app_ids.ids.push((id, name.to_string()));
+    save(harissa_dir, &app_ids)
+}
+
+/// Forget the ID of an application that is no longer tracked (best effort)
+pub fn release(harissa_dir: &Path, name: &str) {
+    let Ok(mut app_ids) = load(harissa_dir) else {
+        return;
+    };
+
+    let count = app_ids.ids.len();
+    app_ids.ids.retain(|(_, app)| app != name);
+    if app_ids.ids.len() != count {
+        let _ = save(harissa_dir, &app_ids);
+    }
+}
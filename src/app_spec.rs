@@ -0,0 +1,291 @@
+// Launch specs of applications, stored next to their logs as `<name>.spec`
+//
+// A spec is a list of `key=value` lines. Keys that hold lists (`arg`, `env`, `after`, `tag`) are repeated,
+// and backslashes and newlines in values are escaped. The dump written by `save` holds
+// several specs, each introduced by a `[name]` header.
+//
+// Specs hold commands that `reload`, `resurrect` and hooks run later, possibly as root. Root only
+// runs those of files that belong to root and that nobody else can write (see `check_trusted`),
+// because in the system-wide home every member of the harissa group can write files.
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use crate::cgroup::CgroupLimits;
+use crate::credentials;
+use crate::error::HarissaError;
+use crate::home;
+use crate::hooks::{self, Hooks};
+use crate::process_attrs::ProcessAttributes;
+
+const DUMP_FILE: &str = "harissa.dump";
+/// Specs hold the environment of whoever started the application, so only the owner may read them
+const TRUSTED_FILE_MODE: u32 = 0o600;
+/// In the system-wide home the `harissa` group may read them too, but only the owner may write them
+const SYSTEM_TRUSTED_FILE_MODE: u32 = 0o640;
+
+/// Everything needed to relaunch an application the same way it was started
+#[derive(Debug, Clone, Default)]
+pub struct AppSpec {
+    pub argv: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    /// Names of the variables of `env` set for the application itself (`--env`, a Procfile's `.env`
+    /// and `$PORT`), as opposed to those inherited from the shell that started it; only these are exported
+    pub explicit_env: Vec<String>,
+    pub limits: CgroupLimits,
+    pub attributes: ProcessAttributes,
+    pub user: Option<String>,
+    pub group: Option<String>,
+    pub cron_restart: Option<String>,
+    /// Applications that must be running before this one starts
+    pub after: Vec<String>,
+    /// Labels for selecting several applications at once, e.g. `kill --tag backend`
+    pub tags: Vec<String>,
+    pub hooks: Hooks,
+}
+
+impl AppSpec {
+    /// The command line as shown in `list` and stored in processes.csv
+    pub fn command_line(&self) -> String {
+        self.argv.join(" ")
+    }
+
+    /// The variables of `env` set for the application itself, without the inherited ones
+    pub fn explicit_env(&self) -> impl Iterator<Item = &(String, String)> {
+        self.env.iter().filter(|(name, _)| self.explicit_env.contains(name))
+    }
+
+    /// Apply one `key=value` line of a spec, e.g. ("memory-max", "512M") or ("after", "db")
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "arg" => self.argv.push(value.to_string()),
+            "cwd" => self.cwd = Some(PathBuf::from(value)),
+            "env" => {
+                let (name, value) = value.split_once('=').unwrap_or((value, ""));
+                self.env.push((name.to_string(), value.to_string()));
+            },
+            "explicit-env" => self.explicit_env.push(value.to_string()),
+            "user" => self.user = Some(value.to_string()),
+            "group" => self.group = Some(value.to_string()),
+            "cron-restart" => self.cron_restart = Some(value.to_string()),
+            "after" => self.after.push(value.to_string()),
+            "tag" => self.tags.push(value.to_string()),
+            _ => {
+                let option = format!("--{}", key);
+                if crate::cgroup::LIMIT_OPTIONS.contains(&option.as_str()) {
+                    self.limits.set_option(&option, value)?;
+                } else if hooks::HOOK_OPTIONS.contains(&option.as_str()) {
+                    self.hooks.set_option(&option, value)?;
+                } else {
+                    self.attributes.set_option(&option, value)?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Write the spec as `key=value` lines
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        for arg in &self.argv {
+            writeln!(writer, "arg={}", escape(arg))?;
+        }
+        if let Some(cwd) = &self.cwd {
+            writeln!(writer, "cwd={}", escape(&cwd.to_string_lossy()))?;
+        }
+        for (name, value) in &self.env {
+            writeln!(writer, "env={}={}", escape(name), escape(value))?;
+        }
+        for name in &self.explicit_env {
+            writeln!(writer, "explicit-env={}", escape(name))?;
+        }
+        if let Some(user) = &self.user {
+            writeln!(writer, "user={}", user)?;
+        }
+        if let Some(group) = &self.group {
+            writeln!(writer, "group={}", group)?;
+        }
+        if let Some(cron) = &self.cron_restart {
+            writeln!(writer, "cron-restart={}", cron)?;
+        }
+        for dependency in &self.after {
+            writeln!(writer, "after={}", escape(dependency))?;
+        }
+        for tag in &self.tags {
+            writeln!(writer, "tag={}", escape(tag))?;
+        }
+        for (option, value) in self.limits.to_options().into_iter().chain(self.attributes.to_options()) {
+            writeln!(writer, "{}={}", option.trim_start_matches("--"), value)?;
+        }
+        for (option, command) in self.hooks.to_options() {
+            writeln!(writer, "{}={}", option.trim_start_matches("--"), escape(&command))?;
+        }
+        Ok(())
+    }
+}
+
+/// Path of an application's spec file
+pub fn spec_path(harissa_dir: &Path, name: &str) -> PathBuf {
+    harissa_dir.join(format!("{}.spec", name))
+}
+
+/// Path of the dump file written by `save`
+pub fn dump_path(harissa_dir: &Path) -> PathBuf {
+    harissa_dir.join(DUMP_FILE)
+}
+
+/// Load an application's spec. Apps started by older versions may have no spec or no `arg` lines,
+/// in which case `argv` is empty and the caller falls back to the command in processes.csv.
+pub fn load(harissa_dir: &Path, name: &str) -> Result<AppSpec, HarissaError> {
+    let path = spec_path(harissa_dir, name);
+    if !path.exists() {
+        return Ok(AppSpec::default());
+    }
+
+    let specs = read_specs(&path)?;
+    Ok(specs.into_iter().next().map(|(_, spec)| spec).unwrap_or_default())
+}
+
+/// Load the spec of an application whose commands are about to be run or saved for `resurrect`.
+/// Root refuses specs that someone else could have written.
+pub fn load_trusted(harissa_dir: &Path, name: &str) -> Result<AppSpec, HarissaError> {
+    check_trusted(&spec_path(harissa_dir, name))?;
+    load(harissa_dir, name)
+}
+
+/// Check that root may run the commands of a file: it must belong to root and must not be
+/// writable by its group or others. Anyone else may run the commands of any file they can read.
+pub fn check_trusted(path: &Path) -> Result<(), HarissaError> {
+    if !credentials::is_root() {
+        return Ok(());
+    }
+
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(HarissaError::io(&format!("Failed to read {}", path.display()), e)),
+    };
+
+    if metadata.uid() != 0 {
+        return Err(HarissaError::PermissionDenied(format!(
+            "Refusing to run the commands of {} as root: it belongs to user {}", path.display(), metadata.uid())));
+    }
+    if metadata.mode() & 0o022 != 0 {
+        return Err(HarissaError::PermissionDenied(format!(
+            "Refusing to run the commands of {} as root: it is writable by its group or others", path.display())));
+    }
+    Ok(())
+}
+
+/// Create a file of a harissa home holding commands and environments, writable only by this user
+/// and readable only by them (and the `harissa` group in the system-wide home). An existing file
+/// is replaced rather than rewritten, so it belongs to whoever wrote it last.
+pub fn create_trusted_file(harissa_dir: &Path, path: &Path) -> io::Result<File> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {},
+    }
+
+    let mode = if home::is_system(harissa_dir) { SYSTEM_TRUSTED_FILE_MODE } else { TRUSTED_FILE_MODE };
+    let file = OpenOptions::new().write(true).create_new(true).mode(mode).open(path)?;
+    // Narrow the mode even if the umask allows more; the setgid directory provides the group
+    file.set_permissions(fs::Permissions::from_mode(mode))?;
+    Ok(file)
+}
+
+/// Save an application's spec
+pub fn save(harissa_dir: &Path, name: &str, spec: &AppSpec) -> Result<(), HarissaError> {
+    let path = spec_path(harissa_dir, name);
+    let mut file = create_trusted_file(harissa_dir, &path)
+        .map_err(|e| HarissaError::io(&format!("Failed to create spec file {}", path.display()), e))?;
+
+    spec.write_to(&mut file)
+        .map_err(|e| HarissaError::io("Failed to write to spec file", e))
+}
+
+/// Remove an application's spec file if it has one
+pub fn remove(harissa_dir: &Path, name: &str) {
+    let _ = fs::remove_file(spec_path(harissa_dir, name));
+}
+
+/// Write the specs of several applications into the dump file
+pub fn write_dump(harissa_dir: &Path, apps: &[(String, AppSpec)]) -> Result<(), HarissaError> {
+    let path = dump_path(harissa_dir);
+    let mut file = create_trusted_file(harissa_dir, &path)
+        .map_err(|e| HarissaError::io(&format!("Failed to create dump file {}", path.display()), e))?;
+
+    for (name, spec) in apps {
+        writeln!(file, "[{}]", name)
+            .and_then(|_| spec.write_to(&mut file))
+            .and_then(|_| writeln!(file))
+            .map_err(|e| HarissaError::io("Failed to write to dump file", e))?;
+    }
+
+    Ok(())
+}
+
+/// Read the applications saved in the dump file
+pub fn read_dump(harissa_dir: &Path) -> Result<Vec<(String, AppSpec)>, HarissaError> {
+    let path = dump_path(harissa_dir);
+    if !path.exists() {
+        return Err(HarissaError::NotFound("No saved applications found. Run 'harissa save' first.".to_string()));
+    }
+
+    check_trusted(&path)?;
+    read_specs(&path)
+}
+
+/// Read a file holding one spec, or several specs each starting with a `[name]` header
+fn read_specs(path: &Path) -> Result<Vec<(String, AppSpec)>, HarissaError> {
+    let file = File::open(path)
+        .map_err(|e| HarissaError::io(&format!("Failed to open {}", path.display()), e))?;
+
+    let mut specs: Vec<(String, AppSpec)> = Vec::new();
+
+    for line in io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| HarissaError::io("Failed to read line", e))?;
+
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            specs.push((name.to_string(), AppSpec::default()));
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            // Skip blank and invalid lines
+            continue;
+        };
+
+        if specs.is_empty() {
+            specs.push((String::new(), AppSpec::default()));
+        }
+        if let Some((_, spec)) = specs.last_mut() {
+            spec.set(key, &unescape(value))
+                .map_err(|e| HarissaError::Failed(format!("Invalid spec in {}: {}", path.display(), e)))?;
+        }
+    }
+
+    Ok(specs)
+}
+
+/// Escape backslashes, newlines and tabs, so a value fits on one line and in a tab-separated field
+pub(crate) fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n").replace('\t', "\\t")
+}
+
+pub(crate) fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
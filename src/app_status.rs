@@ -0,0 +1,217 @@
+// Live status of tracked applications, shared by `list` and the metrics endpoint
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use crate::app_ids;
+use crate::app_spec;
+use crate::cgroup;
+use crate::error::HarissaError;
+use crate::events::{self, EventKind};
+use crate::process_identity;
+use crate::stopped_apps;
+
+/// How long CPU usage of cgroup-tracked apps is sampled for
+const CPU_SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// State of a tracked application's process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppState {
+    /// Running (or sleeping) normally
+    Online,
+    /// Suspended, e.g. by SIGSTOP
+    Paused,
+    /// The process is gone although harissa still tracks it
+    Errored,
+    /// Stopped with `stop` and kept to be started again
+    Stopped,
+}
+
+impl AppState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AppState::Online => "online",
+            AppState::Paused => "paused",
+            AppState::Errored => "errored",
+            AppState::Stopped => "stopped",
+        }
+    }
+}
+
+/// Everything known about a tracked application at one point in time
+#[derive(Debug, Clone)]
+pub struct AppStatus {
+    /// Stable ID; apps started by older versions have none
+    pub id: Option<u32>,
+    pub pid: String,
+    pub name: String,
+    pub command: String,
+    pub state: AppState,
+    /// Percent of one CPU; sampled from the cgroup, or the lifetime average reported by ps
+    pub cpu_percent: f64,
+    pub memory_bytes: u64,
+    /// Share of system memory, only known for apps without their own cgroup
+    pub memory_percent: Option<f64>,
+    pub uptime: Duration,
+    /// Reloads and scheduled restarts since the application was started
+    pub restarts: usize,
+}
+
+impl AppStatus {
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, AppState::Online | AppState::Paused)
+    }
+
+    /// An application is healthy when its process is running and not suspended
+    pub fn is_healthy(&self) -> bool {
+        self.state == AppState::Online
+    }
+}
+
+/// Collect the status of every application in processes.csv, followed by the stopped ones
+pub fn collect(harissa_dir: &Path) -> Result<Vec<AppStatus>, HarissaError> {
+    let mut statuses = collect_tracked(harissa_dir)?;
+
+    let app_ids = app_ids::load(harissa_dir)?;
+    for name in stopped_apps::load(harissa_dir)? {
+        statuses.push(AppStatus {
+            id: app_ids.id_of(&name),
+            pid: String::new(),
+            command: app_spec::load(harissa_dir, &name)?.command_line(),
+            name,
+            state: AppState::Stopped,
+            cpu_percent: 0.0,
+            memory_bytes: 0,
+            memory_percent: None,
+            uptime: Duration::ZERO,
+            restarts: 0,
+        });
+    }
+
+    Ok(statuses)
+}
+
+fn collect_tracked(harissa_dir: &Path) -> Result<Vec<AppStatus>, HarissaError> {
+    let processes_file = harissa_dir.join("processes.csv");
+    if !processes_file.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(processes_file)
+        .map_err(|e| HarissaError::io("Failed to open processes file", e))?;
+
+    let events = events::read(harissa_dir)?;
+    let app_ids = app_ids::load(harissa_dir)?;
+
+    // Collect the applications first so cgroup CPU usage can be sampled once for all of them
+    let mut apps = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| HarissaError::io("Failed to read line", e))?;
+        let parts: Vec<&str> = line.split(',').collect();
+
+        if parts.len() < 3 {
+            continue;
+        }
+
+        let mut status = AppStatus {
+            id: app_ids.id_of(parts[1]),
+            pid: parts[0].to_string(),
+            name: parts[1].to_string(),
+            command: parts[2..].join(","),
+            state: AppState::Errored,
+            cpu_percent: 0.0,
+            memory_bytes: 0,
+            memory_percent: None,
+            uptime: Duration::ZERO,
+            restarts: restarts_since_start(&events, parts[1]),
+        };
+
+        // A process that reuses the PID of an exited application is not that application
+        let process = if process_identity::is_running(harissa_dir, &status.name, &status.pid) { ps_status(&status.pid)? } else { None };
+        let Some(process) = process else {
+            apps.push((status, None));
+            continue;
+        };
+
+        status.state = if process.stat.starts_with('T') { AppState::Paused } else { AppState::Online };
+        status.uptime = Duration::from_secs(process.elapsed_secs);
+        status.cpu_percent = process.cpu_percent;
+        status.memory_bytes = process.rss_kib * 1024;
+        status.memory_percent = Some(process.memory_percent);
+
+        // Apps in their own cgroup get exact accounting from it
+        let usage = cgroup::app_cgroup(harissa_dir, &status.name)
+            .and_then(|app_cgroup| cgroup::read_usage(&app_cgroup).map(|usage| (app_cgroup, usage)));
+
+        apps.push((status, usage));
+    }
+
+    let sample_start = Instant::now();
+    if apps.iter().any(|(_, usage)| usage.is_some()) {
+        std::thread::sleep(CPU_SAMPLE_INTERVAL);
+    }
+    let elapsed_usec = sample_start.elapsed().as_micros().max(1) as f64;
+
+    let statuses = apps.into_iter()
+        .map(|(mut status, usage)| {
+            let current = usage.as_ref()
+                .and_then(|(app_cgroup, first)| cgroup::read_usage(app_cgroup).map(|current| (*first, current)));
+
+            if let Some((first, current)) = current {
+                let cpu_usec = current.cpu_usage_usec.saturating_sub(first.cpu_usage_usec) as f64;
+                status.cpu_percent = cpu_usec / elapsed_usec * 100.0;
+                status.memory_bytes = current.memory_current;
+                status.memory_percent = None;
+            }
+            status
+        })
+        .collect();
+
+    Ok(statuses)
+}
+
+/// What ps reports about a live process
+struct PsStatus {
+    stat: String,
+    elapsed_secs: u64,
+    cpu_percent: f64,
+    memory_percent: f64,
+    rss_kib: u64,
+}
+
+/// Query ps for a process; `None` if it is not running (zombies count as not running)
+fn ps_status(pid: &str) -> Result<Option<PsStatus>, HarissaError> {
+    let output = Command::new("ps")
+        .args(["-p", pid, "-o", "stat=,etimes=,pcpu=,pmem=,rss="])
+        .output()
+        .map_err(|e| HarissaError::System(format!("Failed to execute ps command: {}", e)))?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let output = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = output.split_whitespace().collect();
+    if fields.len() < 5 || fields[0].starts_with('Z') {
+        return Ok(None);
+    }
+
+    Ok(Some(PsStatus {
+        stat: fields[0].to_string(),
+        elapsed_secs: fields[1].parse().unwrap_or(0),
+        cpu_percent: fields[2].parse().unwrap_or(0.0),
+        memory_percent: fields[3].parse().unwrap_or(0.0),
+        rss_kib: fields[4].parse().unwrap_or(0),
+    }))
+}
+
+/// Count the reloads of an application since its most recent start
+fn restarts_since_start(events: &[events::Event], name: &str) -> usize {
+    events.iter()
+        .rev()
+        .filter(|event| event.app == name)
+        .take_while(|event| event.kind != EventKind::Start)
+        .filter(|event| event.kind == EventKind::Reload)
+        .count()
+}
@@ -0,0 +1,373 @@
+// Per-application cgroup v2 placement, resource limits and accounting
+use std::fs::{self, OpenOptions};
+use std::os::fd::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::env;
+
+const CGROUP_MOUNT: &str = "/sys/fs/cgroup";
+const HARISSA_CGROUP: &str = "harissa";
+const CONTROLLERS: [&str; 4] = ["cpu", "memory", "pids", "io"];
+
+/// Resource limit options accepted by `start`
+pub const LIMIT_OPTIONS: [&str; 4] = ["--cpu-quota", "--memory-max", "--pids-max", "--io-weight"];
+
+/// Resource limits for an application's cgroup, stored in the kernel's file formats
+#[derive(Debug, Clone, Default)]
+pub struct CgroupLimits {
+    pub cpu_max: Option<String>,
+    pub memory_max: Option<String>,
+    pub pids_max: Option<String>,
+    pub io_weight: Option<String>,
+}
+
+impl CgroupLimits {
+    /// Check whether no limits were requested
+    pub fn is_empty(&self) -> bool {
+        self.cpu_max.is_none() && self.memory_max.is_none() && self.pids_max.is_none() && self.io_weight.is_none()
+    }
+
+    /// Set a limit from one of the `LIMIT_OPTIONS` command-line flags
+    pub fn set_option(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match option {
+            "--cpu-quota" => self.cpu_max = Some(parse_cpu_quota(value)?),
+            "--memory-max" => self.memory_max = Some(parse_bytes(value)?),
+            "--pids-max" => {
+                if value != "max" {
+                    value.parse::<u64>().map_err(|_| format!("Invalid --pids-max '{}': expected a number or 'max'", value))?;
+                }
+                self.pids_max = Some(value.to_string());
+            },
+            "--io-weight" => {
+                let weight: u32 = value.parse().map_err(|_| format!("Invalid --io-weight '{}': expected a number", value))?;
+                if !(1..=10000).contains(&weight) {
+                    return Err(format!("Invalid --io-weight '{}': must be between 1 and 10000", value));
+                }
+                self.io_weight = Some(weight.to_string());
+            },
+            _ => return Err(format!("Unknown resource limit option: {}", option)),
+        }
+        Ok(())
+    }
+
+    /// The limits as (option, value) pairs, in the form accepted by `set_option`
+    pub fn to_options(&self) -> Vec<(&'static str, String)> {
+        let mut options = Vec::new();
+        if let Some(cpu_max) = &self.cpu_max {
+            let quota = cpu_max.split_whitespace().next().unwrap_or("max");
+            let value = match quota.parse::<f64>() {
+                Ok(quota) => format!("{}%", quota / 1000.0),
+                Err(_) => "max".to_string(),
+            };
+            options.push(("--cpu-quota", value));
+        }
+        if let Some(memory_max) = &self.memory_max {
+            options.push(("--memory-max", memory_max.clone()));
+        }
+        if let Some(pids_max) = &self.pids_max {
+            options.push(("--pids-max", pids_max.clone()));
+        }
+        if let Some(io_weight) = &self.io_weight {
+            options.push(("--io-weight", io_weight.clone()));
+        }
+        options
+    }
+}
+
+/// CPU and memory usage read from an application's cgroup
+#[derive(Debug, Clone, Copy)]
+pub struct CgroupUsage {
+    pub cpu_usage_usec: u64,
+    pub memory_current: u64,
+}
+
+/// Convert "50%" (of one CPU) or "max" into a cpu.max value
+fn parse_cpu_quota(value: &str) -> Result<String, String> {
+    if value == "max" {
+        return Ok("max 100000".to_string());
+    }
+
+    let percent: f64 = value.trim_end_matches('%').parse()
+        .map_err(|_| format!("Invalid --cpu-quota '{}': expected a percentage such as 50% or 200%", value))?;
+    // "nan" and "inf" parse as floats too
+    if !percent.is_finite() || percent <= 0.0 {
+        return Err(format!("Invalid --cpu-quota '{}': must be greater than zero", value));
+    }
+
+    // Quota per 100ms period; the kernel rejects quotas below 1ms
+    let quota = ((percent * 1000.0) as u64).max(1000);
+    Ok(format!("{} 100000", quota))
+}
+
+/// Convert sizes such as "512M", "2G" or "max" into a byte count
+fn parse_bytes(value: &str) -> Result<String, String> {
+    if value == "max" {
+        return Ok(value.to_string());
+    }
+
+    let upper = value.to_uppercase();
+    let digits = upper.trim_end_matches('B').trim_end_matches('I');
+    let (number, multiplier) = match digits.chars().last() {
+        Some('K') => (&digits[..digits.len() - 1], 1u64 << 10),
+        Some('M') => (&digits[..digits.len() - 1], 1u64 << 20),
+        Some('G') => (&digits[..digits.len() - 1], 1u64 << 30),
+        Some('T') => (&digits[..digits.len() - 1], 1u64 << 40),
+        _ => (digits, 1),
+    };
+
+    let number: f64 = number.parse()
+        .map_err(|_| format!("Invalid --memory-max '{}': expected a size such as 512M or 2G", value))?;
+    let bytes = number * multiplier as f64;
+    // A cast would turn negative sizes into 0, which the kernel takes as a limit of no memory at all
+    if !bytes.is_finite() || bytes < 1.0 || bytes >= u64::MAX as f64 {
+        return Err(format!("Invalid --memory-max '{}': must be at least one byte and below 16384T", value));
+    }
+
+    Ok((bytes as u64).to_string())
+}
+
+/// Format a byte count for display
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Find the cgroup under which harissa creates per-app cgroups.
+///
+/// Root uses the top of the hierarchy; other users need a delegated subtree,
+/// which systemd provides as `user@<uid>.service`. `HARISSA_CGROUP_ROOT` overrides both.
+fn harissa_cgroup_root() -> Result<PathBuf, String> {
+    if let Ok(root) = env::var("HARISSA_CGROUP_ROOT") {
+        return Ok(PathBuf::from(root));
+    }
+
+    if !Path::new(CGROUP_MOUNT).join("cgroup.controllers").exists() {
+        return Err("cgroup v2 is not mounted at /sys/fs/cgroup".to_string());
+    }
+
+    // SAFETY: geteuid has no preconditions and cannot fail
+    if unsafe { libc::geteuid() } == 0 {
+        return Ok(Path::new(CGROUP_MOUNT).join(HARISSA_CGROUP));
+    }
+
+    let own_cgroup = fs::read_to_string("/proc/self/cgroup")
+        .map_err(|e| format!("Failed to read /proc/self/cgroup: {}", e))?;
+    let own_path = own_cgroup.lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .ok_or("Process is not in a cgroup v2 hierarchy")?;
+
+    let mut delegated = PathBuf::from(CGROUP_MOUNT);
+    for component in own_path.split('/').filter(|c| !c.is_empty()) {
+        delegated.push(component);
+        if component.starts_with("user@") && component.ends_with(".service") {
+            return Ok(delegated.join(HARISSA_CGROUP));
+        }
+    }
+
+    Err("no delegated cgroup subtree found for this user".to_string())
+}
+
+/// Name of the cgroup holding the applications of a harissa home, so that homes running
+/// applications with the same name keep them apart: `home-` and a hash of the home's canonical path.
+/// FNV-1a is used because its values, unlike those of std's hasher, never change between releases.
+fn home_cgroup_name(harissa_dir: &Path) -> String {
+    let home = fs::canonicalize(harissa_dir).unwrap_or_else(|_| harissa_dir.to_path_buf());
+    let hash = home.as_os_str().as_encoded_bytes().iter()
+        .fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3));
+    format!("home-{:08x}", hash as u32 ^ (hash >> 32) as u32)
+}
+
+/// Name of an application's cgroup. The '/' of namespaced names is escaped, and so is '%', so no
+/// two applications share a cgroup: `qa/api` becomes `qa%2Fapi` and `qa%2Fapi` becomes `qa%252Fapi`.
+fn cgroup_name(name: &str) -> String {
+    name.replace('%', "%25").replace('/', "%2F")
+}
+
+/// Path of an application's cgroup (which may not exist)
+fn app_cgroup_path(harissa_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    Ok(harissa_cgroup_root()?.join(home_cgroup_name(harissa_dir)).join(cgroup_name(name)))
+}
+
+/// Enable the controllers harissa uses for the children of a cgroup, ignoring unavailable ones
+fn enable_controllers(cgroup: &Path) {
+    for controller in CONTROLLERS {
+        let _ = fs::write(cgroup.join("cgroup.subtree_control"), format!("+{}", controller));
+    }
+}
+
+/// Create (or reset) an application's cgroup and apply its limits
+pub fn create_app_cgroup(harissa_dir: &Path, name: &str, limits: &CgroupLimits) -> Result<PathBuf, String> {
+    let root = harissa_cgroup_root()?;
+    let home_cgroup = root.join(home_cgroup_name(harissa_dir));
+
+    if let Some(parent) = root.parent() {
+        enable_controllers(parent);
+    }
+    fs::create_dir_all(&home_cgroup)
+        .map_err(|e| format!("Failed to create cgroup {}: {}", home_cgroup.display(), e))?;
+    enable_controllers(&root);
+    enable_controllers(&home_cgroup);
+
+    let cgroup = app_cgroup_path(harissa_dir, name)?;
+    if !cgroup.exists() {
+        fs::create_dir(&cgroup)
+            .map_err(|e| format!("Failed to create cgroup {}: {}", cgroup.display(), e))?;
+    }
+
+    // Unset limits are written too so a reused cgroup does not keep stale values
+    let settings = [
+        ("cpu.max", limits.cpu_max.as_deref().unwrap_or("max 100000"), limits.cpu_max.is_some()),
+        ("memory.max", limits.memory_max.as_deref().unwrap_or("max"), limits.memory_max.is_some()),
+        ("pids.max", limits.pids_max.as_deref().unwrap_or("max"), limits.pids_max.is_some()),
+        ("io.weight", limits.io_weight.as_deref().unwrap_or("default 100"), limits.io_weight.is_some()),
+    ];
+
+    for (file, value, requested) in settings {
+        let result = fs::write(cgroup.join(file), value);
+        if let Err(e) = result
+            && requested
+        {
+            return Err(format!("Failed to set {} (is the controller delegated?): {}", file, e));
+        }
+    }
+
+    Ok(cgroup)
+}
+
+/// Make the command's process join a cgroup between fork and exec, so neither it nor anything it
+/// starts ever runs outside of it. The kernel may still refuse the move; check with `contains`.
+pub fn join_on_spawn(command: &mut Command, cgroup: &Path) -> Result<(), String> {
+    // Opened here because the child may not allocate between fork and exec
+    let procs_path = cgroup.join("cgroup.procs");
+    let procs = OpenOptions::new()
+        .write(true)
+        .open(&procs_path)
+        .map_err(|e| format!("Failed to open {}: {}", procs_path.display(), e))?;
+
+    // SAFETY: the closure only writes to a descriptor that stays open until the command is dropped
+    unsafe {
+        command.pre_exec(move || {
+            // "0" stands for the writing process
+            libc::write(procs.as_raw_fd(), b"0".as_ptr() as *const libc::c_void, 1);
+            Ok(())
+        });
+    }
+
+    Ok(())
+}
+
+/// Check whether a process is in a cgroup
+pub fn contains(cgroup: &Path, pid: u32) -> bool {
+    let pid = pid.to_string();
+    fs::read_to_string(cgroup.join("cgroup.procs"))
+        .is_ok_and(|procs| procs.lines().any(|line| line.trim() == pid))
+}
+
+/// Existing cgroup of an application, if it has one
+pub fn app_cgroup(harissa_dir: &Path, name: &str) -> Option<PathBuf> {
+    let cgroup = app_cgroup_path(harissa_dir, name).ok()?;
+    if cgroup.join("cgroup.procs").exists() { Some(cgroup) } else { None }
+}
+
+/// Remove an application's cgroup, and that of its home once it is empty. Fails while processes
+/// are still inside, so this is best effort.
+pub fn remove_app_cgroup(harissa_dir: &Path, name: &str) {
+    if let Some(cgroup) = app_cgroup(harissa_dir, name) {
+        let _ = fs::remove_dir(&cgroup);
+        if let Some(home_cgroup) = cgroup.parent() {
+            let _ = fs::remove_dir(home_cgroup);
+        }
+    }
+}
+
+/// Read CPU and memory accounting from a cgroup
+pub fn read_usage(cgroup: &Path) -> Option<CgroupUsage> {
+    let cpu_stat = fs::read_to_string(cgroup.join("cpu.stat")).ok()?;
+    let cpu_usage_usec = cpu_stat.lines()
+        .find_map(|line| line.strip_prefix("usage_usec "))
+        .and_then(|value| value.trim().parse().ok())?;
+
+    let memory_current = fs::read_to_string(cgroup.join("memory.current")).ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(0);
+
+    Some(CgroupUsage { cpu_usage_usec, memory_current })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn homes_have_cgroups_of_their_own() {
+        let home = env::temp_dir();
+        let name = home_cgroup_name(&home);
+        assert!(name.starts_with("home-") && name.len() == 13, "{}", name);
+        // The same home given another way
+        assert_eq!(home_cgroup_name(&home.join(".")), name);
+        assert_ne!(home_cgroup_name(Path::new("/var/lib/harissa")), name);
+        assert_ne!(home_cgroup_name(Path::new("/home/alice/.harissa_apps")), home_cgroup_name(Path::new("/home/bob/.harissa_apps")));
+    }
+
+    #[test]
+    fn parses_cpu_quotas() {
+        let cases = [
+            ("50%", Ok("50000 100000")),
+            ("50", Ok("50000 100000")),
+            ("200%", Ok("200000 100000")),
+            ("2.5%", Ok("2500 100000")),
+            // Below the kernel's minimum of 1ms per period
+            ("0.1%", Ok("1000 100000")),
+            ("max", Ok("max 100000")),
+            ("0%", Err(())),
+            ("-50%", Err(())),
+            ("nan", Err(())),
+            ("inf%", Err(())),
+            ("half", Err(())),
+            ("", Err(())),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_cpu_quota(value).as_deref().map_err(|_| ()), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn parses_sizes() {
+        let cases = [
+            ("4096", Ok("4096")),
+            ("512K", Ok("524288")),
+            ("512M", Ok("536870912")),
+            ("512m", Ok("536870912")),
+            ("512MiB", Ok("536870912")),
+            ("512MB", Ok("536870912")),
+            ("1.5G", Ok("1610612736")),
+            ("2T", Ok("2199023255552")),
+            ("max", Ok("max")),
+            ("0", Err(())),
+            ("-512M", Err(())),
+            ("0.1", Err(())),
+            ("99999999T", Err(())),
+            ("nanM", Err(())),
+            ("512X", Err(())),
+            ("M", Err(())),
+            ("", Err(())),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_bytes(value).as_deref().map_err(|_| ()), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn escapes_namespaced_names() {
+        assert_eq!(cgroup_name("api"), "api");
+        assert_eq!(cgroup_name("qa/api"), "qa%2Fapi");
+        assert_eq!(cgroup_name("qa%2Fapi"), "qa%252Fapi");
+    }
+}
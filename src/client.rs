@@ -0,0 +1,778 @@
+// Typed API for managing applications from Rust code, used by the CLI and the API servers
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use regex::Regex;
+use crate::adopt;
+use crate::app_ids::{self, AppIds};
+use crate::app_spec::{self, AppSpec};
+use crate::app_status::{self, AppStatus};
+use crate::cgroup;
+use crate::credentials;
+use crate::dependencies::{self, StartTracker};
+use crate::error::HarissaError;
+use crate::events::{self, EventKind};
+use crate::glob;
+use crate::home;
+use crate::hooks::{self, Hook};
+use crate::launcher::{self, LogMode};
+use crate::logs::{LogLine, LogStream, LogSubscription};
+use crate::namespace;
+use crate::notice::Notice;
+use crate::process_identity;
+use crate::signals;
+use crate::stopped_apps;
+use crate::supervisor::{self, CronSchedule, ScheduleKind};
+
+/// Manages the applications tracked in one harissa directory
+#[derive(Clone)]
+pub struct Harissa {
+    dir: PathBuf,
+    /// Only the applications of this namespace are managed; see `in_namespace`
+    namespace: Option<String>,
+    /// Receives warnings and progress; see `with_observer`
+    observer: Option<Arc<dyn Fn(Notice) + Send + Sync>>,
+}
+
+impl fmt::Debug for Harissa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Harissa")
+            .field("dir", &self.dir)
+            .field("namespace", &self.namespace)
+            .field("observer", &self.observer.is_some())
+            .finish()
+    }
+}
+
+/// A started application
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppHandle {
+    /// Stable ID that stays the same across reloads
+    pub id: u32,
+    /// Qualified with the namespace, e.g. `qa/api`
+    pub name: String,
+    pub pid: u32,
+}
+
+/// Identifies tracked applications
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppRef {
+    /// The stable ID shown by `list`; when no app has this ID, the app with this PID
+    Id(u32),
+    Pid(u32),
+    Name(String),
+    /// A wildcard pattern matched against names, e.g. `api-*`
+    Glob(String),
+    /// A regular expression that must match somewhere in the name
+    Regex(String),
+    /// Applications started with `--tag`
+    Tag(String),
+    /// Every tracked application
+    All,
+}
+
+impl AppRef {
+    /// `all`, a regex if the identifier is `/.../`, an ID if it is all digits,
+    /// a glob if it has wildcards, otherwise a name
+    pub fn parse(identifier: &str) -> Self {
+        if identifier == "all" {
+            return AppRef::All;
+        }
+        if let Some(pattern) = identifier.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+            return AppRef::Regex(pattern.to_string());
+        }
+
+        match identifier.parse() {
+            Ok(id) if identifier.chars().all(|c| c.is_ascii_digit()) => AppRef::Id(id),
+            _ if glob::is_pattern(identifier) => AppRef::Glob(identifier.to_string()),
+            _ => AppRef::Name(identifier.to_string()),
+        }
+    }
+}
+
+/// An `AppRef` ready to be matched against many apps, with its regex compiled once
+struct Matcher<'a> {
+    target: &'a AppRef,
+    regex: Option<Regex>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(target: &'a AppRef) -> Result<Self, HarissaError> {
+        let regex = match target {
+            AppRef::Regex(pattern) => Some(Regex::new(pattern)
+                .map_err(|e| HarissaError::Usage(format!("Invalid regular expression /{}/: {}", pattern, e)))?),
+            _ => None,
+        };
+        Ok(Matcher { target, regex })
+    }
+
+    /// Whether the app tracked as `qualified` matches; names and patterns are matched against `name`,
+    /// which leaves out the namespace when selecting within one
+    fn matches(&self, harissa_dir: &Path, pid: &str, qualified: &str, name: &str, app_ids: &AppIds) -> Result<bool, HarissaError> {
+        Ok(match self.target {
+            AppRef::Id(id) => app_ids.id_of(qualified) == Some(*id),
+            AppRef::Pid(target) => pid == target.to_string(),
+            AppRef::Name(target) => name == target,
+            AppRef::Glob(pattern) => glob::matches(pattern, name),
+            AppRef::Regex(_) => self.regex.as_ref().is_some_and(|regex| regex.is_match(name)),
+            AppRef::Tag(tag) => app_spec::load(harissa_dir, qualified)?.tags.contains(tag),
+            AppRef::All => true,
+        })
+    }
+}
+
+impl fmt::Display for AppRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppRef::Id(id) => write!(f, "ID '{}'", id),
+            AppRef::Pid(pid) => write!(f, "PID '{}'", pid),
+            AppRef::Name(name) => write!(f, "name '{}'", name),
+            AppRef::Glob(pattern) => write!(f, "name matching '{}'", pattern),
+            AppRef::Regex(pattern) => write!(f, "name matching /{}/", pattern),
+            AppRef::Tag(tag) => write!(f, "tag '{}'", tag),
+            AppRef::All => f.write_str("any name"),
+        }
+    }
+}
+
+/// What `stop` did with one matching application
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopOutcome {
+    /// The process was terminated
+    Stopped,
+    /// The process had already exited; it is no longer tracked
+    NotRunning,
+    /// Terminating the process failed; it is still tracked
+    Failed(HarissaError),
+}
+
+/// An application matched by `stop`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoppedApp {
+    pub name: String,
+    pub pid: String,
+    pub outcome: StopOutcome,
+    /// Whether a scheduled restart of the application was removed
+    pub removed_schedule: bool,
+}
+
+/// A stopped application started again by `start_stopped`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartedApp {
+    pub name: String,
+    /// The running application, or why it could not be started
+    pub result: Result<AppHandle, HarissaError>,
+}
+
+/// An application sent a signal by `signal`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalledApp {
+    pub name: String,
+    pub pid: String,
+    /// Whether the signal was delivered
+    pub result: Result<(), HarissaError>,
+}
+
+/// An application restarted by `reload`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReloadedApp {
+    pub name: String,
+    pub previous_pid: String,
+    /// The new PID, or why the application could not be started again
+    pub result: Result<u32, HarissaError>,
+}
+
+impl Harissa {
+    /// Use the harissa home (`~/.harissa_apps` unless `HARISSA_HOME` is set), creating it if needed
+    pub fn new() -> Result<Self, HarissaError> {
+        Ok(Harissa { dir: home::harissa_dir()?, namespace: None, observer: None })
+    }
+
+    /// Use another directory, e.g. for tests or a separate set of applications
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Self {
+        Harissa { dir: dir.into(), namespace: None, observer: None }
+    }
+
+    /// Pass warnings and progress to `observer` as they happen, e.g. a hook that failed while the
+    /// application stopped anyway, or waiting for a dependency. Without an observer they are dropped.
+    pub fn with_observer(mut self, observer: impl Fn(Notice) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    fn notify(&self, notice: Notice) {
+        if let Some(observer) = &self.observer {
+            observer(notice);
+        }
+    }
+
+    /// Only manage the applications of one namespace: names are given without the namespace,
+    /// selectors only match its applications and new applications are started in it
+    pub fn in_namespace(mut self, namespace: &str) -> Result<Self, HarissaError> {
+        namespace::validate(namespace).map_err(HarissaError::Usage)?;
+        self.namespace = Some(namespace.to_string());
+        Ok(self)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// The name an application is tracked under, e.g. `qa/api` for `api` in the qa namespace
+    pub fn qualify(&self, name: &str) -> String {
+        namespace::qualify(self.namespace(), name)
+    }
+
+    /// Whether an application tracked under a qualified name belongs to the managed namespace
+    fn manages(&self, qualified: &str) -> bool {
+        self.namespace.is_none() || namespace::split(qualified).0 == self.namespace()
+    }
+
+    /// Start an application in the background and track it.
+    /// Fails if an application with the same name is running; see `replace`.
+    pub fn start(&self, name: &str, spec: &AppSpec) -> Result<AppHandle, HarissaError> {
+        let qualified = self.qualify(name);
+        if launcher::running_app_names(&self.dir)?.contains(&qualified) {
+            return Err(HarissaError::AlreadyExists(format!("An application named '{}' is already running", qualified)));
+        }
+
+        // An entry whose process is gone, e.g. after a crash or a reboot, is replaced and keeps its ID
+        self.replace(name, spec)
+    }
+
+    /// Start an application, stopping a running application with the same name first.
+    /// The new application keeps the ID of the one it replaces.
+    pub fn replace(&self, name: &str, spec: &AppSpec) -> Result<AppHandle, HarissaError> {
+        let qualified = self.qualify(name);
+        self.untrack(&qualified)?;
+
+        // A stopped application keeps its ID if it cannot be replaced
+        let stopped = stopped_apps::load(&self.dir)?.contains(&qualified);
+        let app = self.launch(&qualified, spec).inspect_err(|_| if !stopped { app_ids::release(&self.dir, &qualified) })?;
+        stopped_apps::remove(&self.dir, &qualified)?;
+        Ok(app)
+    }
+
+    /// Stop tracking the entries of an application that is about to be launched again under the
+    /// same qualified name, giving it back its ID
+    fn untrack(&self, qualified: &str) -> Result<(), HarissaError> {
+        let previous_id = app_ids::load(&self.dir)?.id_of(qualified);
+        if self.tracked()?.iter().any(|(_, tracked)| tracked == qualified) {
+            let name = if self.namespace.is_some() { namespace::split(qualified).1 } else { qualified };
+            self.delete(&AppRef::Name(name.to_string()))?;
+        }
+
+        if let Some(id) = previous_id {
+            app_ids::restore(&self.dir, qualified, id)?;
+        }
+        Ok(())
+    }
+
+    /// Launch an application under its qualified name
+    fn launch(&self, name: &str, spec: &AppSpec) -> Result<AppHandle, HarissaError> {
+        validate(name, spec)?;
+        let app_namespace = namespace::split(name).0;
+        if spec.argv.is_empty() {
+            return Err(HarissaError::Usage("No command specified".to_string()));
+        }
+        if let Some(cron) = &spec.cron_restart {
+            CronSchedule::parse(cron).map_err(HarissaError::Usage)?;
+        }
+        if (spec.user.is_some() || spec.group.is_some()) && !credentials::is_root() {
+            return Err(HarissaError::PermissionDenied("--user and --group can only be used when harissa runs as root".to_string()));
+        }
+
+        // Dependencies are looked up in the application's own namespace
+        let mut spec = spec.clone();
+        for dependency in spec.after.iter_mut().filter(|dependency| !dependency.contains(namespace::SEPARATOR)) {
+            *dependency = namespace::qualify(app_namespace, dependency);
+        }
+        let spec = &spec;
+
+        self.create_namespace_dirs(app_namespace)?;
+        dependencies::check_running(&self.dir, name, spec)?;
+        let (pid, id) = launcher::start_app(&self.dir, name, spec, &|notice| self.notify(notice))?;
+
+        Ok(AppHandle { id, name: name.to_string(), pid })
+    }
+
+    /// Track a process that was started outside harissa under a name, with the spec read from
+    /// `/proc` so that `reload` can relaunch it
+    pub fn adopt(&self, pid: u32, name: &str, tags: &[String]) -> Result<AppHandle, HarissaError> {
+        let qualified = self.qualify(name);
+        let mut spec = adopt::capture_spec(pid)?;
+        spec.tags = tags.to_vec();
+        validate(&qualified, &spec)?;
+
+        if let Some((_, tracked)) = self.tracked()?.into_iter().find(|(tracked_pid, tracked)| {
+            *tracked_pid == pid.to_string() && process_identity::is_running(&self.dir, tracked, tracked_pid)
+        }) {
+            return Err(HarissaError::AlreadyExists(format!("Process {} is already tracked as '{}'", pid, tracked)));
+        }
+        if launcher::running_app_names(&self.dir)?.contains(&qualified) {
+            return Err(HarissaError::AlreadyExists(format!("An application named '{}' is already running", qualified)));
+        }
+        // An entry whose process is gone would otherwise be tracked twice; its ID is kept
+        self.untrack(&qualified)?;
+
+        self.create_namespace_dirs(namespace::split(&qualified).0)?;
+        // Without its identity the entry would be taken for whatever process gets the PID next
+        process_identity::record(&self.dir, &qualified, pid)?;
+        if let Err(e) = launcher::track_process(&self.dir, pid, &qualified, &spec.command_line()) {
+            process_identity::forget(&self.dir, &qualified);
+            return Err(e);
+        }
+        let id = app_ids::assign(&self.dir, &qualified)?;
+        app_spec::save(&self.dir, &qualified, &spec)?;
+        stopped_apps::remove(&self.dir, &qualified)?;
+        events::record(&self.dir, EventKind::Start, &qualified, Some(pid), &format!("adopted {}", spec.command_line()));
+
+        Ok(AppHandle { id, name: qualified, pid })
+    }
+
+    /// Create the directories of a namespace for specs and logs
+    fn create_namespace_dirs(&self, app_namespace: Option<&str>) -> Result<(), HarissaError> {
+        if let Some(app_namespace) = app_namespace {
+            home::create_dir(&self.dir.join(app_namespace))?;
+            home::create_dir(&home::log_dir(&self.dir).join(app_namespace))?;
+        }
+        Ok(())
+    }
+
+    /// The ID of a tracked application
+    pub fn id_of(&self, name: &str) -> Result<Option<u32>, HarissaError> {
+        Ok(app_ids::load(&self.dir)?.id_of(&self.qualify(name)))
+    }
+
+    /// Names of the tracked applications that the target matches
+    pub fn names(&self, target: &AppRef) -> Result<Vec<String>, HarissaError> {
+        let mut names: Vec<String> = self.select(target)?.into_iter().map(|(_, name)| name).collect();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Names of the stopped applications that the target matches
+    pub fn stopped_names(&self, target: &AppRef) -> Result<Vec<String>, HarissaError> {
+        let app_ids = app_ids::load(&self.dir)?;
+        let matcher = Matcher::new(target)?;
+
+        let mut selected = Vec::new();
+        for qualified in stopped_apps::load(&self.dir)?.into_iter().filter(|qualified| self.manages(qualified)) {
+            let name = if self.namespace.is_some() { namespace::split(&qualified).1 } else { &qualified };
+            if matcher.matches(&self.dir, "", &qualified, name, &app_ids)? {
+                selected.push(qualified);
+            }
+        }
+        Ok(selected)
+    }
+
+    /// (PID, name) of every entry in processes.csv
+    fn tracked(&self) -> Result<Vec<(String, String)>, HarissaError> {
+        let processes_file = self.dir.join("processes.csv");
+        if !processes_file.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file = File::open(&processes_file)
+            .map_err(|e| HarissaError::io("Failed to open processes file", e))?;
+
+        let mut tracked = Vec::new();
+        for line in io::BufReader::new(file).lines() {
+            let line = line.map_err(|e| HarissaError::io("Failed to read line", e))?;
+            let parts: Vec<&str> = line.split(',').collect();
+            if parts.len() >= 3 {
+                tracked.push((parts[0].to_string(), parts[1].to_string()));
+            }
+        }
+
+        Ok(tracked)
+    }
+
+    /// (PID, qualified name) of the entries in processes.csv that the target matches
+    fn select(&self, target: &AppRef) -> Result<Vec<(String, String)>, HarissaError> {
+        let tracked = self.tracked()?;
+        let app_ids = app_ids::load(&self.dir)?;
+        let matcher = Matcher::new(target)?;
+
+        let mut selected = Vec::new();
+        for (pid, qualified) in tracked.into_iter().filter(|(_, qualified)| self.manages(qualified)) {
+            let name = if self.namespace.is_some() { namespace::split(&qualified).1 } else { &qualified };
+            if matcher.matches(&self.dir, &pid, &qualified, name, &app_ids)? {
+                selected.push((pid, qualified));
+            }
+        }
+
+        // Numbers on the command line used to be PIDs, so they still are when no app has that ID
+        if let (true, AppRef::Id(id)) = (selected.is_empty(), target) {
+            return self.select(&AppRef::Pid(*id));
+        }
+
+        Ok(selected)
+    }
+
+    /// The status of every tracked application, including ones whose process is gone
+    pub fn list(&self) -> Result<Vec<AppStatus>, HarissaError> {
+        let apps = app_status::collect(&self.dir)?;
+        Ok(apps.into_iter().filter(|app| self.manages(&app.name)).collect())
+    }
+
+    /// Terminate the matching applications but keep their spec and ID, so `start_stopped` can
+    /// start them again. Applications whose process is gone are marked as stopped too.
+    pub fn stop(&self, target: &AppRef) -> Result<Vec<StoppedApp>, HarissaError> {
+        self.terminate(target, true)
+    }
+
+    /// Terminate the matching applications and stop tracking them, and forget the matching
+    /// stopped applications
+    pub fn delete(&self, target: &AppRef) -> Result<Vec<StoppedApp>, HarissaError> {
+        let stopped_names = self.stopped_names(target)?;
+        let mut deleted = match self.terminate(target, false) {
+            Err(HarissaError::NotFound(_)) if !stopped_names.is_empty() => Vec::new(),
+            result => result?,
+        };
+
+        for name in stopped_names {
+            stopped_apps::remove(&self.dir, &name)?;
+            self.forget(&name);
+            deleted.push(StoppedApp { name, pid: String::new(), outcome: StopOutcome::NotRunning, removed_schedule: false });
+        }
+
+        Ok(deleted)
+    }
+
+    /// Terminate the matching entries of processes.csv, keeping them as stopped applications or not
+    fn terminate(&self, target: &AppRef, keep: bool) -> Result<Vec<StoppedApp>, HarissaError> {
+        let processes_file = self.dir.join("processes.csv");
+        if !processes_file.exists() {
+            return Err(HarissaError::NotFound("No applications are currently running.".to_string()));
+        }
+
+        let file = File::open(&processes_file)
+            .map_err(|e| HarissaError::io("Failed to open processes file", e))?;
+        let lines = io::BufReader::new(file).lines().collect::<Result<Vec<_>, _>>()
+            .map_err(|e| HarissaError::io("Failed to read line", e))?;
+
+        let selected = self.select(target)?;
+
+        // Lines of the selected applications and their specs. Invalid lines and the entries of
+        // other applications are kept, including ones that have exited, so they are listed as errored.
+        let mut matched = Vec::new();
+        let mut apps = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            let parts: Vec<&str> = line.split(',').collect();
+            if parts.len() < 3 || !selected.iter().any(|(pid, name)| pid == parts[0] && name == parts[1]) {
+                continue;
+            }
+
+            // Root still stops applications whose spec it does not trust, only without their hooks
+            let spec = match app_spec::load_trusted(&self.dir, parts[1]) {
+                Err(HarissaError::PermissionDenied(message)) => {
+                    self.notify(Notice::Warning(format!("{}; skipping the stop hooks of {}", message, parts[1])));
+                    AppSpec::default()
+                },
+                result => result?,
+            };
+            matched.push((index, parts[0].to_string()));
+            apps.push((parts[1].to_string(), spec));
+        }
+
+        // Dependents stop before the applications they run after
+        let order = dependencies::stop_order(&apps);
+        let mut removed = vec![false; lines.len()];
+        let mut stopped = Vec::new();
+
+        let notify = |notice| self.notify(notice);
+        for (position, &app) in order.iter().enumerate() {
+            let ((index, pid), (name, spec)) = (&matched[app], &apps[app]);
+
+            let outcome = if process_identity::is_running(&self.dir, name, pid) {
+                hooks::mark_exit_handled(&self.dir, name, pid);
+                hooks::run_or_warn(&self.dir, name, spec, Hook::PreStop, pid.parse().ok(), &notify);
+
+                match terminate_process(pid) {
+                    Ok(()) => {
+                        events::record(&self.dir, EventKind::Stop, name, pid.parse().ok(), "terminated by kill");
+                        hooks::run_or_warn(&self.dir, name, spec, Hook::PostStop, pid.parse().ok(), &notify);
+
+                        let runs_after_later_app = order[position + 1..].iter().any(|&later| spec.after.contains(&apps[later].0));
+                        if runs_after_later_app && !pid.parse().is_ok_and(dependencies::wait_exited) {
+                            self.notify(Notice::Warning(format!("{} has not exited yet, stopping the applications it runs after anyway", name)));
+                        }
+                        removed[*index] = true;
+                        StopOutcome::Stopped
+                    },
+                    Err(error) => StopOutcome::Failed(error),
+                }
+            } else {
+                removed[*index] = true;
+                StopOutcome::NotRunning
+            };
+
+            stopped.push(StoppedApp { name: name.clone(), pid: pid.clone(), outcome, removed_schedule: false });
+        }
+
+        let processes: Vec<String> = lines.into_iter().zip(removed).filter(|(_, removed)| !removed).map(|(line, _)| line).collect();
+        write_processes(&processes_file, &processes)?;
+
+        if stopped.is_empty() {
+            return Err(HarissaError::NotFound(format!("No running application found with {}", target)));
+        }
+
+        // Remove the cgroup of stopped applications (best effort, the process may still be exiting)
+        // and make sure a scheduled restart does not bring them back. Starting a stopped
+        // application again registers its schedule from the spec.
+        for app in stopped.iter_mut().filter(|app| !matches!(app.outcome, StopOutcome::Failed(_))) {
+            cgroup::remove_app_cgroup(&self.dir, &app.name);
+            hooks::clear_exit_marker(&self.dir, &app.name);
+            app.removed_schedule = supervisor::remove_schedules(&self.dir, ScheduleKind::Restart, &app.name)? > 0;
+            if keep {
+                stopped_apps::add(&self.dir, &app.name)?;
+            } else {
+                self.forget(&app.name);
+            }
+        }
+
+        Ok(stopped)
+    }
+
+    /// Forget the spec and ID of an application that is no longer tracked
+    fn forget(&self, name: &str) {
+        app_spec::remove(&self.dir, name);
+        app_ids::release(&self.dir, name);
+        process_identity::forget(&self.dir, name);
+    }
+
+    /// Start the matching stopped applications again from their specs, in dependency order
+    pub fn start_stopped(&self, target: &AppRef) -> Result<Vec<StartedApp>, HarissaError> {
+        let names = self.stopped_names(target)?;
+        if names.is_empty() {
+            return Err(HarissaError::NotFound(format!("No stopped application found with {}", target)));
+        }
+
+        let apps = names.into_iter()
+            .map(|name| app_spec::load_trusted(&self.dir, &name).map(|spec| (name, spec)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut started = Vec::new();
+        for index in dependencies::start_order(&apps)? {
+            let (name, spec) = &apps[index];
+            let result = self.launch(name, spec);
+            if result.is_ok() {
+                stopped_apps::remove(&self.dir, name)?;
+            }
+            started.push(StartedApp { name: name.clone(), result });
+        }
+
+        Ok(started)
+    }
+
+    /// Restart the matching applications, or all of them, with the spec they were started with.
+    /// Apps are stopped in reverse dependency order and started again in dependency order.
+    pub fn reload(&self, target: Option<&AppRef>) -> Result<Vec<ReloadedApp>, HarissaError> {
+        // All applications of a namespace are the ones it selects
+        let target = target.or(self.namespace.is_some().then_some(&AppRef::All));
+
+        let processes_file = self.dir.join("processes.csv");
+        if !processes_file.exists() {
+            return Err(HarissaError::NotFound("No applications are currently running.".to_string()));
+        }
+
+        let file = File::open(&processes_file)
+            .map_err(|e| HarissaError::io("Failed to open processes file", e))?;
+
+        // Find the applications to reload; the others keep their entry unchanged
+        let selected = match target {
+            Some(target) => Some(self.select(target)?),
+            None => None,
+        };
+        let mut processes = Vec::new();
+        let mut targets = Vec::new();
+        for line in io::BufReader::new(file).lines() {
+            let line = line.map_err(|e| HarissaError::io("Failed to read line", e))?;
+            let parts: Vec<&str> = line.split(',').collect();
+
+            if parts.len() < 3 {
+                processes.push(line);
+                continue;
+            }
+
+            let (pid, name) = (parts[0], parts[1]);
+            let combined_command = parts[2..].join(",");
+            let command_parts: Vec<&str> = combined_command.split_whitespace().collect();
+
+            let is_selected = selected.as_ref()
+                .is_none_or(|selected| selected.iter().any(|(selected_pid, selected_name)| selected_pid == pid && selected_name == name));
+            if command_parts.is_empty() || !is_selected {
+                processes.push(line);
+                continue;
+            }
+
+            // Relaunch with the spec it was started with; apps started by older versions
+            // only have the command stored in processes.csv
+            let mut spec = app_spec::load_trusted(&self.dir, name)?;
+            if spec.argv.is_empty() {
+                app_spec::check_trusted(&processes_file)?;
+                spec.argv = command_parts.iter().map(|part| part.to_string()).collect();
+            }
+
+            targets.push((pid.to_string(), name.to_string(), combined_command, spec));
+        }
+
+        if targets.is_empty() {
+            return Err(HarissaError::NotFound(match target {
+                Some(target) => format!("No running application found with {}", target),
+                None => "No running applications found to reload.".to_string(),
+            }));
+        }
+
+        // Dependencies that are not reloaded must be running, which is checked before stopping anything
+        let apps: Vec<(String, AppSpec)> = targets.iter().map(|(_, name, _, spec)| (name.clone(), spec.clone())).collect();
+        let order = dependencies::start_order(&apps)?;
+        let running = launcher::running_app_names(&self.dir)?;
+        let mut tracker = StartTracker::default();
+
+        for (name, spec) in &apps {
+            for dependency in &spec.after {
+                if apps.iter().any(|(app_name, _)| app_name == dependency) {
+                    continue;
+                }
+                if !running.contains(dependency) {
+                    return Err(HarissaError::Failed(format!("Cannot reload {}: it starts after {}, which is not running", name, dependency)));
+                }
+                tracker.running(dependency);
+            }
+        }
+
+        let notify = |notice| self.notify(notice);
+        for &index in order.iter().rev() {
+            let (pid, name, _, spec) = &targets[index];
+            if !process_identity::is_running(&self.dir, name, pid) {
+                continue;
+            }
+
+            hooks::mark_exit_handled(&self.dir, name, pid);
+            hooks::run_or_warn(&self.dir, name, spec, Hook::PreStop, pid.parse().ok(), &notify);
+
+            if let Err(error) = terminate_process(pid) {
+                self.notify(Notice::Warning(format!("Failed to kill process {} ({}): {}", name, pid, error)));
+            }
+
+            // Give it a moment to shut down
+            std::thread::sleep(std::time::Duration::from_millis(500));
+
+            hooks::run_or_warn(&self.dir, name, spec, Hook::PostStop, pid.parse().ok(), &notify);
+        }
+
+        let mut reloaded = Vec::new();
+        for index in order {
+            let (previous_pid, name, command, spec) = &targets[index];
+
+            let result = tracker.wait_for_dependencies(spec, &notify)
+                .and_then(|_| hooks::run(&self.dir, name, spec, Hook::PreStart, None))
+                .and_then(|_| launcher::spawn_app(&self.dir, name, spec, LogMode::Append, &notify));
+
+            match &result {
+                Ok(new_pid) => {
+                    processes.push(format!("{},{},{}", new_pid, name, command));
+                    tracker.started(name, *new_pid);
+                    events::record(&self.dir, EventKind::Reload, name, Some(*new_pid), &format!("previous PID {}", previous_pid));
+                    hooks::run_or_warn(&self.dir, name, spec, Hook::PostStart, Some(*new_pid), &notify);
+                },
+                Err(_) => {
+                    // Keep tracking the app so it can be reloaded once the problem is fixed
+                    processes.push(format!("{},{},{}", previous_pid, name, command));
+                    tracker.failed(name);
+                },
+            }
+
+            reloaded.push(ReloadedApp { name: name.clone(), previous_pid: previous_pid.clone(), result });
+        }
+
+        write_processes(&processes_file, &processes)?;
+
+        Ok(reloaded)
+    }
+
+    /// Send a signal (e.g. `libc::SIGHUP`) to the matching running applications, or with `group`
+    /// to every process in their process groups
+    pub fn signal(&self, target: &AppRef, signal: libc::c_int, group: bool) -> Result<Vec<SignalledApp>, HarissaError> {
+        let mut signalled = Vec::new();
+        for (pid, name) in self.select(target)? {
+            if !process_identity::is_running(&self.dir, &name, &pid) {
+                continue;
+            }
+
+            let result = pid.parse()
+                .map_err(|_| HarissaError::Failed(format!("Invalid PID '{}' in processes file", pid)))
+                .and_then(|pid| if group { signals::send_to_group(pid, signal) } else { signals::send(pid, signal) });
+            if result.is_ok() {
+                let detail = format!("{}{}", signals::name(signal), if group { " to its process group" } else { "" });
+                events::record(&self.dir, EventKind::Signal, &name, pid.parse().ok(), &detail);
+            }
+
+            signalled.push(SignalledApp { name, pid, result });
+        }
+
+        if signalled.is_empty() {
+            return Err(HarissaError::NotFound(format!("No running application found with {}", target)));
+        }
+        Ok(signalled)
+    }
+
+    /// The last `lines` lines of an application's logs
+    pub fn logs(&self, name: &str, stream: LogStream, lines: usize) -> Result<Vec<LogLine>, HarissaError> {
+        Ok(LogSubscription::new(&self.dir, &self.qualify(name), stream, lines).map_err(HarissaError::NotFound)?.recent())
+    }
+
+    /// Send the last `lines` lines of an application's logs and then every new line to `on_line`,
+    /// until it returns false
+    pub fn follow_logs(&self, name: &str, stream: LogStream, lines: usize, on_line: impl FnMut(&LogLine) -> bool) -> Result<(), HarissaError> {
+        LogSubscription::new(&self.dir, &self.qualify(name), stream, lines).map_err(HarissaError::NotFound)?.follow_logs(on_line);
+        Ok(())
+    }
+}
+
+/// Check the name and tags of an application, given by its qualified name
+fn validate(name: &str, spec: &AppSpec) -> Result<(), HarissaError> {
+    let (app_namespace, short_name) = namespace::split(name);
+    if let Some(app_namespace) = app_namespace {
+        namespace::validate(app_namespace).map_err(HarissaError::Usage)?;
+    }
+    // Such names would be taken as IDs, selectors or paths by kill and reload
+    if short_name.is_empty() || short_name == "all" || short_name.contains(['/', ',', '\t', '\n'])
+        || short_name.chars().all(|c| c.is_ascii_digit()) {
+        return Err(HarissaError::Usage(format!("Invalid application name '{}': names cannot be numbers or 'all', or contain slashes, commas, tabs or newlines", short_name)));
+    }
+    if let Some(tag) = spec.tags.iter().find(|tag| tag.is_empty() || tag.contains([',', '\n'])) {
+        return Err(HarissaError::Usage(format!("Invalid tag '{}': tags cannot be empty or contain commas or newlines", tag)));
+    }
+    Ok(())
+}
+
+/// Send SIGTERM to a tracked process, given by the PID stored in processes.csv
+fn terminate_process(pid: &str) -> Result<(), HarissaError> {
+    let pid = pid.parse().map_err(|_| HarissaError::Failed(format!("Invalid PID '{}' in processes file", pid)))?;
+    signals::send(pid, libc::SIGTERM)
+}
+
+fn write_processes(processes_file: &Path, processes: &[String]) -> Result<(), HarissaError> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .create(true)
+        .open(processes_file)
+        .map_err(|e| HarissaError::io("Failed to open processes file for writing", e))?;
+
+    for process in processes {
+        writeln!(file, "{}", process)
+            .map_err(|e| HarissaError::io("Failed to write to processes file", e))?;
+    }
+
+    Ok(())
+}
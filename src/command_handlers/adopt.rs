@@ -0,0 +1,45 @@
+use harissa::error::HarissaError;
+use super::selection;
+
+/// Track a process that was started outside harissa
+pub fn adopt_command(args: &[String]) -> Result<(), HarissaError> {
+    let usage = "Usage: adopt <pid> -n <name> [--tag tag[,tag...]] [--namespace namespace]\nExample: adopt 4242 -n legacy-api";
+
+    let mut pid = None;
+    let mut name = None;
+    let mut tags = Vec::new();
+    let mut namespace = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-n" | "--tag" | "--namespace" => {
+                let value = args.get(i + 1)
+                    .ok_or_else(|| HarissaError::Usage(format!("{} requires a value\n{}", args[i], usage)))?;
+                match args[i].as_str() {
+                    "-n" => name = Some(value.clone()),
+                    "--tag" => tags.extend(value.split(',').map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty())),
+                    _ => namespace = Some(value.clone()),
+                }
+                i += 2;
+            },
+            value if pid.is_none() => {
+                pid = Some(value.parse::<u32>()
+                    .map_err(|_| HarissaError::Usage(format!("Invalid PID '{}'\n{}", value, usage)))?);
+                i += 1;
+            },
+            other => return Err(HarissaError::Usage(format!("Unexpected argument '{}'\n{}", other, usage))),
+        }
+    }
+
+    let (Some(pid), Some(name)) = (pid, name) else {
+        return Err(HarissaError::Usage(usage.to_string()));
+    };
+
+    let harissa = selection::client(namespace.as_deref())?;
+    let app = harissa.adopt(pid, &name, &tags)?;
+
+    println!("Adopted process {} as {} with ID {}", app.pid, app.name, app.id);
+    println!("Its output is not captured until 'harissa reload {}' relaunches it through harissa", app.name);
+    Ok(())
+}
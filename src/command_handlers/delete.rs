@@ -0,0 +1,36 @@
+use harissa::client::StopOutcome;
+use harissa::error::HarissaError;
+use super::selection;
+
+/// Delete the matching applications: stop them if they are running and forget their spec and ID
+pub fn delete_command(args: &[String]) -> Result<(), HarissaError> {
+    let usage = "Usage: delete <ID | app name | glob | /regex/ | all | PID> [--tag tag] [--namespace namespace] [--yes]\nExample: delete api";
+    let selection = selection::parse(args, usage)?;
+    let target = selection.target.clone().ok_or_else(|| HarissaError::Usage(usage.to_string()))?;
+
+    let harissa = selection.client()?;
+    let names = [harissa.names(&target)?, harissa.stopped_names(&target)?].concat();
+    selection::confirm(&names, &target, "delete", selection.yes)?;
+    let deleted = harissa.delete(&target)?;
+
+    let mut failure = None;
+    for app in &deleted {
+        match &app.outcome {
+            StopOutcome::Stopped => println!("Stopped and deleted {} ({})", app.name, app.pid),
+            StopOutcome::NotRunning if app.pid.is_empty() => println!("Deleted {}", app.name),
+            StopOutcome::NotRunning => println!("Deleted {} ({}), which was not running", app.name, app.pid),
+            StopOutcome::Failed(error) => {
+                println!("Failed to delete {} ({}): {}", app.name, app.pid, error);
+                failure.get_or_insert_with(|| error.clone());
+            },
+        }
+        if app.removed_schedule {
+            println!("Removed scheduled restart of {}", app.name);
+        }
+    }
+
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
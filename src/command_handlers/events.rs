@@ -0,0 +1,63 @@
+use harissa::error::HarissaError;
+use harissa::events;
+use harissa::glob;
+use harissa::namespace;
+use harissa::home;
+
+/// Show the recorded history of application events
+pub fn events_command(args: &[String]) -> Result<(), HarissaError> {
+    let mut app = None;
+    let mut since = None;
+    let mut only_namespace = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--app" | "--since" | "--namespace" => {
+                let value = args.get(i + 1)
+                    .ok_or_else(|| HarissaError::Usage(format!("{} requires a value", args[i])))?;
+                match args[i].as_str() {
+                    "--app" => app = Some(value.clone()),
+                    "--since" => since = Some(events::parse_since(value).map_err(HarissaError::Usage)?),
+                    _ => only_namespace = Some(value.clone()),
+                }
+                i += 2;
+            },
+            other => return Err(HarissaError::Usage(format!("Unknown events option: {}\nUsage: events [--app name | glob] [--namespace namespace] [--since 1h]", other))),
+        }
+    }
+
+    let harissa_dir = home::harissa_dir()?;
+    let events: Vec<events::Event> = events::read(&harissa_dir)?
+        .into_iter()
+        .filter(|event| only_namespace.is_none() || namespace::split(&event.app).0 == only_namespace.as_deref())
+        // --app also takes a wildcard pattern; events of apps that are gone have no ID to look up.
+        // Within a namespace it is matched against the names without the namespace.
+        .filter(|event| app.as_ref().is_none_or(|app| {
+            let name = if only_namespace.is_some() { namespace::split(&event.app).1 } else { &event.app };
+            glob::matches(app, name)
+        }))
+        .filter(|event| since.is_none_or(|since| event.time >= since))
+        .collect();
+
+    if events.is_empty() {
+        println!("No events found.");
+        return Ok(());
+    }
+
+    println!("{:<20} {:<8} {:<20} {:<8} {:<10} {:<30} CWD", "TIME", "EVENT", "APP", "PID", "USER", "DETAILS");
+    println!("{:-<120}", "");
+
+    for event in events {
+        println!("{:<20} {:<8} {:<20} {:<8} {:<10} {:<30} {}",
+            event.time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            event.kind.as_str(),
+            event.app,
+            event.pid.map(|pid| pid.to_string()).unwrap_or_else(|| "-".to_string()),
+            event.user,
+            event.details,
+            event.cwd);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,379 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use harissa::app_spec::{self, AppSpec};
+use harissa::client::{AppRef, Harissa};
+use harissa::error::HarissaError;
+use harissa::home;
+use harissa::init_scripts::{shell_quote, systemd_quote};
+
+const DEFAULT_COMPOSE_IMAGE: &str = "debian:stable-slim";
+
+/// Export an application's stored spec as a systemd unit, docker-compose service or Procfile
+pub fn export_command(args: &[String]) -> Result<(), HarissaError> {
+    let usage = "Usage: export <systemd | docker-compose | procfile> [ID | name | glob...] [-o file] [--image image] [--namespace namespace]";
+    if args.is_empty() {
+        return Err(HarissaError::Usage(format!("{}\nExample: export systemd my_app", usage)));
+    }
+
+    let format = args[0].as_str();
+    let mut names = Vec::new();
+    let mut output = None;
+    let mut image = DEFAULT_COMPOSE_IMAGE.to_string();
+    let mut only_namespace = None;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-o" | "--image" | "--namespace" => {
+                let value = args.get(i + 1)
+                    .ok_or_else(|| HarissaError::Usage(format!("{} requires a value", args[i])))?
+                    .clone();
+                match args[i].as_str() {
+                    "-o" => output = Some(value),
+                    "--image" => image = value,
+                    _ => only_namespace = Some(value),
+                }
+                i += 2;
+            },
+            _ => {
+                names.push(args[i].clone());
+                i += 1;
+            }
+        }
+    }
+
+    // Get the harissa directory
+    let harissa_dir = home::harissa_dir()?;
+    let mut harissa = Harissa::with_dir(&harissa_dir);
+    if let Some(only_namespace) = &only_namespace {
+        harissa = harissa.in_namespace(only_namespace)?;
+    }
+
+    // Every tracked app (of the namespace) is exported when no name is given (multi-app formats only)
+    if names.is_empty() {
+        if format == "systemd" {
+            return Err(HarissaError::Usage(format!("export systemd needs an app name\n{}", usage)));
+        }
+        names = vec!["all".to_string()];
+    }
+
+    // Like kill and reload, a single app name may span several arguments
+    if format == "systemd" {
+        names = vec![names.join(" ")];
+    }
+
+    // IDs and patterns stand for tracked apps; a plain name may also be a stopped app's spec
+    let mut apps = Vec::new();
+    for identifier in names {
+        let target = AppRef::parse(&identifier);
+        let matched = match &target {
+            AppRef::Name(name) => vec![harissa.qualify(name)],
+            _ => harissa.names(&target)?,
+        };
+        if matched.is_empty() && target == AppRef::All {
+            return Err(HarissaError::NotFound("No applications are currently tracked. Nothing to export.".to_string()));
+        }
+        if matched.is_empty() {
+            return Err(HarissaError::NotFound(format!("No application found with {}", target)));
+        }
+        for name in matched {
+            let spec = load_spec(&harissa_dir, &name)?;
+            apps.push((name, spec));
+        }
+    }
+
+    if format == "systemd" && apps.len() > 1 {
+        return Err(HarissaError::Usage(format!("export systemd needs a single app, but {} match", apps.len())));
+    }
+
+    let contents = match format {
+        "systemd" => {
+            let (name, spec) = &apps[0];
+            systemd_unit(&harissa_dir, name, spec)
+        },
+        "docker-compose" => docker_compose(&apps, &image),
+        "procfile" => procfile(&apps),
+        _ => return Err(HarissaError::Usage(format!("Unknown export format: {}\n{}", format, usage))),
+    };
+
+    match output {
+        Some(path) => {
+            // The exported variables may hold secrets, so only the owner may read them, even in a
+            // file that existed before
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(&path)
+                .and_then(|mut file| {
+                    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+                    file.write_all(contents.as_bytes())
+                })
+                .map_err(|e| HarissaError::io(&format!("Failed to write {}", path), e))?;
+            println!("Exported {} to {}", format, path);
+        },
+        None => print!("{}", contents),
+    }
+
+    Ok(())
+}
+
+/// (name, command) of every tracked application in processes.csv
+fn tracked_apps(harissa_dir: &Path) -> Result<Vec<(String, String)>, HarissaError> {
+    let processes_file = harissa_dir.join("processes.csv");
+    if !processes_file.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(&processes_file)
+        .map_err(|e| HarissaError::io("Failed to open processes file", e))?;
+
+    let mut apps = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| HarissaError::io("Failed to read line", e))?;
+        let parts: Vec<&str> = line.split(',').collect();
+
+        if parts.len() < 3 {
+            continue;
+        }
+
+        apps.push((parts[1].to_string(), parts[2..].join(",")));
+    }
+
+    Ok(apps)
+}
+
+/// Load an app's spec, falling back to the command in processes.csv for apps started by older versions
+fn load_spec(harissa_dir: &Path, name: &str) -> Result<AppSpec, HarissaError> {
+    let mut spec = app_spec::load(harissa_dir, name)?;
+
+    if spec.argv.is_empty() {
+        let command = tracked_apps(harissa_dir)?
+            .into_iter()
+            .find(|(app_name, _)| app_name == name)
+            .map(|(_, command)| command)
+            .ok_or_else(|| HarissaError::NotFound(format!("No application found with name '{}'", name)))?;
+        spec.argv = command.split_whitespace().map(|part| part.to_string()).collect();
+    }
+
+    Ok(spec)
+}
+
+fn systemd_unit(harissa_dir: &Path, name: &str, spec: &AppSpec) -> String {
+    let mut unit = String::new();
+
+    unit.push_str("[Unit]\n");
+    unit.push_str(&format!("Description={} (exported from harissa)\n", name));
+    unit.push_str("After=network.target\n\n");
+
+    unit.push_str("[Service]\n");
+    unit.push_str("Type=simple\n");
+    // '$' would start a variable reference in ExecStart
+    let exec: Vec<String> = spec.argv.iter().map(|arg| systemd_quote(&arg.replace('$', "$$"))).collect();
+    unit.push_str(&format!("ExecStart={}\n", exec.join(" ")));
+    if let Some(cwd) = &spec.cwd {
+        unit.push_str(&format!("WorkingDirectory={}\n", cwd.to_string_lossy().replace('%', "%%")));
+    }
+    if let Some(user) = &spec.user {
+        unit.push_str(&format!("User={}\n", user));
+    }
+    if let Some(group) = &spec.group {
+        unit.push_str(&format!("Group={}\n", group));
+    }
+    // Only what was set for the app: the rest is the environment of the shell that started it
+    for (key, value) in spec.explicit_env() {
+        unit.push_str(&format!("Environment={}\n", systemd_quote(&format!("{}={}", key, value))));
+    }
+
+    for (option, value) in spec.limits.to_options().into_iter().chain(spec.attributes.to_options()) {
+        let setting = match option {
+            "--cpu-quota" if value != "max" => format!("CPUQuota={}", value),
+            "--memory-max" => format!("MemoryMax={}", value.replace("max", "infinity")),
+            "--pids-max" => format!("TasksMax={}", value.replace("max", "infinity")),
+            "--io-weight" => format!("IOWeight={}", value),
+            "--nofile" => format!("LimitNOFILE={}", value.replace("unlimited", "infinity")),
+            "--nproc" => format!("LimitNPROC={}", value.replace("unlimited", "infinity")),
+            "--core" => format!("LimitCORE={}", value.replace("unlimited", "infinity")),
+            "--nice" => format!("Nice={}", value),
+            "--umask" => format!("UMask=0{}", value),
+            "--oom-score-adj" => format!("OOMScoreAdjust={}", value),
+            _ => continue,
+        };
+        unit.push_str(&setting);
+        unit.push('\n');
+    }
+
+    let stdout_log = home::log_path(harissa_dir, name, "out");
+    let stderr_log = home::log_path(harissa_dir, name, "err");
+    unit.push_str(&format!("StandardOutput=append:{}\n", stdout_log.to_string_lossy().replace('%', "%%")));
+    unit.push_str(&format!("StandardError=append:{}\n", stderr_log.to_string_lossy().replace('%', "%%")));
+
+    // Harissa does not restart crashed apps, so neither does the exported unit
+    unit.push_str("Restart=no\n");
+    if let Some(cron) = &spec.cron_restart {
+        unit.push_str(&format!("# Harissa restarted this app on the cron schedule \"{}\";\n", cron));
+        unit.push_str("# use a .timer unit running 'systemctl restart' to keep that behaviour.\n");
+    }
+
+    unit.push_str("\n[Install]\n");
+    unit.push_str("WantedBy=multi-user.target\n");
+    unit
+}
+
+fn docker_compose(apps: &[(String, AppSpec)], image: &str) -> String {
+    let mut compose = String::from("services:\n");
+
+    for (name, spec) in apps {
+        compose.push_str(&format!("  {}:\n", service_name(name, '-')));
+        compose.push_str(&format!("    image: {}\n", yaml_quote(image)));
+
+        compose.push_str("    command:\n");
+        for arg in &spec.argv {
+            compose.push_str(&format!("      - {}\n", yaml_quote(arg)));
+        }
+
+        if let Some(cwd) = &spec.cwd {
+            compose.push_str(&format!("    working_dir: {}\n", yaml_quote(&cwd.to_string_lossy())));
+        }
+
+        match (&spec.user, &spec.group) {
+            (Some(user), Some(group)) => compose.push_str(&format!("    user: {}\n", yaml_quote(&format!("{}:{}", user, group)))),
+            (Some(user), None) => compose.push_str(&format!("    user: {}\n", yaml_quote(user))),
+            _ => {},
+        }
+
+        let env: Vec<_> = spec.explicit_env().collect();
+        if !env.is_empty() {
+            compose.push_str("    environment:\n");
+            for (key, value) in env {
+                compose.push_str(&format!("      {}: {}\n", yaml_quote(key), yaml_quote(value)));
+            }
+        }
+
+        let mut ulimits = Vec::new();
+        for (option, value) in spec.limits.to_options().into_iter().chain(spec.attributes.to_options()) {
+            match option {
+                "--cpu-quota" => {
+                    if let Ok(percent) = value.trim_end_matches('%').parse::<f64>() {
+                        compose.push_str(&format!("    cpus: {}\n", percent / 100.0));
+                    }
+                },
+                "--memory-max" if value != "max" => compose.push_str(&format!("    mem_limit: {}\n", value)),
+                "--pids-max" if value != "max" => compose.push_str(&format!("    pids_limit: {}\n", value)),
+                "--io-weight" => {
+                    // Docker weights range from 10 to 1000 instead of 1 to 10000
+                    let weight = value.parse::<u32>().map(|w| (w / 10).clamp(10, 1000)).unwrap_or(100);
+                    compose.push_str(&format!("    blkio_config:\n      weight: {}\n", weight));
+                },
+                "--oom-score-adj" => compose.push_str(&format!("    oom_score_adj: {}\n", value)),
+                "--nofile" | "--nproc" | "--core" => ulimits.push((option.trim_start_matches("--"), value)),
+                _ => compose.push_str(&format!("    # {} {} has no docker-compose equivalent\n", option, value)),
+            }
+        }
+
+        if !ulimits.is_empty() {
+            compose.push_str("    ulimits:\n");
+            for (name, value) in ulimits {
+                let value = value.replace("unlimited", "-1");
+                let (soft, hard) = value.split_once(':').unwrap_or((&value, &value));
+                compose.push_str(&format!("      {}:\n        soft: {}\n        hard: {}\n", name, soft, hard));
+            }
+        }
+
+        compose.push_str("    restart: \"no\"\n");
+    }
+
+    compose
+}
+
+fn procfile(apps: &[(String, AppSpec)]) -> String {
+    let mut procfile = String::new();
+
+    for (name, spec) in apps {
+        let command: Vec<String> = spec.argv.iter().map(|arg| shell_quote(arg)).collect();
+        procfile.push_str(&format!("{}: {}\n", service_name(name, '_'), command.join(" ")));
+    }
+
+    procfile
+}
+
+/// Turn an app name into a compose service name (dashes allowed) or Procfile process type (no dashes)
+fn service_name(name: &str, separator: char) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || (c == '-' && separator == '-') { c } else { separator })
+        .collect()
+}
+
+/// Quote a value for docker-compose, where '$' starts a variable
+fn yaml_quote(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> AppSpec {
+        let mut spec = AppSpec {
+            argv: vec!["node".into(), "server.js".into(), "--greeting".into(), "hello $USER".into()],
+            cwd: Some("/srv/api".into()),
+            env: vec![
+                ("SSH_AUTH_SOCK".into(), "/tmp/ssh-agent.sock".into()),
+                ("AWS_SECRET_ACCESS_KEY".into(), "from-the-shell".into()),
+                ("DATABASE_URL".into(), "postgres://localhost/app".into()),
+            ],
+            explicit_env: vec!["DATABASE_URL".into()],
+            ..AppSpec::default()
+        };
+        spec.set("memory-max", "512M").unwrap();
+        spec.set("nofile", "1024:4096").unwrap();
+        spec
+    }
+
+    #[test]
+    fn systemd_units_hold_only_the_variables_set_for_the_app() {
+        let harissa_dir = Path::new("/home/me/.harissa_apps");
+        let unit = systemd_unit(harissa_dir, "api", &spec());
+
+        assert!(unit.contains("ExecStart=node server.js --greeting \"hello $$USER\"\n"), "{}", unit);
+        assert!(unit.contains("WorkingDirectory=/srv/api\n"));
+        assert!(unit.contains("Environment=DATABASE_URL=postgres://localhost/app\n"), "{}", unit);
+        assert!(!unit.contains("SSH_AUTH_SOCK") && !unit.contains("from-the-shell"));
+        assert!(unit.contains("MemoryMax=536870912\n"));
+        assert!(unit.contains("LimitNOFILE=1024:4096\n"));
+        let stdout_log = format!("StandardOutput=append:{}\n", home::log_path(harissa_dir, "api", "out").display());
+        assert!(unit.contains(&stdout_log), "{}", unit);
+    }
+
+    #[test]
+    fn compose_services_hold_only_the_variables_set_for_the_app() {
+        let compose = docker_compose(&[("qa/api".to_string(), spec())], DEFAULT_COMPOSE_IMAGE);
+
+        assert!(compose.starts_with("services:\n  qa-api:\n    image: \"debian:stable-slim\"\n"), "{}", compose);
+        assert!(compose.contains("      - \"hello $$USER\"\n"));
+        assert!(compose.contains("    environment:\n      \"DATABASE_URL\": \"postgres://localhost/app\"\n"), "{}", compose);
+        assert!(!compose.contains("SSH_AUTH_SOCK") && !compose.contains("from-the-shell"));
+        assert!(compose.contains("    mem_limit: 536870912\n"));
+        assert!(compose.contains("      nofile:\n        soft: 1024\n        hard: 4096\n"));
+
+        // Without variables set for it, a service has no environment at all
+        let inherited = AppSpec { explicit_env: Vec::new(), ..spec() };
+        assert!(!docker_compose(&[("api".to_string(), inherited)], "node:22").contains("environment"));
+    }
+
+    #[test]
+    fn procfiles_quote_arguments_and_name_process_types() {
+        let worker = AppSpec { argv: vec!["sh".into(), "-c".into(), "exec worker --queue 'a b'".into()], ..AppSpec::default() };
+        let procfile = procfile(&[("shop.web-1".to_string(), spec()), ("worker".to_string(), worker)]);
+
+        let lines: Vec<&str> = procfile.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("shop_web_1: node server.js --greeting "), "{}", lines[0]);
+        assert!(lines[1].starts_with("worker: sh -c "), "{}", lines[1]);
+        assert!(!procfile.contains("DATABASE_URL"));
+    }
+}
@@ -1,7 +1,8 @@
 use crate::commands::CommandRegistry;
+use harissa::error::HarissaError;
 
 /// Displays help information about available commands
-pub fn help_command(args: &[String]) -> Result<(), String> {
+pub fn help_command(args: &[String]) -> Result<(), HarissaError> {
     if args.is_empty() {
         println!("Available commands:");
         let registry = CommandRegistry::new();
@@ -9,6 +10,14 @@ pub fn help_command(args: &[String]) -> Result<(), String> {
             println!("  {}", cmd);
         }
         println!("\nUse 'help <command>' for more information about a specific command.");
+        println!("\nGlobal options (before the command):");
+        println!("  --json-errors  Print errors as JSON on stderr");
+        println!("  --home <dir>   Keep harissa's state in <dir> instead of ~/.harissa_apps");
+        println!("                 (same as setting HARISSA_HOME), e.g. for an isolated instance");
+        println!("  --system       Manage the system-wide applications in /var/lib/harissa, with logs in");
+        println!("                 /var/log/harissa; for root and members of the harissa group");
+        println!("\nExit codes: 0 success, 1 failed, 2 usage, 3 not found, 4 permission denied, 5 system error,");
+        println!("            6 already exists");
     } else {
         let command = &args[0];
         match command.as_str() {
@@ -27,47 +36,272 @@ pub fn help_command(args: &[String]) -> Result<(), String> {
             "start" => {
                 println!("start - Start a command in the background");
                 println!("\nUsage: start <command> [args...] [-n name]");
+                println!("       start <ID | app name | glob | /regex/ | all>");
                 println!("  Starts the specified command in the background and tracks it.");
+                println!("  Given only applications stopped with 'stop', starts them again as they were.");
                 println!("  Options:");
                 println!("    -n <name>  Specify a name for the background process (optional)");
                 println!("               If not provided, the command name will be used");
+                println!("    --cron-restart <cron>");
+                println!("               Restart the application on a cron schedule (e.g. \"0 3 * * *\")");
+                println!("    --cpu-quota <percent>   Limit CPU time, e.g. 50% of one CPU or 200% for two");
+                println!("    --memory-max <size>     Limit memory, e.g. 512M or 2G");
+                println!("    --pids-max <count>      Limit the number of processes and threads");
+                println!("    --io-weight <1-10000>   Set the relative IO weight (default 100)");
+                println!("               Each application runs in its own cgroup v2 when cgroups are");
+                println!("               delegated to the user; otherwise limits are skipped with a warning");
+                println!("    --nofile <n|soft:hard>  Limit open files (RLIMIT_NOFILE)");
+                println!("    --nproc <n|soft:hard>   Limit processes for the user (RLIMIT_NPROC)");
+                println!("    --core <n|soft:hard>    Limit core dump size (RLIMIT_CORE)");
+                println!("    --nice <-20..19>        Set the scheduling priority");
+                println!("    --umask <mode>          Set the file creation mask, e.g. 022");
+                println!("    --oom-score-adj <n>     Adjust the OOM killer score (-1000..1000)");
+                println!("               Limits accept 'unlimited'. These attributes are set before the");
+                println!("               command is executed and are applied again on reload");
+                println!("    --user <name|uid>       Run the application as this user (root only)");
+                println!("    --group <name|gid>      Run the application with this group (root only)");
+                println!("               HOME and USER are set for the user and log files are owned by it");
+                println!("    --pre-start <cmd>       Run a shell command before starting; a failure aborts the start");
+                println!("    --post-start <cmd>      Run a shell command after starting");
+                println!("    --pre-stop <cmd>        Run a shell command before kill or reload stops the application");
+                println!("    --post-stop <cmd>       Run a shell command after it was stopped");
+                println!("    --on-crash <cmd>        Run a shell command when the application exits on its own");
+                println!("               Hooks run in the app's directory and environment and log to its logs");
+                println!("               and are killed, failing, when they run for more than 5 minutes");
+                println!("    --after <app[,app...]>  Start after these running applications; resurrect and");
+                println!("               reload start dependencies first and stop them last");
+                println!("    --namespace <name>      Start the application in a namespace, e.g. staging; it is");
+                println!("               tracked as <namespace>/<name> and logs to ~/.harissa_apps/<namespace>/");
+                println!("    --tag <tag[,tag...]>    Label the application, e.g. to kill or reload it with --tag");
+                println!("    --env <NAME=value>      Set an environment variable on top of the current environment;");
+                println!("               only these, .env and $PORT are exported by export");
+                println!("    --force                 Replace a running application with the same name instead");
+                println!("               of failing; it keeps its ID");
+                println!("    --procfile <path>       Start every process type of a Procfile instead of a command");
+                println!("    --formation <spec>      Instances per process type, e.g. web=2,worker=1 (default 1)");
+                println!("               Apps are named <project>.<type>.<n>, where the project is -n or the");
+                println!("               Procfile's directory. .env is loaded and $PORT is set per instance");
                 println!("\nExample: start node app.js -n my_app");
                 println!("Example: start python server.py");
+                println!("Example: start node app.js -n my_app --cron-restart \"0 3 * * *\"");
+                println!("Example: start node api.js -n api --after db-proxy");
+                println!("Example: start node app.js -n api --pre-start \"npm run migrate\"");
+                println!("Example: start --procfile Procfile --formation web=2,worker=1");
+                println!("Example: start node app.js -n my_app --force");
             },
             "list" => {
-                println!("list - List all running applications started with this CLI");
-                println!("\nUsage: list");
-                println!("  Shows information about all currently running applications that were");
-                println!("  started using the 'start' command.");
-                println!("  For each application, displays the PID, name, CPU usage, memory usage,");
+                println!("list - List the applications started with this CLI");
+                println!("\nUsage: list [--namespace namespace]");
+                println!("  Shows information about all applications that were started using the");
+                println!("  'start' command, or only those of one namespace.");
+                println!("  For each application, displays the ID, PID, namespace, name, status (online,");
+                println!("  paused, errored when its process is gone, or stopped), CPU usage, memory usage,");
                 println!("  and the command that was used to start it.");
+                println!("  Applications running in their own cgroup report CPU usage and memory");
+                println!("  from the cgroup; others report the ps percentages.");
             },
             "kill" => {
-                println!("kill - Terminate a running application by PID or name");
-                println!("\nUsage: kill <PID | app name>");
-                println!("  Terminates a running application that was started with the 'start' command.");
-                println!("  You can specify either the PID or the name of the application.");
-                println!("\nExample: kill 1234");
+                println!("kill - Terminate applications by ID, name, pattern or PID and forget them");
+                println!("\nUsage: kill <ID | app name | glob | /regex/ | all | PID> [--yes]");
+                println!("       kill --tag <tag> [--yes]");
+                println!("  Terminates running applications that were started with the 'start' command");
+                println!("  and stops tracking them, like 'delete'. Use 'stop' to start them again later.");
+                println!("  A number is an application ID, or a PID if no application has that ID.");
+                println!("  A name with wildcards (*, ?, [...]) terminates every application it matches,");
+                println!("  /regex/ every application whose name matches the regular expression, --tag");
+                println!("  every application started with that tag and 'all' every application.");
+                println!("  With --namespace, only applications of that namespace are matched, by their");
+                println!("  names within it; otherwise names include the namespace, e.g. qa/api.");
+                println!("  A PID that another process took over, e.g. after a reboot, is never signalled:");
+                println!("  the start time and executable recorded at start must still match.");
+                println!("  When more than one application matches, kill asks for confirmation;");
+                println!("  --yes (or -y) skips the question and is required without a terminal.");
+                println!("\nExample: kill 3");
                 println!("Example: kill my_app");
+                println!("Example: kill 'worker-*'");
+                println!("Example: kill '/^api-[0-9]+$/' --yes");
+                println!("Example: kill --tag backend");
+                println!("Example: kill --namespace qa all --yes");
+            },
+            "stop" => {
+                println!("stop - Stop applications but keep them to start again later");
+                println!("\nUsage: stop <ID | app name | glob | /regex/ | all | PID> [--yes]");
+                println!("       stop --tag <tag> [--yes]");
+                println!("  Terminates the matching applications like 'kill', but keeps their spec and ID.");
+                println!("  They are listed as stopped until 'start <name>' starts them again or 'delete'");
+                println!("  removes them. Errored applications can be stopped as well. Applications are");
+                println!("  selected like for 'kill'.");
+                println!("\nExample: stop api");
+                println!("Example: start api");
+            },
+            "delete" => {
+                println!("delete - Stop applications and forget them");
+                println!("\nUsage: delete <ID | app name | glob | /regex/ | all | PID> [--yes]");
+                println!("       delete --tag <tag> [--yes]");
+                println!("  Terminates the matching running applications and removes them, together with");
+                println!("  matching stopped applications, from harissa. Applications are selected like");
+                println!("  for 'kill', which does the same.");
+                println!("\nExample: delete api");
+                println!("Example: delete all --yes");
+            },
+            "signal" => {
+                println!("signal - Send a signal to running applications");
+                println!("\nUsage: signal <signal> <ID | app name | glob | /regex/ | all | PID> [--group] [--yes]");
+                println!("       signal <signal> --tag <tag> [--group] [--yes]");
+                println!("  Sends the signal, e.g. to make applications reload their configuration or");
+                println!("  reopen their logs. Signals are given by name, with or without SIG and in any");
+                println!("  case (SIGHUP, usr1), or by number. With --group the signal goes to every");
+                println!("  process in the application's process group, including its children.");
+                println!("  Applications are selected like for 'kill'.");
+                println!("\nExample: signal SIGHUP api");
+                println!("Example: signal usr2 'worker-*' --group --yes");
+            },
+            "adopt" => {
+                println!("adopt - Track a process that was started outside harissa");
+                println!("\nUsage: adopt <pid> -n <name> [--tag tag[,tag...]] [--namespace namespace]");
+                println!("  Records the running process under the given name, with its command line,");
+                println!("  working directory and environment read from /proc, so it shows up in 'list'");
+                println!("  and can be stopped, signalled and reloaded like any other application.");
+                println!("  Its output is not captured until 'reload' relaunches it through harissa.");
+                println!("\nExample: adopt 4242 -n legacy-api");
             },
             "reload" => {
-                println!("reload - Restart running applications by PID or name");
-                println!("\nUsage: reload [PID | app name]");
+                println!("reload - Restart running applications by ID, name, pattern or PID");
+                println!("\nUsage: reload [ID | app name | glob | /regex/ | all | PID] [--yes]");
+                println!("       reload --tag <tag> [--yes]");
+                println!("  --namespace <namespace> limits the applications like for kill; without a");
+                println!("  selector, every application of the namespace is reloaded.");
                 println!("  Restarts running applications that were started with the 'start' command.");
                 println!("  If no argument is provided, all running applications will be reloaded.");
-                println!("  If an argument is provided, only the specified application will be reloaded.");
+                println!("  If an argument is provided, only the matching applications will be reloaded;");
+                println!("  it is resolved like for kill, including the confirmation when several match.");
+                println!("  Reloaded applications keep their IDs.");
+                println!("  Applications are stopped in reverse --after order and started again in");
+                println!("  dependency order, each waiting until the applications it runs after are ready.");
                 println!("\nExample: reload         # Reload all applications");
-                println!("Example: reload 1234    # Reload application with PID 1234");
+                println!("Example: reload 3       # Reload application with ID 3");
                 println!("Example: reload my_app  # Reload application named my_app");
+                println!("Example: reload 'web-*' # Reload every application whose name starts with web-");
+                println!("Example: reload --tag backend --yes");
             },
             "startup" => {
-                println!("startup - Generate a startup script for auto-starting applications on system boot");
-                println!("\nUsage: startup");
-                println!("  Generates a shell script that can be used to automatically start all tracked");
-                println!("  applications when the system boots up.");
-                println!("  The script is printed to the console and can be redirected to a file.");
-                println!("  Instructions for setting up the startup script are provided when run.");
-                println!("\nExample: startup > ~/harissa_startup.sh");
+                println!("startup - Start the saved applications automatically on system boot");
+                println!("\nUsage: startup [install | uninstall] [options]");
+                println!("  Generates a boot-time hook that runs 'harissa resurrect': a systemd unit,");
+                println!("  an OpenRC script or a SysV init script. Without a subcommand, the generated");
+                println!("  file is printed.");
+                println!("  install    Save the running applications, write the file and enable it");
+                println!("  uninstall  Disable the hook and remove the file");
+                println!("  Options:");
+                println!("    --init <systemd|openrc|sysv>  Init system (detected by default)");
+                println!("    --user                        Install a systemd user unit (no root needed)");
+                println!("    -u <user>                     User whose applications are started (default: you)");
+                println!("    --hp <home>                   Home directory of that user");
+                println!("    --dry-run                     Print the file and commands without changing anything");
+                println!("\nExample: startup install --user");
+                println!("Example: sudo harissa startup install --init systemd -u alice --hp /home/alice");
+                println!("Example: startup uninstall --dry-run");
+            },
+            "save" => {
+                println!("save - Save the running applications so they can be resurrected later");
+                println!("\nUsage: save [--namespace namespace]");
+                println!("  Records the full launch spec of every running application (command and");
+                println!("  arguments, working directory, environment and options) in");
+                println!("  ~/.harissa_apps/harissa.dump, replacing any previous snapshot.");
+                println!("  With --namespace, only that namespace's part of the snapshot is replaced.");
+            },
+            "resurrect" => {
+                println!("resurrect - Start the applications recorded by 'save'");
+                println!("\nUsage: resurrect [--namespace namespace]");
+                println!("  Starts every saved application (of the namespace) exactly as it was started before.");
+                println!("  Applications that are already running are skipped. Applications started with");
+                println!("  --after are started once the applications they depend on are ready.");
+            },
+            "export" => {
+                println!("export - Convert applications into systemd, docker-compose or Procfile definitions");
+                println!("\nUsage: export systemd <ID | app name> [-o file]");
+                println!("       export docker-compose [ID | app name | glob...] [-o file] [--image image]");
+                println!("       export procfile [ID | app name | glob...] [-o file]");
+                println!("       Every format takes --namespace to select applications of one namespace.");
+                println!("  Converts the stored launch spec (command, working directory, environment,");
+                println!("  user, resource limits and log paths) into an equivalent standalone");
+                println!("  definition, printed or written to the file given with -o (mode 0600).");
+                println!("  Only variables given with --env, from .env or $PORT are exported, not the");
+                println!("  rest of the shell environment the app was started with.");
+                println!("  docker-compose and procfile export every tracked app when no name is given;");
+                println!("  docker-compose services use debian:stable-slim unless --image is given.");
+                println!("\nExample: export systemd my_app -o /etc/systemd/system/my_app.service");
+                println!("Example: export docker-compose -o docker-compose.yml --image node:20");
+                println!("Example: export procfile > Procfile");
+            },
+            "schedule" => {
+                println!("schedule - Run a short-lived command on a cron schedule");
+                println!("\nUsage: schedule \"<cron>\" <command> [args...] [-n name]");
+                println!("       schedule \"<cron>\" [-n name] -- <command> [args...]");
+                println!("       schedule remove <job name>");
+                println!("       schedule");
+                println!("  Registers a job that the supervisor launches whenever the cron expression");
+                println!("  matches, in the current directory. The output of each run and its exit code");
+                println!("  are appended to the job's log files. Without arguments, lists all schedules.");
+                println!("  Cron expressions have five fields (minute hour day month weekday) and support");
+                println!("  '*', lists, ranges, steps, month/day names and @hourly, @daily, @weekly,");
+                println!("  @monthly and @yearly.");
+                println!("\nExample: schedule \"0 3 * * *\" ./backup.sh -n nightly_backup");
+                println!("Example: schedule \"*/15 * * * *\" curl -s http://localhost/ping");
+                println!("Example: schedule remove nightly_backup");
+            },
+            "events" => {
+                println!("events - Show the history of application events");
+                println!("\nUsage: events [--app name | glob] [--namespace namespace] [--since duration | date]");
+                println!("  Lists recorded starts, stops, reloads, scheduled restarts, crashes and job");
+                println!("  exit codes with the user and working directory that caused them.");
+                println!("  Options:");
+                println!("    --app <name>     Only show events of this application, or of the applications");
+                println!("                     matching a wildcard pattern such as 'web-*'");
+                println!("    --namespace <ns> Only show events of applications in this namespace");
+                println!("    --since <when>   Only show events after a duration ago (30s, 15m, 1h, 2d, 1w)");
+                println!("                     or a date (2024-05-01, \"2024-05-01 14:30\")");
+                println!("\nExample: events --app api --since 1h");
+            },
+            "serve-metrics" => {
+                println!("serve-metrics - Serve application metrics for Prometheus");
+                println!("\nUsage: serve-metrics [--listen address:port]");
+                println!("  Serves /metrics in the Prometheus text format with the CPU, memory, uptime,");
+                println!("  restarts, health and status of every tracked application.");
+                println!("  Listens on 127.0.0.1:9615 unless --listen is given. Runs in the foreground.");
+                println!("\nExample: serve-metrics --listen 0.0.0.0:9615");
+            },
+            "serve-api" => {
+                println!("serve-api - Serve the JSON-RPC control API on a Unix socket");
+                println!("\nUsage: serve-api [--socket path]");
+                println!("  Answers line-delimited JSON-RPC 2.0 requests on ~/.harissa_apps/harissa.sock.");
+                println!("  Methods: list, start, kill, reload and logs.subscribe.");
+                println!("  Only the owner of the socket (and root) can connect. Runs in the foreground.");
+                println!("  With --system it listens on /run/harissa/harissa.sock, open to the harissa group.");
+                println!("\nExample: echo '{{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"list\"}}' | nc -U ~/.harissa_apps/harissa.sock");
+            },
+            "web" => {
+                println!("web - Serve a REST API and dashboard over HTTP");
+                println!("\nUsage: web [--listen address:port]");
+                println!("  Serves a dashboard on / and a REST API under /api on 127.0.0.1:9616 unless");
+                println!("  --listen is given. Runs in the foreground.");
+                println!("  API requests need the web_token from ~/.harissa_apps/harissa.conf, sent as");
+                println!("  'Authorization: Bearer <token>' or ?token=<token>. A token is generated on first use.");
+                println!("  Routes:");
+                println!("    GET  /api/apps                 Status of every application");
+                println!("    POST /api/apps                 Start an application (JSON body as for serve-api's start)");
+                println!("    POST /api/apps/<name>/stop     Kill an application");
+                println!("    POST /api/apps/<name>/reload   Reload an application");
+                println!("    GET  /api/apps/<name>/logs     Stream logs as server-sent events (?stream=out|err|both&lines=N)");
+                println!("\nExample: curl -H \"Authorization: Bearer $TOKEN\" http://127.0.0.1:9616/api/apps");
+            },
+            "supervise" => {
+                println!("supervise - Run the scheduling supervisor in the foreground");
+                println!("\nUsage: supervise");
+                println!("  Runs scheduled restarts and jobs and the on-crash hooks of applications.");
+                println!("  The supervisor is started automatically in the background by 'schedule',");
+                println!("  'start --cron-restart' and 'start --on-crash', and exits once no schedules");
+                println!("  or applications with an on-crash hook are left.");
             },
             _ => {
                 println!("Help for command: {}", command);
@@ -1,81 +1,40 @@
-use std::fs::File;
-use std::io::{self, BufRead};
-use std::path::Path;
-use std::env;
-use std::process::Command;
+use harissa::app_status::AppStatus;
+use harissa::cgroup;
+use harissa::error::HarissaError;
+use harissa::namespace;
+use super::selection;
 
-const HARISSA_APPS_DIR: &str = ".harissa_apps";
+/// List the applications started with this CLI, including stopped and errored ones
+pub fn list_command(args: &[String]) -> Result<(), HarissaError> {
+    let namespace = match args {
+        [] => None,
+        [option, value] if option == "--namespace" => Some(value.as_str()),
+        _ => return Err(HarissaError::Usage("Usage: list [--namespace namespace]".to_string())),
+    };
 
-/// List all running applications started with this CLI
-pub fn list_command(_args: &[String]) -> Result<(), String> {
-    // Get the harissa directory
-    let home_dir = env::var("HOME").map_err(|e| format!("Could not get HOME directory: {}", e))?;
-    let harissa_dir = Path::new(&home_dir).join(HARISSA_APPS_DIR);
-    let processes_file = harissa_dir.join("processes.csv");
-    
-    if !processes_file.exists() {
+    let harissa = selection::client(namespace)?;
+    let apps: Vec<AppStatus> = harissa.list()?;
+    if apps.is_empty() {
         println!("No applications are currently running.");
         return Ok(());
     }
     
-    // Read the processes file
-    let file = File::open(processes_file)
-        .map_err(|e| format!("Failed to open processes file: {}", e))?;
-    let reader = io::BufReader::new(file);
-    
     // Prepare the table headers
-    println!("{:<10} {:<20} {:<15} {:<15} {:<30}", "PID", "NAME", "CPU (%)", "MEMORY (%)", "COMMAND");
-    println!("{:-<90}", "");
-    
-    // Track if we found any running processes
-    let mut found_running = false;
-    
-    // Process each line
-    for line in reader.lines() {
-        let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
-        let parts: Vec<&str> = line.split(',').collect();
-        
-        if parts.len() < 3 {
-            continue;
-        }
-        
-        let pid = parts[0];
-        let name = parts[1];
-        let command = parts[2..].join(",");
-        
-        // Check if the process is still running
-        let check_process = Command::new("ps")
-            .args(["-p", pid, "-o", "pid="])
-            .output()
-            .map_err(|e| format!("Failed to execute ps command: {}", e))?;
-        
-        if !check_process.status.success() {
-            continue;
-        }
-        
-        found_running = true;
-        
-        // Get CPU and memory usage using ps
-        let usage = Command::new("ps")
-            .args(["-p", pid, "-o", "pcpu,pmem"])  // Use pcpu,pmem format to get plain numbers without %
-            .output()
-            .map_err(|e| format!("Failed to get process usage: {}", e))?;
-        
-        let usage_str = String::from_utf8_lossy(&usage.stdout);
-        let usage_parts: Vec<&str> = usage_str.trim().split_whitespace().collect();
-        
-        // Skip the header row if present (pcpu, pmem)
-        let start_idx = if usage_parts.len() >= 2 && 
-                          (usage_parts[0].contains("CPU") || usage_parts[0].contains("pcpu")) { 2 } else { 0 };
-        
-        let cpu = if usage_parts.len() > start_idx { format!("{}%", usage_parts[start_idx]) } else { "0.0%".to_string() };
-        let mem = if usage_parts.len() > start_idx + 1 { format!("{}%", usage_parts[start_idx + 1]) } else { "0.0%".to_string() };
-        
-        println!("{:<10} {:<20} {:<15} {:<15} {:<30}", pid, name, cpu, mem, command);
-    }
+    println!("{:<5} {:<10} {:<12} {:<20} {:<8} {:<15} {:<15} {:<30}", "ID", "PID", "NAMESPACE", "NAME", "STATUS", "CPU (%)", "MEMORY", "COMMAND");
+    println!("{:-<118}", "");
     
-    if !found_running {
-        println!("No applications are currently running.");
+    for app in &apps {
+        // Apps in their own cgroup report exact memory, others their share of system memory
+        let (cpu, mem) = match (app.is_running(), app.memory_percent) {
+            (false, _) => ("-".to_string(), "-".to_string()),
+            (true, Some(percent)) => (format!("{:.1}%", app.cpu_percent), format!("{:.1}%", percent)),
+            (true, None) => (format!("{:.1}%", app.cpu_percent), cgroup::format_bytes(app.memory_bytes)),
+        };
+        
+        let id = app.id.map(|id| id.to_string()).unwrap_or_else(|| "-".to_string());
+        let pid = if app.pid.is_empty() { "-" } else { &app.pid };
+        let (app_namespace, name) = namespace::split(&app.name);
+        println!("{:<5} {:<10} {:<12} {:<20} {:<8} {:<15} {:<15} {:<30}", id, pid, app_namespace.unwrap_or("-"), name, app.state.as_str(), cpu, mem, app.command);
     }
     
     Ok(())
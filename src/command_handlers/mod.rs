@@ -4,8 +4,24 @@ mod run;
 mod start;
 mod list;
 mod kill;
+mod stop;
+mod delete;
+mod signal;
+mod adopt;
 mod reload;
 mod startup;
+mod schedule;
+mod supervise;
+mod save;
+mod resurrect;
+mod export;
+mod events;
+mod serve_metrics;
+mod serve_api;
+mod web;
+
+// Helpers shared by command handlers
+mod selection;
 
 // Export command handlers
 pub use help::help_command;
@@ -13,7 +29,20 @@ pub use run::run_command;
 pub use start::start_command;
 pub use list::list_command;
 pub use kill::kill_command;
+pub use stop::stop_command;
+pub use delete::delete_command;
+pub use signal::signal_command;
+pub use adopt::adopt_command;
 pub use reload::reload_command;
 pub use startup::startup_command;
+pub use schedule::schedule_command;
+pub use supervise::supervise_command;
+pub use save::save_command;
+pub use resurrect::resurrect_command;
+pub use export::export_command;
+pub use events::events_command;
+pub use serve_metrics::serve_metrics_command;
+pub use serve_api::serve_api_command;
+pub use web::web_command;
 
 
@@ -0,0 +1,71 @@
+use harissa::app_spec;
+use harissa::dependencies::{self, StartTracker};
+use harissa::error::HarissaError;
+use harissa::launcher;
+use harissa::namespace;
+use super::selection;
+
+/// Start the applications recorded by 'save' that are not already running
+pub fn resurrect_command(args: &[String]) -> Result<(), HarissaError> {
+    let only_namespace = match args {
+        [] => None,
+        [option, value] if option == "--namespace" => Some(value.as_str()),
+        _ => return Err(HarissaError::Usage("Usage: resurrect [--namespace namespace]".to_string())),
+    };
+    
+    // Ensure directory for tracking apps exists
+    let harissa = selection::client(None)?;
+    
+    // Saved apps have qualified names, so they are started in their own namespaces
+    let apps: Vec<_> = app_spec::read_dump(harissa.dir())?
+        .into_iter()
+        .filter(|(name, _)| only_namespace.is_none() || namespace::split(name).0 == only_namespace)
+        .collect();
+    let running = launcher::running_app_names(harissa.dir())?;
+    
+    // Dependencies start first, and each app waits until the apps it runs after are ready
+    let order = dependencies::start_order(&apps)?;
+    let mut tracker = StartTracker::default();
+    for name in &running {
+        tracker.running(name);
+    }
+    
+    let mut started_count = 0;
+    let mut failed_count = 0;
+    
+    for index in order {
+        let (name, spec) = &apps[index];
+        if running.contains(name) {
+            println!("{} is already running, skipping", name);
+            continue;
+        }
+        
+        if let Err(e) = tracker.wait_for_dependencies(spec, &selection::print_notice) {
+            println!("Not starting {}: {}", name, e);
+            tracker.failed(name);
+            failed_count += 1;
+            continue;
+        }
+        
+        match harissa.start(name, spec) {
+            Ok(app) => {
+                println!("Started {} with ID {} and PID {}", app.name, app.id, app.pid);
+                tracker.started(name, app.pid);
+                started_count += 1;
+            },
+            Err(e) => {
+                println!("Failed to start {}: {}", name, e);
+                tracker.failed(name);
+                failed_count += 1;
+            }
+        }
+    }
+    
+    println!("Resurrected {} application(s).", started_count);
+    
+    if failed_count > 0 {
+        return Err(HarissaError::Failed(format!("{} application(s) failed to start.", failed_count)));
+    }
+    
+    Ok(())
+}
@@ -1,7 +1,9 @@
+use harissa::error::HarissaError;
+
 /// Example of a run command
-pub fn run_command(args: &[String]) -> Result<(), String> {
+pub fn run_command(args: &[String]) -> Result<(), HarissaError> {
     if args.is_empty() {
-        return Err("The run command requires at least one argument".to_string());
+        return Err(HarissaError::Usage("The run command requires at least one argument".to_string()));
     }
     
     println!("Running with arguments: {:?}", args);
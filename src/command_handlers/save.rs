@@ -0,0 +1,96 @@
+use std::fs::File;
+use std::io::{self, BufRead};
+use harissa::app_spec;
+use harissa::error::HarissaError;
+use harissa::home;
+use harissa::namespace;
+use harissa::process_identity;
+
+
+/// Save the launch spec of every running application so 'resurrect' can restart them
+pub fn save_command(args: &[String]) -> Result<(), HarissaError> {
+    let only_namespace = match args {
+        [] => None,
+        [option, value] if option == "--namespace" => {
+            namespace::validate(value).map_err(HarissaError::Usage)?;
+            Some(value.as_str())
+        },
+        _ => return Err(HarissaError::Usage("Usage: save [--namespace namespace]".to_string())),
+    };
+
+    // Get the harissa directory
+    let harissa_dir = home::harissa_dir()?;
+    let processes_file = harissa_dir.join("processes.csv");
+    
+    if !processes_file.exists() {
+        return Err(HarissaError::NotFound("No applications are currently running. Nothing to save.".to_string()));
+    }
+
+    // Read the processes file
+    let file = File::open(&processes_file)
+        .map_err(|e| HarissaError::io("Failed to open processes file", e))?;
+    let reader = io::BufReader::new(file);
+    
+    let mut apps = Vec::new();
+    
+    // Process each line
+    for line in reader.lines() {
+        let line = line.map_err(|e| HarissaError::io("Failed to read line", e))?;
+        let parts: Vec<&str> = line.split(',').collect();
+        
+        if parts.len() < 3 {
+            // Skip invalid lines
+            continue;
+        }
+        
+        let pid = parts[0];
+        let name = parts[1];
+        
+        if only_namespace.is_some() && namespace::split(name).0 != only_namespace {
+            continue;
+        }
+        
+        // Only running applications are saved
+        if !process_identity::is_running(&harissa_dir, name, pid) {
+            continue;
+        }
+        
+        // Root only saves what it would run itself, as 'resurrect' runs it as root
+        let trusted = app_spec::load_trusted(&harissa_dir, name)
+            .and_then(|spec| if spec.argv.is_empty() { app_spec::check_trusted(&processes_file).map(|_| spec) } else { Ok(spec) });
+        let mut spec = match trusted {
+            Err(HarissaError::PermissionDenied(message)) => {
+                println!("Not saving {}: {}", name, message);
+                continue;
+            },
+            result => result?,
+        };
+        // Apps started by older versions only have the command stored in processes.csv
+        if spec.argv.is_empty() {
+            spec.argv = parts[2..].join(",").split_whitespace().map(|part| part.to_string()).collect();
+        }
+        
+        println!("Saving {} ({})", name, spec.command_line());
+        apps.push((name.to_string(), spec));
+    }
+    
+    if apps.is_empty() {
+        return Err(HarissaError::NotFound("No running applications found to save.".to_string()));
+    }
+    
+    let saved_count = apps.len();
+    
+    // Saving one namespace keeps what was saved for the others
+    if only_namespace.is_some() && app_spec::dump_path(&harissa_dir).exists() {
+        let others = app_spec::read_dump(&harissa_dir)?
+            .into_iter()
+            .filter(|(name, _)| namespace::split(name).0 != only_namespace);
+        apps.extend(others);
+    }
+    
+    app_spec::write_dump(&harissa_dir, &apps)?;
+    
+    println!("Saved {} application(s) to {}", saved_count, app_spec::dump_path(&harissa_dir).display());
+    println!("Run 'harissa resurrect' to start them again.");
+    Ok(())
+}
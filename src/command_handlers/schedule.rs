@@ -0,0 +1,98 @@
+use std::env;
+use harissa::error::HarissaError;
+use harissa::home;
+use harissa::supervisor::{self, CronSchedule, ScheduleEntry, ScheduleKind};
+use super::selection;
+
+/// Run a short-lived command on a cron schedule, or list and remove schedules
+pub fn schedule_command(args: &[String]) -> Result<(), HarissaError> {
+    let harissa_dir = home::harissa_dir()?;
+
+    if args.is_empty() {
+        let entries = supervisor::read_schedules(&harissa_dir)?;
+        if entries.is_empty() {
+            println!("No schedules are configured.");
+            return Ok(());
+        }
+
+        println!("{:<10} {:<20} {:<20} {:<30}", "KIND", "NAME", "SCHEDULE", "COMMAND");
+        println!("{:-<80}", "");
+        for entry in entries {
+            println!("{:<10} {:<20} {:<20} {:<30}", entry.kind.as_str(), entry.name, entry.cron, entry.command_line());
+        }
+        return Ok(());
+    }
+
+    if args[0] == "remove" {
+        if args.len() < 2 {
+            return Err(HarissaError::Usage("Usage: schedule remove <job name>".to_string()));
+        }
+
+        let name = args[1..].join(" ");
+        let removed = supervisor::remove_schedules(&harissa_dir, ScheduleKind::Job, &name)?;
+        if removed == 0 {
+            return Err(HarissaError::NotFound(format!("No scheduled job found with name '{}'", name)));
+        }
+
+        println!("Removed scheduled job {}", name);
+        return Ok(());
+    }
+
+    if args.len() < 2 {
+        return Err(HarissaError::Usage("Usage: schedule \"<cron>\" <command> [args...] [-n name]\nExample: schedule \"0 3 * * *\" ./backup.sh -n nightly_backup".to_string()));
+    }
+
+    let cron = args[0].clone();
+    CronSchedule::parse(&cron).map_err(HarissaError::Usage)?;
+
+    // Extract the name if provided with -n flag
+    let mut job_name = None;
+    let mut filtered_args = Vec::new();
+
+    let mut i = 1;
+    while i < args.len() {
+        if args[i] == "--" {
+            // Everything after `--` is the command, even arguments starting with a dash
+            filtered_args.extend_from_slice(&args[i + 1..]);
+            break;
+        } else if args[i] == "-n" {
+            let mut name_parts = Vec::new();
+            i += 1; // Skip the -n flag
+
+            while i < args.len() && !args[i].starts_with('-') {
+                name_parts.push(args[i].clone());
+                i += 1;
+            }
+
+            if !name_parts.is_empty() {
+                job_name = Some(name_parts.join(" "));
+            }
+        } else {
+            filtered_args.push(args[i].clone());
+            i += 1;
+        }
+    }
+
+    if filtered_args.is_empty() {
+        return Err(HarissaError::Usage("No command specified after removing flags".to_string()));
+    }
+
+    let job_name = job_name.unwrap_or_else(|| filtered_args[0].clone());
+    // Relative commands and paths are resolved from here, wherever the supervisor was started
+    let cwd = env::current_dir().map_err(|e| HarissaError::io("Could not get current directory", e))?;
+
+    supervisor::add_schedule(&harissa_dir, ScheduleEntry {
+        kind: ScheduleKind::Job,
+        name: job_name.clone(),
+        cron: cron.clone(),
+        argv: filtered_args,
+        cwd: Some(cwd),
+    })?;
+
+    println!("Scheduled job {} to run at \"{}\"", job_name, cron);
+    println!("Output of each run is appended to:");
+    println!("  Stdout: {}", home::log_path(&harissa_dir, &job_name, "out").display());
+    println!("  Stderr: {}", home::log_path(&harissa_dir, &job_name, "err").display());
+
+    supervisor::ensure_running(&harissa_dir, &selection::print_notice)
+}
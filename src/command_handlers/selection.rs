@@ -0,0 +1,108 @@
+// Selecting the applications that kill, reload and similar commands act on, and the client
+// that acts on them
+use std::io::{self, BufRead, IsTerminal, Write};
+use harissa::client::{AppRef, Harissa};
+use harissa::error::HarissaError;
+use harissa::notice::Notice;
+
+/// The applications given on the command line
+pub struct Selection {
+    /// Only applications of this namespace are selected, by their names within it
+    pub namespace: Option<String>,
+    /// None when neither a selector nor `--tag` was given
+    pub target: Option<AppRef>,
+    /// Whether `--yes` skips the confirmation when several applications match
+    pub yes: bool,
+}
+
+/// Parse `[selector...] [--tag tag] [--namespace namespace] [--yes]`,
+/// where the words of the selector form one name
+pub fn parse(args: &[String], usage: &str) -> Result<Selection, HarissaError> {
+    let mut words = Vec::new();
+    let mut tag = None;
+    let mut namespace = None;
+    let mut yes = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--tag" | "--namespace" => {
+                let value = args.get(i + 1)
+                    .ok_or_else(|| HarissaError::Usage(format!("{} requires a value\n{}", args[i], usage)))?;
+                if args[i] == "--tag" { tag = Some(value.clone()) } else { namespace = Some(value.clone()) }
+                i += 2;
+            },
+            "--yes" | "-y" => {
+                yes = true;
+                i += 1;
+            },
+            _ => {
+                words.push(args[i].clone());
+                i += 1;
+            }
+        }
+    }
+
+    let target = match (words.is_empty(), tag) {
+        (true, None) => None,
+        (true, Some(tag)) => Some(AppRef::Tag(tag)),
+        (false, None) => Some(AppRef::parse(&words.join(" "))),
+        (false, Some(_)) => return Err(HarissaError::Usage(format!("Give either an application or --tag, not both\n{}", usage))),
+    };
+
+    Ok(Selection { namespace, target, yes })
+}
+
+impl Selection {
+    /// The client managing the selected namespace, or every application without one
+    pub fn client(&self) -> Result<Harissa, HarissaError> {
+        client(self.namespace.as_deref())
+    }
+}
+
+/// The client for `--namespace`, or for every application without it. It prints its warnings
+/// and progress as they come.
+pub fn client(namespace: Option<&str>) -> Result<Harissa, HarissaError> {
+    let harissa = Harissa::new()?.with_observer(print_notice);
+    match namespace {
+        Some(namespace) => harissa.in_namespace(namespace),
+        None => Ok(harissa),
+    }
+}
+
+/// Print a warning or progress reported by the library
+pub fn print_notice(notice: Notice) {
+    println!("{}", notice);
+}
+
+/// Ask before `action` (e.g. "kill") is applied to more than one application, given by the
+/// names `target` matches. Without a terminal to ask on, `--yes` is required instead.
+pub fn confirm(names: &[String], target: &AppRef, action: &str, yes: bool) -> Result<(), HarissaError> {
+    if names.len() <= 1 || yes {
+        return Ok(());
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(HarissaError::Usage(format!("{} applications match {}: {}\nPass --yes to {} them all",
+            names.len(), target, names.join(", "), action)));
+    }
+
+    print!("{} {} applications ({})? [y/N] ", capitalize(action), names.len(), names.join(", "));
+    io::stdout().flush().map_err(|e| HarissaError::io("Failed to write to stdout", e))?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).map_err(|e| HarissaError::io("Failed to read the answer", e))?;
+
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(HarissaError::Failed("Aborted".to_string())),
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+use harissa::control_socket;
+use harissa::error::HarissaError;
+use harissa::home;
+
+/// Serve the JSON-RPC control API on a Unix socket
+pub fn serve_api_command(args: &[String]) -> Result<(), HarissaError> {
+    let harissa_dir = home::harissa_dir()?;
+    let mut socket_path = control_socket::socket_path(&harissa_dir);
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--socket" => {
+                socket_path = PathBuf::from(args.get(i + 1).ok_or_else(|| HarissaError::Usage("--socket requires a path".to_string()))?);
+                i += 2;
+            },
+            other => return Err(HarissaError::Usage(format!("Unknown serve-api option: {}\nUsage: serve-api [--socket path]", other))),
+        }
+    }
+
+    if let Some(socket_dir) = socket_path.parent() {
+        home::create_dir(socket_dir)?;
+    }
+
+    control_socket::serve(&harissa_dir, &socket_path)
+}
@@ -0,0 +1,31 @@
+use std::net::TcpListener;
+use harissa::error::HarissaError;
+use harissa::metrics;
+use harissa::home;
+
+const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:9615";
+
+/// Serve application metrics in the Prometheus text format
+pub fn serve_metrics_command(args: &[String]) -> Result<(), HarissaError> {
+    let mut listen = DEFAULT_LISTEN_ADDRESS.to_string();
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--listen" => {
+                listen = args.get(i + 1)
+                    .ok_or_else(|| HarissaError::Usage("--listen requires an address, e.g. --listen 127.0.0.1:9615".to_string()))?
+                    .clone();
+                i += 2;
+            },
+            other => return Err(HarissaError::Usage(format!("Unknown serve-metrics option: {}\nUsage: serve-metrics [--listen address:port]", other))),
+        }
+    }
+
+    let harissa_dir = home::harissa_dir()?;
+    let listener = TcpListener::bind(&listen)
+        .map_err(|e| HarissaError::io(&format!("Failed to listen on {}", listen), e))?;
+
+    println!("Serving metrics on http://{}/metrics", listen);
+    metrics::serve(listener, &harissa_dir)
+}
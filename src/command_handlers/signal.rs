@@ -0,0 +1,37 @@
+use harissa::error::HarissaError;
+use harissa::signals;
+use super::selection;
+
+/// Send a signal to the running applications matching a selector
+pub fn signal_command(args: &[String]) -> Result<(), HarissaError> {
+    let usage = "Usage: signal <signal> <ID | app name | glob | /regex/ | all | PID> [--group] [--tag tag] [--namespace namespace] [--yes]\nExample: signal SIGHUP api\nExample: signal usr1 'worker-*' --group";
+    let Some((signal, args)) = args.split_first() else {
+        return Err(HarissaError::Usage(usage.to_string()));
+    };
+    let signal = signals::parse(signal).map_err(|e| HarissaError::Usage(format!("{}\n{}", e, usage)))?;
+
+    let group = args.iter().any(|arg| arg == "--group");
+    let args: Vec<String> = args.iter().filter(|arg| *arg != "--group").cloned().collect();
+    let selection = selection::parse(&args, usage)?;
+    let target = selection.target.clone().ok_or_else(|| HarissaError::Usage(usage.to_string()))?;
+
+    let harissa = selection.client()?;
+    let action = format!("send {} to", signals::name(signal));
+    selection::confirm(&harissa.names(&target)?, &target, &action, selection.yes)?;
+
+    let mut failure = None;
+    for app in harissa.signal(&target, signal, group)? {
+        match app.result {
+            Ok(()) => println!("Sent {} to {} ({}){}", signals::name(signal), app.name, app.pid, if group { " and its process group" } else { "" }),
+            Err(error) => {
+                println!("Failed to send {} to {} ({}): {}", signals::name(signal), app.name, app.pid, error);
+                failure.get_or_insert(error);
+            },
+        }
+    }
+
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
@@ -1,19 +1,34 @@
-use std::process::{Command, Stdio};
-use std::fs::{File, OpenOptions};
-use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::env;
-
-const HARISSA_APPS_DIR: &str = ".harissa_apps";
+use harissa::app_spec::AppSpec;
+use harissa::cgroup;
+use harissa::client::{AppRef, Harissa};
+use harissa::error::HarissaError;
+use harissa::home;
+use harissa::hooks;
+use harissa::process_attrs;
+use harissa::procfile;
+use harissa::supervisor::CronSchedule;
+use super::selection;
 
 /// Start a command in the background and track it
-pub fn start_command(args: &[String]) -> Result<(), String> {
+pub fn start_command(args: &[String]) -> Result<(), HarissaError> {
     if args.is_empty() {
-        return Err("Usage: start <command> [args...]\nExample: start node app.js -n my_app".to_string());
+        return Err(HarissaError::Usage("Usage: start <command> [args...]\nExample: start node app.js -n my_app".to_string()));
+    }
+
+    // `start <name>` starts a stopped application again
+    if start_stopped(args)? {
+        return Ok(());
     }
 
     // Extract the name if provided with -n flag
     let mut app_name = None;
+    let mut spec = AppSpec::default();
+    let mut procfile_path = None;
+    let mut formation = None;
+    let mut force = false;
+    let mut namespace = None;
     let mut filtered_args = Vec::new();
     
     let mut i = 0;
@@ -35,73 +50,225 @@ pub fn start_command(args: &[String]) -> Result<(), String> {
                 // If we immediately hit another flag or the end, use a default name
                 app_name = Some(format!("app_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()));
             }
+        } else if args[i] == "--force" {
+            force = true;
+            i += 1;
+        } else if args[i] == "--cron-restart" {
+            let expression = args.get(i + 1)
+                .ok_or_else(|| HarissaError::Usage("--cron-restart requires a cron expression, e.g. --cron-restart \"0 3 * * *\"".to_string()))?;
+            CronSchedule::parse(expression).map_err(HarissaError::Usage)?;
+            spec.cron_restart = Some(expression.clone());
+            i += 2;
+        } else if cgroup::LIMIT_OPTIONS.contains(&args[i].as_str()) {
+            let value = args.get(i + 1)
+                .ok_or_else(|| HarissaError::Usage(format!("{} requires a value", args[i])))?;
+            spec.limits.set_option(&args[i], value).map_err(HarissaError::Usage)?;
+            i += 2;
+        } else if process_attrs::ATTRIBUTE_OPTIONS.contains(&args[i].as_str()) {
+            let value = args.get(i + 1)
+                .ok_or_else(|| HarissaError::Usage(format!("{} requires a value", args[i])))?;
+            spec.attributes.set_option(&args[i], value).map_err(HarissaError::Usage)?;
+            i += 2;
+        } else if hooks::HOOK_OPTIONS.contains(&args[i].as_str()) {
+            let hook_command = args.get(i + 1)
+                .ok_or_else(|| HarissaError::Usage(format!("{} requires a command", args[i])))?;
+            spec.hooks.set_option(&args[i], hook_command).map_err(HarissaError::Usage)?;
+            i += 2;
+        } else if args[i] == "--user" || args[i] == "--group" {
+            let value = args.get(i + 1)
+                .ok_or_else(|| HarissaError::Usage(format!("{} requires a user or group name", args[i])))?;
+            if args[i] == "--user" { spec.user = Some(value.clone()) } else { spec.group = Some(value.clone()) }
+            i += 2;
+        } else if args[i] == "--after" {
+            let dependencies = args.get(i + 1)
+                .ok_or_else(|| HarissaError::Usage("--after requires the name of an application".to_string()))?;
+            spec.after.extend(dependencies.split(',').map(|name| name.trim().to_string()).filter(|name| !name.is_empty()));
+            i += 2;
+        } else if args[i] == "--env" {
+            let variable = args.get(i + 1)
+                .ok_or_else(|| HarissaError::Usage("--env requires a variable, e.g. --env PORT=8080".to_string()))?;
+            let (name, value) = variable.split_once('=')
+                .ok_or_else(|| HarissaError::Usage(format!("--env takes NAME=value, not '{}'", variable)))?;
+            spec.env.retain(|(set, _)| set != name);
+            spec.env.push((name.to_string(), value.to_string()));
+            if !spec.explicit_env.iter().any(|set| set == name) {
+                spec.explicit_env.push(name.to_string());
+            }
+            i += 2;
+        } else if args[i] == "--namespace" {
+            let value = args.get(i + 1)
+                .ok_or_else(|| HarissaError::Usage("--namespace requires a namespace, e.g. --namespace staging".to_string()))?;
+            namespace = Some(value.clone());
+            i += 2;
+        } else if args[i] == "--tag" {
+            let tags = args.get(i + 1)
+                .ok_or_else(|| HarissaError::Usage("--tag requires a tag, e.g. --tag backend".to_string()))?;
+            spec.tags.extend(tags.split(',').map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty()));
+            i += 2;
+        } else if args[i] == "--procfile" || args[i] == "--formation" {
+            let value = args.get(i + 1)
+                .ok_or_else(|| HarissaError::Usage(format!("{} requires a value", args[i])))?;
+            if args[i] == "--procfile" { procfile_path = Some(PathBuf::from(value)) } else { formation = Some(value.clone()) }
+            i += 2;
         } else {
             filtered_args.push(args[i].clone());
             i += 1;
         }
     }
 
-    // If no name was provided, use the command as the name
-    let app_name = app_name.unwrap_or_else(|| filtered_args[0].clone());
-    
+    // Ensure directory for tracking apps exists
+    let harissa = selection::client(namespace.as_deref())?;
+
+    if let Some(procfile_path) = procfile_path {
+        if !filtered_args.is_empty() {
+            return Err(HarissaError::Usage("--procfile cannot be combined with a command".to_string()));
+        }
+        return start_procfile(&harissa, &procfile_path, formation.as_deref(), app_name, spec, force);
+    }
+
+    if formation.is_some() {
+        return Err(HarissaError::Usage("--formation can only be used with --procfile".to_string()));
+    }
+
     // Ensure the command exists
     if filtered_args.is_empty() {
-        return Err("No command specified after removing flags".to_string());
+        return Err(HarissaError::Usage("No command specified after removing flags".to_string()));
     }
 
-    // Create the command
-    let command = &filtered_args[0];
-    let args = &filtered_args[1..];
+    // If no name was provided, use the command as the name
+    let app_name = app_name.unwrap_or_else(|| filtered_args[0].clone());
 
-    // Ensure directory for tracking apps exists
-    let home_dir = env::var("HOME").map_err(|e| format!("Could not get HOME directory: {}", e))?;
-    let harissa_dir = Path::new(&home_dir).join(HARISSA_APPS_DIR);
-    
-    if !harissa_dir.exists() {
-        std::fs::create_dir_all(&harissa_dir)
-            .map_err(|e| format!("Failed to create directory for tracking apps: {}", e))?;
-    }
+    // Capture everything needed to launch the app the same way again later
+    spec.argv = filtered_args;
+    spec.cwd = Some(env::current_dir().map_err(|e| HarissaError::io("Could not get current directory", e))?);
+    spec.env = with_environment(&spec.env);
 
-    // Prepare log files
-    let stdout_log = harissa_dir.join(format!("{}.out.log", app_name));
-    let stderr_log = harissa_dir.join(format!("{}.err.log", app_name));
-    
-    let stdout_file = File::create(&stdout_log)
-        .map_err(|e| format!("Failed to create stdout log file: {}", e))?;
-    let stderr_file = File::create(&stderr_log)
-        .map_err(|e| format!("Failed to create stderr log file: {}", e))?;
-
-    // Start the process
-    let child = Command::new(command)
-        .args(args)
-        .stdout(Stdio::from(stdout_file))
-        .stderr(Stdio::from(stderr_file))
-        .spawn()
-        .map_err(|e| format!("Failed to start command: {}", e))?;
-
-    // Get the PID
-    let pid = child.id();
-    
-    // Detach the child process - we don't need to wait for it
-    std::mem::forget(child);
-    
-    // Save the process info
-    let process_info = format!("{},{},{}", pid, app_name, filtered_args.join(" "));
-    let processes_file = harissa_dir.join("processes.csv");
-    
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(processes_file)
-        .map_err(|e| format!("Failed to open processes file: {}", e))?;
-        
-    writeln!(file, "{}", process_info)
-        .map_err(|e| format!("Failed to write to processes file: {}", e))?;
-
-    println!("Started {} with PID {} in the background", app_name, pid);
+    let app = if force { harissa.replace(&app_name, &spec)? } else { harissa.start(&app_name, &spec)? };
+
+    println!("Started {} with ID {} and PID {} in the background", app.name, app.id, app.pid);
     println!("Logs available at:");
-    println!("  Stdout: {}", stdout_log.display());
-    println!("  Stderr: {}", stderr_log.display());
+    println!("  Stdout: {}", home::log_path(harissa.dir(), &app.name, "out").display());
+    println!("  Stderr: {}", home::log_path(harissa.dir(), &app.name, "err").display());
     
     Ok(())
 }
+
+/// The current environment with `variables` set on top of it
+fn with_environment(variables: &[(String, String)]) -> Vec<(String, String)> {
+    env::vars()
+        .filter(|(key, _)| !variables.iter().any(|(name, _)| name == key))
+        .chain(variables.iter().cloned())
+        .collect()
+}
+
+/// Start the stopped applications selected by `[--namespace namespace] <selector>`.
+/// Returns false when the arguments select no stopped application, so they are taken as a command.
+fn start_stopped(args: &[String]) -> Result<bool, HarissaError> {
+    let (namespace, selector) = match args {
+        [selector] => (None, selector),
+        [option, namespace, selector] if option == "--namespace" => (Some(namespace.as_str()), selector),
+        _ => return Ok(false),
+    };
+
+    let harissa = selection::client(namespace)?;
+    let target = AppRef::parse(selector);
+    if harissa.stopped_names(&target)?.is_empty() {
+        return Ok(false);
+    }
+
+    let mut failure = None;
+    for app in harissa.start_stopped(&target)? {
+        match app.result {
+            Ok(app) => println!("Started {} with ID {} and PID {} in the background", app.name, app.id, app.pid),
+            Err(error) => {
+                println!("Failed to start {}: {}", app.name, error);
+                failure.get_or_insert(error);
+            },
+        }
+    }
+
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(true),
+    }
+}
+
+/// Start every process type of a Procfile as `<project>.<type>.<n>` apps
+fn start_procfile(harissa: &Harissa, procfile_path: &Path, formation: Option<&str>, project: Option<String>, template: AppSpec, force: bool) -> Result<(), HarissaError> {
+    let procfile_path = procfile_path.canonicalize()
+        .map_err(|e| HarissaError::io(&format!("Failed to find Procfile {}", procfile_path.display()), e))?;
+    let project_dir = procfile_path.parent().unwrap_or(Path::new("/")).to_path_buf();
+
+    // The project is named after the Procfile's directory unless -n is given
+    let project = project.unwrap_or_else(|| {
+        project_dir.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| "app".to_string())
+    });
+
+    let entries = procfile::parse_procfile(&procfile_path)?;
+    let formation = procfile::parse_formation(formation.unwrap_or("")).map_err(HarissaError::Usage)?;
+
+    let mut unknown_types: Vec<&str> = formation.keys()
+        .map(String::as_str)
+        .filter(|process_type| *process_type != "all" && !entries.iter().any(|entry| entry.process_type == *process_type))
+        .collect();
+    if !unknown_types.is_empty() {
+        unknown_types.sort();
+        let known_types: Vec<&str> = entries.iter().map(|entry| entry.process_type.as_str()).collect();
+        return Err(HarissaError::Usage(format!(
+            "Unknown process type(s) in --formation: {}. The Procfile has {}",
+            unknown_types.join(", "),
+            known_types.join(", "),
+        )));
+    }
+
+    // Variables from .env override the current environment, and those of --env override .env
+    let mut dotenv = procfile::parse_env_file(&project_dir.join(".env"))?;
+    dotenv.retain(|(key, _)| !template.explicit_env.contains(key));
+    let mut explicit_env: Vec<String> = dotenv.iter().map(|(key, _)| key.clone()).collect();
+    explicit_env.extend(template.explicit_env.iter().cloned());
+    explicit_env.push("PORT".to_string());
+    dotenv.extend(template.env.iter().cloned());
+    let base_env = with_environment(&dotenv);
+
+    let mut started_count = 0;
+    let mut failed_count = 0;
+
+    for (type_index, entry) in entries.iter().enumerate() {
+        for instance in 1..=procfile::instance_count(&formation, &entry.process_type) {
+            let port = procfile::BASE_PORT + 100 * type_index as u32 + (instance as u32 - 1);
+            let name = format!("{}.{}.{}", project, entry.process_type, instance);
+            let command = procfile::expand_port(&entry.command, port);
+
+            let mut spec = template.clone();
+            spec.argv = vec!["sh".to_string(), "-c".to_string(), procfile::shell_script(&command)];
+            spec.cwd = Some(project_dir.clone());
+            spec.explicit_env = explicit_env.clone();
+            spec.env = base_env.iter()
+                .filter(|(key, _)| key != "PORT")
+                .cloned()
+                .chain(std::iter::once(("PORT".to_string(), port.to_string())))
+                .collect();
+
+            let started = if force { harissa.replace(&name, &spec) } else { harissa.start(&name, &spec) };
+            match started {
+                Ok(app) => {
+                    println!("Started {} with PID {} on port {}", app.name, app.pid, port);
+                    started_count += 1;
+                },
+                Err(e) => {
+                    println!("Failed to start {}: {}", name, e);
+                    failed_count += 1;
+                }
+            }
+        }
+    }
+
+    println!("Started {} process(es) from {}", started_count, procfile_path.display());
+    println!("Logs available in {}", harissa.dir().display());
+
+    if failed_count > 0 {
+        return Err(HarissaError::Failed(format!("{} process(es) failed to start.", failed_count)));
+    }
+
+    Ok(())
+}
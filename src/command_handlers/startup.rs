@@ -1,180 +1,184 @@
-use std::fs::File;
-use std::io::{self, BufRead, Write};
-use std::path::Path;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 use std::env;
 use std::process::Command;
+use crate::command_handlers::save_command;
+use harissa::credentials;
+use harissa::error::HarissaError;
+use harissa::home;
+use harissa::init_scripts::{self, InitArtifact, InitSystem, StartupTarget};
 
-const HARISSA_APPS_DIR: &str = ".harissa_apps";
-
-/// Detect the Linux distribution
-fn detect_linux_distribution() -> Result<String, String> {
-    // Check if /etc/os-release exists
-    if Path::new("/etc/os-release").exists() {
-        let output = Command::new("sh")
-            .args(["-c", "cat /etc/os-release | grep ^ID= | cut -d= -f2"])
-            .output()
-            .map_err(|e| format!("Failed to detect distribution: {}", e))?;
-            
-        let distro = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        
-        // Remove quotes if present
-        let distro = distro.trim_matches('"').trim_matches('\'').to_string();
-        
-        if !distro.is_empty() {
-            return Ok(distro);
+/// What the startup command was asked to do
+#[derive(PartialEq, Eq)]
+enum StartupAction {
+    Show,
+    Install,
+    Uninstall,
+}
+
+/// Generate, install or remove the boot-time hook that runs 'harissa resurrect'
+pub fn startup_command(args: &[String]) -> Result<(), HarissaError> {
+    let mut action = StartupAction::Show;
+    let mut init_name = None;
+    let mut user_mode = false;
+    let mut dry_run = false;
+    let mut target_user = None;
+    let mut target_home = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "install" if i == 0 => action = StartupAction::Install,
+            "uninstall" if i == 0 => action = StartupAction::Uninstall,
+            "--user" => user_mode = true,
+            "--dry-run" => dry_run = true,
+            "--init" | "-u" | "--hp" => {
+                let value = args.get(i + 1)
+                    .ok_or_else(|| HarissaError::Usage(format!("{} requires a value", args[i])))?
+                    .clone();
+                match args[i].as_str() {
+                    "--init" => init_name = Some(value),
+                    "-u" => target_user = Some(value),
+                    _ => target_home = Some(PathBuf::from(value)),
+                }
+                i += 1;
+            },
+            other => return Err(HarissaError::Usage(format!("Unknown startup option: {}\nUsage: startup [install | uninstall] [--init systemd|openrc|sysv] [--user] [-u user] [--hp home] [--dry-run]", other))),
         }
+        i += 1;
     }
-    
-    // Try lsb_release as fallback
-    let output = Command::new("lsb_release")
-        .args(["-is"])
-        .output();
-        
-    if let Ok(output) = output {
-        let distro = String::from_utf8_lossy(&output.stdout).trim().to_lowercase();
-        if !distro.is_empty() {
-            return Ok(distro);
-        }
+
+    let init = match init_name {
+        Some(name) => InitSystem::parse(&name, user_mode).map_err(HarissaError::Usage)?,
+        None => InitSystem::detect(user_mode),
+    };
+
+    let home_dir = env::var("HOME").map_err(|e| HarissaError::System(format!("Could not get HOME directory: {}", e)))?;
+    let current_user = env::var("USER")
+        .unwrap_or_else(|_| if credentials::is_root() { "root".to_string() } else { "user".to_string() });
+
+    let target = StartupTarget {
+        user: target_user.unwrap_or(current_user),
+        home: target_home.unwrap_or_else(|| PathBuf::from(&home_dir)),
+        harissa_path: env::current_exe()
+            .map_err(|e| HarissaError::io("Failed to get harissa executable path", e))?,
+        harissa_home: home::custom().map(|harissa_home| home::absolute(&harissa_home)).transpose()?,
+    };
+
+    let artifact = init_scripts::generate(init, &target);
+
+    match action {
+        StartupAction::Show => {
+            println!("Startup file for {} ({}):\n", init.as_str(), artifact.path.display());
+            println!("{}", artifact.contents);
+            println!("Run 'harissa startup install' to install it, or add --dry-run to see every step.");
+            if init.is_system_wide() && !credentials::is_root() {
+                print_sudo_hint(init, &target);
+            }
+            Ok(())
+        },
+        StartupAction::Install => install(init, &target, &artifact, &home_dir, dry_run),
+        StartupAction::Uninstall => uninstall(&artifact, dry_run),
     }
-    
-    // Default fallback
-    Ok("linux".to_string())
 }
 
-/// Generate a startup script and system service setup command to auto-start applications on system boot
-pub fn startup_command(args: &[String]) -> Result<(), String> {
-    // Check if we need to determine the distribution
-    let detect_distro = args.is_empty() || args[0] != "--skip-detect";
-    // Get the current user for systemd user services
-    let _user = env::var("USER").unwrap_or_else(|_| "user".to_string());
-    let home_dir = env::var("HOME").map_err(|e| format!("Could not get HOME directory: {}", e))?;
-    
-    // Detect Linux distribution if needed
-    let distribution = if detect_distro {
-        detect_linux_distribution()?
+fn install(init: InitSystem, target: &StartupTarget, artifact: &InitArtifact, home_dir: &str, dry_run: bool) -> Result<(), HarissaError> {
+    if dry_run {
+        println!("Would write {}:\n", artifact.path.display());
+        println!("{}", artifact.contents);
+        for command in &artifact.enable_commands {
+            println!("Would run: {}", command.join(" "));
+        }
+        return Ok(());
+    }
+
+    if init.is_system_wide() && !credentials::is_root() {
+        print_sudo_hint(init, target);
+        return Err(HarissaError::PermissionDenied("Installing a system startup service requires root.".to_string()));
+    }
+
+    // Snapshot the running applications; the boot hook restores exactly this set.
+    // When installing for another user, that user runs 'harissa save' themselves.
+    if target.home == Path::new(home_dir) {
+        if let Err(e) = save_command(&[]) {
+            println!("Warning: {}", e);
+        }
     } else {
-        "linux".to_string()
-    };
-    // Get the harissa directory
-    let harissa_dir = Path::new(&home_dir).join(HARISSA_APPS_DIR);
-    let processes_file = harissa_dir.join("processes.csv");
-    
-    if !processes_file.exists() {
-        return Err("No applications are currently running. Nothing to add to startup.".to_string());
+        println!("Run 'harissa save' as {} to choose the applications started at boot.", target.user);
+    }
+
+    if let Some(parent) = artifact.path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| HarissaError::io(&format!("Failed to create directory {}", parent.display()), e))?;
+    }
+
+    fs::write(&artifact.path, &artifact.contents)
+        .map_err(|e| HarissaError::io(&format!("Failed to write {}", artifact.path.display()), e))?;
+
+    let mode = if artifact.executable { 0o755 } else { 0o644 };
+    fs::set_permissions(&artifact.path, fs::Permissions::from_mode(mode))
+        .map_err(|e| HarissaError::io(&format!("Failed to set permissions of {}", artifact.path.display()), e))?;
+
+    println!("Wrote {}", artifact.path.display());
+
+    for command in &artifact.enable_commands {
+        run(command)?;
+    }
+
+    println!("Harissa applications will be resurrected at boot using {}.", init.as_str());
+    if init == InitSystem::SystemdUser {
+        println!("To start them at boot without logging in, run: sudo loginctl enable-linger {}", target.user);
     }
 
-    // Read the processes file
-    let file = File::open(&processes_file)
-        .map_err(|e| format!("Failed to open processes file: {}", e))?;
-    
-    let reader = io::BufReader::new(file);
-    let mut script_contents = String::new();
-    
-    // Add shebang and header
-    script_contents.push_str("#!/bin/bash\n\n");
-    script_contents.push_str("# Auto-generated startup script for harissa applications\n");
-    script_contents.push_str("# Generated on: ");
-    script_contents.push_str(&chrono::Local::now().to_string());
-    script_contents.push_str("\n\n");
-    
-    let harissa_path = env::current_exe()
-        .map_err(|e| format!("Failed to get harissa executable path: {}", e))?;
-    
-    script_contents.push_str("# Path to harissa executable\n");
-    script_contents.push_str(&format!("HARISSA=\"{}\"\n\n", harissa_path.display()));
-    
-    let mut app_count = 0;
-    
-    // Process each line
-    for line in reader.lines() {
-        let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
-        let parts: Vec<&str> = line.split(',').collect();
-        
-        if parts.len() < 3 {
-            // Skip invalid lines
-            continue;
+    Ok(())
+}
+
+fn uninstall(artifact: &InitArtifact, dry_run: bool) -> Result<(), HarissaError> {
+    if dry_run {
+        for command in &artifact.disable_commands {
+            println!("Would run: {}", command.join(" "));
         }
-        
-        let name = parts[1];
-        let command = parts[2..].join(",");
-        
-        script_contents.push_str(&format!("# Start {}\n", name));
-        script_contents.push_str(&format!("$HARISSA start -n \"{}\" {}\n\n", name, command));
-        
-        app_count += 1;
+        println!("Would remove {}", artifact.path.display());
+        return Ok(());
     }
-    
-    if app_count == 0 {
-        return Err("No valid applications found to add to startup.".to_string());
+
+    if !artifact.path.exists() {
+        return Err(HarissaError::NotFound(format!("No startup file installed at {}", artifact.path.display())));
     }
 
-    // Create startup script path
-    let startup_script_path = Path::new(&home_dir).join("harissa_startup.sh");
-    
-    // Write the script to a file
-    let mut script_file = File::create(&startup_script_path)
-        .map_err(|e| format!("Failed to create startup script: {}", e))?;
-    
-    write!(script_file, "{}", script_contents)
-        .map_err(|e| format!("Failed to write to startup script: {}", e))?;
-    
-    // Make the script executable
-    Command::new("chmod")
-        .args(["+x", startup_script_path.to_str().unwrap()])
-        .output()
-        .map_err(|e| format!("Failed to make startup script executable: {}", e))?;
-    
-    // Print out the script
-    println!("\nStartup script saved to: {}", startup_script_path.display());
-    println!("\nScript contents:");
-    println!("{}", script_contents);
-    
-    // Generate the system service installation command
-    println!("\nTo install as a system service, run the following command:");
-    
-    let path_var = env::var("PATH").unwrap_or_else(|_| "/usr/local/bin:/usr/bin:/bin".to_string());
-    
-    match distribution.as_str() {
-        "ubuntu" | "debian" | "linuxmint" => {
-            println!("sudo su -c \"env PATH={} {} startup --skip-detect > /etc/init.d/harissa-startup && chmod +x /etc/init.d/harissa-startup && update-rc.d harissa-startup defaults\" -s /bin/sh",
-                     path_var, harissa_path.display());
-        },
-        "fedora" | "rhel" | "centos" | "rocky" | "almalinux" => {
-            println!("sudo su -c \"env PATH={} {} startup --skip-detect > /etc/systemd/system/harissa-startup.service && systemctl enable harissa-startup.service\" -s /bin/sh",
-                     path_var, harissa_path.display());
-        },
-        "arch" | "manjaro" => {
-            println!("sudo su -c \"env PATH={} {} startup --skip-detect > /etc/systemd/system/harissa-startup.service && systemctl enable harissa-startup.service\" -s /bin/sh",
-                     path_var, harissa_path.display());
-        },
-        _ => {
-            // Generic systemd (most Linux distributions use systemd nowadays)
-            println!("sudo su -c \"env PATH={} {} startup --skip-detect > /etc/systemd/system/harissa-startup.service && systemctl enable harissa-startup.service\" -s /bin/sh",
-                     path_var, harissa_path.display());
-            println!("\nIf your system doesn't use systemd, you can add the startup script to /etc/rc.local or the appropriate startup mechanism for your distribution.");
+    for command in &artifact.disable_commands {
+        if let Err(e) = run(command) {
+            println!("Warning: {}", e);
         }
     }
-    
-    // Instructions for user-level startup (without sudo)
-    println!("\nFor user-level startup (without sudo):");
-    println!("mkdir -p ~/.config/systemd/user/");
-    println!("cat << EOF > ~/.config/systemd/user/harissa.service");
-    println!("[Unit]");
-    println!("Description=Harissa Applications Startup");
-    println!("After=network.target");
-    println!("");
-    println!("[Service]");
-    println!("Type=oneshot");
-    println!("ExecStart=/bin/bash {}", startup_script_path.display());
-    println!("");
-    println!("[Install]");
-    println!("WantedBy=default.target");
-    println!("EOF");
-    println!("systemctl --user enable harissa.service");
-    
-    println!("\nOr for crontab:");
-    println!("crontab -e");
-    println!("Then add: @reboot {}", startup_script_path.display());
-    
+
+    fs::remove_file(&artifact.path)
+        .map_err(|e| HarissaError::io(&format!("Failed to remove {}", artifact.path.display()), e))?;
+
+    println!("Removed {}", artifact.path.display());
+    Ok(())
+}
+
+/// Run an enable/disable command, failing if it does not succeed
+fn run(command: &[String]) -> Result<(), HarissaError> {
+    println!("Running: {}", command.join(" "));
+
+    let status = Command::new(&command[0])
+        .args(&command[1..])
+        .status()
+        .map_err(|e| HarissaError::io(&format!("Failed to execute {}", command[0]), e))?;
+
+    if !status.success() {
+        return Err(HarissaError::Failed(format!("'{}' failed with {}", command.join(" "), status)));
+    }
+
     Ok(())
 }
+
+fn print_sudo_hint(init: InitSystem, target: &StartupTarget) {
+    let path_var = env::var("PATH").unwrap_or_else(|_| "/usr/local/bin:/usr/bin:/bin".to_string());
+    println!("\nTo install as a system service, run the following command:");
+    println!("sudo env PATH={} {} startup install --init {} -u {} --hp {}",
+             path_var, target.harissa_path.display(), init.as_str(), target.user, target.home.display());
+}
@@ -0,0 +1,34 @@
+use harissa::client::StopOutcome;
+use harissa::error::HarissaError;
+use super::selection;
+
+/// Stop the matching applications, keeping them to be started again with `start <name>`
+pub fn stop_command(args: &[String]) -> Result<(), HarissaError> {
+    let usage = "Usage: stop <ID | app name | glob | /regex/ | all | PID> [--tag tag] [--namespace namespace] [--yes]\nExample: stop api";
+    let selection = selection::parse(args, usage)?;
+    let target = selection.target.clone().ok_or_else(|| HarissaError::Usage(usage.to_string()))?;
+
+    let harissa = selection.client()?;
+    selection::confirm(&harissa.names(&target)?, &target, "stop", selection.yes)?;
+    let stopped = harissa.stop(&target)?;
+
+    let mut failure = None;
+    for app in &stopped {
+        match &app.outcome {
+            StopOutcome::Stopped => println!("Stopped {} ({})", app.name, app.pid),
+            StopOutcome::NotRunning => println!("{} ({}) was not running; marked as stopped", app.name, app.pid),
+            StopOutcome::Failed(error) => {
+                println!("Failed to stop {} ({}): {}", app.name, app.pid, error);
+                failure.get_or_insert_with(|| error.clone());
+            },
+        }
+        if app.removed_schedule {
+            println!("Removed scheduled restart of {} until it is started again", app.name);
+        }
+    }
+
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,9 @@
+use harissa::error::HarissaError;
+use harissa::home;
+use harissa::supervisor;
+
+/// Run the scheduling supervisor in the foreground (normally started automatically)
+pub fn supervise_command(_args: &[String]) -> Result<(), HarissaError> {
+    let harissa_dir = home::harissa_dir()?;
+    supervisor::run(&harissa_dir)
+}
@@ -0,0 +1,45 @@
+use std::net::TcpListener;
+use harissa::config;
+use harissa::error::HarissaError;
+use harissa::home;
+use harissa::web;
+
+const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:9616";
+const TOKEN_KEY: &str = "web_token";
+
+/// Serve the REST API and dashboard over HTTP
+pub fn web_command(args: &[String]) -> Result<(), HarissaError> {
+    let mut listen = DEFAULT_LISTEN_ADDRESS.to_string();
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--listen" => {
+                listen = args.get(i + 1)
+                    .ok_or_else(|| HarissaError::Usage("--listen requires an address, e.g. --listen 127.0.0.1:9616".to_string()))?
+                    .clone();
+                i += 2;
+            },
+            other => return Err(HarissaError::Usage(format!("Unknown web option: {}\nUsage: web [--listen address:port]", other))),
+        }
+    }
+
+    let harissa_dir = home::harissa_dir()?;
+
+    let token = match config::get(&harissa_dir, TOKEN_KEY)? {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            let token = config::generate_token()?;
+            config::set(&harissa_dir, TOKEN_KEY, &token)?;
+            println!("Generated an API token and saved it as {} in {}", TOKEN_KEY, config::config_path(&harissa_dir).display());
+            println!("Token: {}", token);
+            token
+        }
+    };
+
+    let listener = TcpListener::bind(&listen)
+        .map_err(|e| HarissaError::io(&format!("Failed to listen on {}", listen), e))?;
+
+    println!("Serving the dashboard on http://{}/", listen);
+    web::serve(listener, &harissa_dir, &token)
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use harissa::error::HarissaError;
 
 // Import command handlers
 use crate::command_handlers::help_command;
@@ -6,10 +7,23 @@ use crate::command_handlers::run_command;
 use crate::command_handlers::start_command;
 use crate::command_handlers::list_command;
 use crate::command_handlers::kill_command;
+use crate::command_handlers::stop_command;
+use crate::command_handlers::delete_command;
+use crate::command_handlers::signal_command;
+use crate::command_handlers::adopt_command;
 use crate::command_handlers::reload_command;
 use crate::command_handlers::startup_command;
+use crate::command_handlers::schedule_command;
+use crate::command_handlers::supervise_command;
+use crate::command_handlers::save_command;
+use crate::command_handlers::resurrect_command;
+use crate::command_handlers::export_command;
+use crate::command_handlers::events_command;
+use crate::command_handlers::serve_metrics_command;
+use crate::command_handlers::serve_api_command;
+use crate::command_handlers::web_command;
 
-pub type CommandFn = fn(&[String]) -> Result<(), String>;
+pub type CommandFn = fn(&[String]) -> Result<(), HarissaError>;
 
 pub struct CommandRegistry {
     commands: HashMap<String, CommandFn>,
@@ -25,13 +39,26 @@ impl CommandRegistry {
         commands.insert("start".to_string(), start_command);
         commands.insert("list".to_string(), list_command);
         commands.insert("kill".to_string(), kill_command);
+        commands.insert("stop".to_string(), stop_command);
+        commands.insert("delete".to_string(), delete_command);
+        commands.insert("signal".to_string(), signal_command);
+        commands.insert("adopt".to_string(), adopt_command);
         commands.insert("reload".to_string(), reload_command);
         commands.insert("startup".to_string(), startup_command);
+        commands.insert("schedule".to_string(), schedule_command);
+        commands.insert("supervise".to_string(), supervise_command);
+        commands.insert("save".to_string(), save_command);
+        commands.insert("resurrect".to_string(), resurrect_command);
+        commands.insert("export".to_string(), export_command);
+        commands.insert("events".to_string(), events_command);
+        commands.insert("serve-metrics".to_string(), serve_metrics_command);
+        commands.insert("serve-api".to_string(), serve_api_command);
+        commands.insert("web".to_string(), web_command);
         
         CommandRegistry { commands }
     }
     
-    pub fn execute(&self, args: &[String]) -> Result<(), String> {
+    pub fn execute(&self, args: &[String]) -> Result<(), HarissaError> {
         if args.is_empty() {
             return help_command(&[]);
         }
@@ -41,7 +68,7 @@ impl CommandRegistry {
         
         match self.commands.get(command_name) {
             Some(command_fn) => command_fn(command_args),
-            None => Err(format!("Unknown command: {}", command_name)),
+            None => Err(HarissaError::Usage(format!("Unknown command: {}", command_name))),
         }
     }
     
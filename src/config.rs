@@ -0,0 +1,72 @@
+// Harissa settings, stored as `key = value` lines in `~/.harissa_apps/harissa.conf`
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use crate::error::HarissaError;
+
+const CONFIG_FILE: &str = "harissa.conf";
+
+/// Path of the config file
+pub fn config_path(harissa_dir: &Path) -> PathBuf {
+    harissa_dir.join(CONFIG_FILE)
+}
+
+/// Read a setting; a missing file or key means the setting is unset
+pub fn get(harissa_dir: &Path, key: &str) -> Result<Option<String>, HarissaError> {
+    let path = config_path(harissa_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| HarissaError::io(&format!("Failed to read {}", path.display()), e))?;
+
+    Ok(contents.lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .find(|(name, _)| name.trim() == key)
+        .map(|(_, value)| value.trim().to_string()))
+}
+
+/// Set a setting, keeping the other lines of the file as they are.
+/// The file may hold secrets, so it is only readable by its owner: the new contents go to a
+/// fresh file created with mode 0600, which then replaces the old one.
+pub fn set(harissa_dir: &Path, key: &str, value: &str) -> Result<(), HarissaError> {
+    let path = config_path(harissa_dir);
+    let contents = if path.exists() {
+        fs::read_to_string(&path).map_err(|e| HarissaError::io(&format!("Failed to read {}", path.display()), e))?
+    } else {
+        String::new()
+    };
+
+    let mut lines: Vec<String> = contents.lines()
+        .filter(|line| line.split_once('=').is_none_or(|(name, _)| name.trim() != key || line.trim_start().starts_with('#')))
+        .map(str::to_string)
+        .collect();
+    lines.push(format!("{} = {}", key, value));
+
+    let temp_path = path.with_extension("conf.new");
+    // A leftover from an interrupted write may have any mode, so never reuse it
+    let _ = fs::remove_file(&temp_path);
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&temp_path)
+        .and_then(|mut file| file.write_all((lines.join("\n") + "\n").as_bytes()))
+        .map_err(|e| HarissaError::io(&format!("Failed to write {}", temp_path.display()), e))?;
+
+    fs::rename(&temp_path, &path)
+        .map_err(|e| HarissaError::io(&format!("Failed to replace {}", path.display()), e))
+}
+
+/// A random hex token for authenticating API clients
+pub fn generate_token() -> Result<String, HarissaError> {
+    let mut bytes = [0u8; 24];
+    fs::File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(&mut bytes))
+        .map_err(|e| HarissaError::System(format!("Failed to generate a token: {}", e)))?;
+
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
@@ -0,0 +1,297 @@
+// Control API on a Unix socket: line-delimited JSON-RPC 2.0 requests and responses.
+// The socket is only accessible to its owner, and connections from other users are refused.
+// The system-wide instance also admits members of the `harissa` group, but only lets them run
+// methods that launch no processes: a root-run server would otherwise run their commands as root.
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use serde_json::Value;
+use crate::api::{self, ApiError};
+use crate::error::HarissaError;
+use crate::home;
+
+const SOCKET_FILE: &str = "harissa.sock";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+
+/// Methods that launch processes, with the server's privileges
+const LAUNCHING_METHODS: &[&str] = &["start", "reload"];
+
+/// Default path of the control socket
+pub fn socket_path(harissa_dir: &Path) -> PathBuf {
+    home::run_dir(harissa_dir).join(SOCKET_FILE)
+}
+
+/// Listen on the socket and answer requests until the process is stopped
+pub fn serve(harissa_dir: &Path, socket_path: &Path) -> Result<(), HarissaError> {
+    if socket_path.exists() {
+        if UnixStream::connect(socket_path).is_ok() {
+            return Err(HarissaError::AlreadyExists(format!("Another harissa API server is already listening on {}", socket_path.display())));
+        }
+        // Left behind by a server that did not shut down cleanly
+        fs::remove_file(socket_path)
+            .map_err(|e| HarissaError::io(&format!("Failed to remove stale socket {}", socket_path.display()), e))?;
+    }
+
+    // Create the socket without group or other permissions so there is no window where others can connect
+    // SAFETY: umask has no preconditions and cannot fail
+    let previous_umask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(socket_path);
+    // SAFETY: as above
+    unsafe { libc::umask(previous_umask) };
+
+    let listener = listener
+        .map_err(|e| HarissaError::io(&format!("Failed to listen on {}", socket_path.display()), e))?;
+
+    // Open the socket of the system-wide instance to the harissa group once it belongs to it
+    let group = if home::is_system(harissa_dir) { home::system_group() } else { None };
+    if let Some(gid) = group {
+        std::os::unix::fs::chown(socket_path, None, Some(gid))
+            .map_err(|e| HarissaError::io(&format!("Failed to give {} to group '{}'", socket_path.display(), home::SYSTEM_GROUP), e))?;
+    }
+    let mode = if group.is_some() { 0o660 } else { 0o600 };
+    fs::set_permissions(socket_path, fs::Permissions::from_mode(mode))
+        .map_err(|e| HarissaError::io(&format!("Failed to set permissions of {}", socket_path.display()), e))?;
+
+    println!("Listening for API requests on {}", socket_path.display());
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        let Some(peer) = peer_credentials(&stream).filter(|peer| peer_allowed(peer, group)) else {
+            eprintln!("Refused API connection from another user");
+            continue;
+        };
+        // SAFETY: geteuid has no preconditions and cannot fail
+        let may_launch = peer.uid == 0 || peer.uid == unsafe { libc::geteuid() };
+
+        let harissa_dir = harissa_dir.to_path_buf();
+        thread::spawn(move || handle_connection(stream, &harissa_dir, may_launch));
+    }
+
+    Ok(())
+}
+
+/// The user and group of the process at the other end of the socket
+fn peer_credentials(stream: &UnixStream) -> Option<libc::ucred> {
+    let mut credentials = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut length = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+
+    // SAFETY: credentials and length describe a valid ucred buffer for SO_PEERCRED
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut credentials as *mut libc::ucred as *mut libc::c_void,
+            &mut length,
+        )
+    };
+
+    (result == 0).then_some(credentials)
+}
+
+/// Only the user running the server, root and members of `group` may use the API
+fn peer_allowed(peer: &libc::ucred, group: Option<libc::gid_t>) -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    peer.uid == 0 || peer.uid == unsafe { libc::geteuid() }
+        || group.is_some_and(|gid| peer.gid == gid || crate::credentials::user_in_group(peer.uid, gid))
+}
+
+/// Answer the requests of one client; `may_launch` is false for group members of the system-wide
+/// instance, which may not start or reload applications through the server
+fn handle_connection(stream: UnixStream, harissa_dir: &Path, may_launch: bool) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let error = ApiError { code: PARSE_ERROR, message: format!("Invalid JSON: {}", e), kind: "usage" };
+                if send(&mut writer, &api::error_response(&Value::Null, &error)).is_err() {
+                    return;
+                }
+                continue;
+            }
+        };
+
+        let id = request.get("id").cloned();
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            let error = ApiError { code: INVALID_REQUEST, message: "Missing \"method\"".to_string(), kind: "usage" };
+            if send(&mut writer, &api::error_response(&id.unwrap_or(Value::Null), &error)).is_err() {
+                return;
+            }
+            continue;
+        };
+
+        // A subscription streams log lines until the client disconnects
+        if method == "logs.subscribe" {
+            subscribe_logs(&mut writer, harissa_dir, id.as_ref(), &params);
+            return;
+        }
+
+        let result = if LAUNCHING_METHODS.contains(&method) && !may_launch {
+            Err(ApiError {
+                code: ApiError::COMMAND_FAILED,
+                message: format!("Only root and the user running the server may call \"{}\"; run 'harissa --system {}' yourself instead", method, method),
+                kind: "permission_denied",
+            })
+        } else {
+            api::call(harissa_dir, method, &params)
+        };
+        let response = match result {
+            Ok(result) => api::response(id.as_ref().unwrap_or(&Value::Null), result),
+            Err(error) => api::error_response(id.as_ref().unwrap_or(&Value::Null), &error),
+        };
+
+        // Requests without an id are notifications and get no response
+        if id.is_some() && send(&mut writer, &response).is_err() {
+            return;
+        }
+    }
+}
+
+fn subscribe_logs(writer: &mut UnixStream, harissa_dir: &Path, id: Option<&Value>, params: &Value) {
+    let id = id.cloned().unwrap_or(Value::Null);
+
+    let subscription = match api::log_subscription(harissa_dir, params) {
+        Ok(subscription) => subscription,
+        Err(error) => {
+            let _ = send(writer, &api::error_response(&id, &error));
+            return;
+        }
+    };
+
+    if send(writer, &api::response(&id, serde_json::json!({ "subscribed": true }))).is_err() {
+        return;
+    }
+
+    subscription.follow_logs(|log| {
+        send(writer, &api::log_notification(&subscription.name, log.stream, &log.line)).is_ok()
+    });
+}
+
+/// Write one JSON message followed by a newline
+fn send(writer: &mut UnixStream, message: &Value) -> std::io::Result<()> {
+    writeln!(writer, "{}", message)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+    use serde_json::json;
+    use crate::client::{AppRef, Harissa};
+
+    /// A connection to `handle_connection` on a temporary harissa home
+    struct TestConnection {
+        reader: BufReader<UnixStream>,
+        writer: UnixStream,
+        dir: PathBuf,
+    }
+
+    impl TestConnection {
+        fn open(test: &str, may_launch: bool) -> Self {
+            let dir = std::env::temp_dir().join(format!("harissa-api-test-{}-{}", process::id(), test));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+
+            let (client, server) = UnixStream::pair().unwrap();
+            let server_dir = dir.clone();
+            thread::spawn(move || handle_connection(server, &server_dir, may_launch));
+            TestConnection { reader: BufReader::new(client.try_clone().unwrap()), writer: client, dir }
+        }
+
+        /// Send a line and return the response to it
+        fn request(&mut self, line: &str) -> Value {
+            writeln!(self.writer, "{}", line).unwrap();
+            let mut response = String::new();
+            self.reader.read_line(&mut response).unwrap();
+            serde_json::from_str(&response).unwrap()
+        }
+
+        fn call(&mut self, id: u64, method: &str, params: Value) -> Value {
+            self.request(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string())
+        }
+    }
+
+    impl Drop for TestConnection {
+        fn drop(&mut self) {
+            let _ = Harissa::with_dir(&self.dir).delete(&AppRef::All);
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn error_of(response: &Value) -> (i64, &str) {
+        let error = &response["error"];
+        (error["code"].as_i64().unwrap(), error["data"]["kind"].as_str().unwrap())
+    }
+
+    #[test]
+    fn answers_requests_with_results_and_error_codes() {
+        let mut connection = TestConnection::open("requests", true);
+
+        let response = connection.request("{not json");
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(error_of(&response), (PARSE_ERROR, "usage"));
+        let response = connection.request(r#"{"jsonrpc": "2.0", "id": 1}"#);
+        assert_eq!(response["id"], 1);
+        assert_eq!(error_of(&response), (INVALID_REQUEST, "usage"));
+        assert_eq!(error_of(&connection.call(2, "explode", json!({}))), (ApiError::METHOD_NOT_FOUND, "usage"));
+        assert_eq!(error_of(&connection.call(3, "start", json!({ "command": [] }))), (ApiError::INVALID_PARAMS, "usage"));
+        assert_eq!(error_of(&connection.call(4, "stop", json!({ "name": "api" }))), (ApiError::COMMAND_FAILED, "not_found"));
+
+        let response = connection.call(5, "start", json!({ "command": ["sleep", "300"], "name": "api" }));
+        assert_eq!(response["id"], 5);
+        assert_eq!(response["result"]["name"], "api");
+        let pid = response["result"]["pid"].as_u64().unwrap();
+        assert_eq!(error_of(&connection.call(6, "start", json!({ "command": ["sleep", "300"], "name": "api" }))), (ApiError::COMMAND_FAILED, "already_exists"));
+
+        // A notification gets no response, so the next response is that of the next request
+        writeln!(connection.writer, "{}", json!({ "jsonrpc": "2.0", "method": "delete", "params": { "name": "nothing" } })).unwrap();
+        let response = connection.call(7, "list", json!({}));
+        assert_eq!(response["id"], 7);
+        let apps = response["result"].as_array().unwrap();
+        assert_eq!(apps.len(), 1);
+        assert_eq!((apps[0]["name"].as_str(), apps[0]["pid"].as_u64()), (Some("api"), Some(pid)));
+
+        let response = connection.call(8, "delete", json!({ "name": "api" }));
+        assert!(response.get("error").is_none(), "{}", response);
+        // SAFETY: waitpid has no memory-safety preconditions
+        unsafe { libc::waitpid(pid as libc::pid_t, std::ptr::null_mut(), 0) };
+        assert_eq!(connection.call(9, "list", json!({}))["result"], json!([]));
+    }
+
+    #[test]
+    fn refuses_to_launch_for_other_members_of_the_group() {
+        let mut connection = TestConnection::open("launching", false);
+
+        for method in LAUNCHING_METHODS {
+            let response = connection.call(1, method, json!({ "command": ["sleep", "300"], "name": "api" }));
+            assert_eq!(error_of(&response), (ApiError::COMMAND_FAILED, "permission_denied"), "{}", method);
+        }
+        assert!(connection.call(2, "list", json!({}))["result"].as_array().unwrap().is_empty());
+    }
+}
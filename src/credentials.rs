@@ -0,0 +1,190 @@
+// Running applications as a different user and group
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+use crate::error::HarissaError;
+
+/// The identity a child process switches to before exec
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub uid: libc::uid_t,
+    pub gid: libc::gid_t,
+    groups: Vec<libc::gid_t>,
+    // Only set when switching user, so HOME and USER are left alone for a group-only switch
+    user: Option<(String, String)>,
+}
+
+/// A passwd entry
+struct User {
+    name: String,
+    uid: libc::uid_t,
+    gid: libc::gid_t,
+    home: String,
+}
+
+/// Check whether harissa runs with root privileges
+pub fn is_root() -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Resolve `--user` and `--group` (names or numeric IDs) into credentials.
+/// Returns `None` when neither is given.
+pub fn resolve(user: Option<&str>, group: Option<&str>) -> Result<Option<Credentials>, HarissaError> {
+    if user.is_none() && group.is_none() {
+        return Ok(None);
+    }
+
+    if !is_root() {
+        return Err(HarissaError::PermissionDenied("--user and --group can only be used when harissa runs as root".to_string()));
+    }
+
+    let user = user.map(lookup_user).transpose()?;
+    let group_gid = group.map(lookup_group).transpose()?;
+
+    let credentials = match user {
+        Some(user) => {
+            let gid = group_gid.unwrap_or(user.gid);
+            Credentials {
+                uid: user.uid,
+                gid,
+                groups: supplementary_groups(&user.name, gid)?,
+                user: Some((user.name, user.home)),
+            }
+        },
+        None => {
+            let gid = group_gid.unwrap_or(0);
+            // SAFETY: getuid has no preconditions and cannot fail
+            Credentials { uid: unsafe { libc::getuid() }, gid, groups: vec![gid], user: None }
+        },
+    };
+
+    Ok(Some(credentials))
+}
+
+impl Credentials {
+    /// Arrange for the child to drop to these credentials before exec and set HOME/USER.
+    /// Must be called after any other pre-exec setup that needs root.
+    pub fn apply(&self, command: &mut Command) {
+        if let Some((name, home)) = &self.user {
+            command.env("HOME", home).env("USER", name).env("LOGNAME", name);
+        }
+
+        let credentials = self.clone();
+        // SAFETY: the closure only makes async-signal-safe system calls and does not allocate
+        unsafe {
+            command.pre_exec(move || {
+                if libc::setgroups(credentials.groups.len(), credentials.groups.as_ptr()) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if libc::setgid(credentials.gid) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if libc::setuid(credentials.uid) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    /// Give a file (such as a log file) to the target user and group
+    pub fn chown(&self, path: &Path) -> Result<(), HarissaError> {
+        std::os::unix::fs::chown(path, Some(self.uid), Some(self.gid))
+            .map_err(|e| HarissaError::io(&format!("Failed to change owner of {}", path.display()), e))
+    }
+}
+
+/// The ID of a group given by name or number
+pub fn group_id(group: &str) -> Result<libc::gid_t, HarissaError> {
+    lookup_group(group)
+}
+
+/// The name of the user who runs harissa, from the real user ID rather than the environment,
+/// which the caller controls. Users without a passwd entry are given by their ID.
+pub fn invoking_user() -> String {
+    // SAFETY: getuid has no preconditions and cannot fail
+    let uid = unsafe { libc::getuid() };
+    lookup_user(&uid.to_string()).map(|user| user.name).unwrap_or_else(|_| uid.to_string())
+}
+
+/// Check whether a user belongs to a group, as primary or supplementary group
+pub fn user_in_group(uid: libc::uid_t, gid: libc::gid_t) -> bool {
+    match lookup_user(&uid.to_string()) {
+        Ok(user) => user.gid == gid || supplementary_groups(&user.name, user.gid).is_ok_and(|groups| groups.contains(&gid)),
+        Err(_) => false,
+    }
+}
+
+fn lookup_user(user: &str) -> Result<User, HarissaError> {
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buffer = vec![0 as libc::c_char; 16384];
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    // SAFETY: all pointers are valid for the duration of the call and the buffer length is correct
+    let status = match user.parse::<libc::uid_t>() {
+        Ok(uid) => unsafe { libc::getpwuid_r(uid, &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result) },
+        Err(_) => {
+            let name = CString::new(user).map_err(|_| HarissaError::Usage(format!("Invalid user name '{}'", user)))?;
+            unsafe { libc::getpwnam_r(name.as_ptr(), &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result) }
+        },
+    };
+
+    if status != 0 || result.is_null() {
+        return Err(HarissaError::NotFound(format!("Unknown user '{}'", user)));
+    }
+
+    // SAFETY: on success the string fields point into our buffer and are NUL-terminated
+    unsafe {
+        Ok(User {
+            name: CStr::from_ptr(passwd.pw_name).to_string_lossy().into_owned(),
+            uid: passwd.pw_uid,
+            gid: passwd.pw_gid,
+            home: CStr::from_ptr(passwd.pw_dir).to_string_lossy().into_owned(),
+        })
+    }
+}
+
+fn lookup_group(group: &str) -> Result<libc::gid_t, HarissaError> {
+    if let Ok(gid) = group.parse::<libc::gid_t>() {
+        return Ok(gid);
+    }
+
+    let name = CString::new(group).map_err(|_| HarissaError::Usage(format!("Invalid group name '{}'", group)))?;
+    let mut entry: libc::group = unsafe { std::mem::zeroed() };
+    let mut buffer = vec![0 as libc::c_char; 16384];
+    let mut result: *mut libc::group = std::ptr::null_mut();
+
+    // SAFETY: all pointers are valid for the duration of the call and the buffer length is correct
+    let status = unsafe { libc::getgrnam_r(name.as_ptr(), &mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+
+    if status != 0 || result.is_null() {
+        return Err(HarissaError::NotFound(format!("Unknown group '{}'", group)));
+    }
+
+    Ok(entry.gr_gid)
+}
+
+/// The user's supplementary groups, resolved here because the child may not read /etc/group
+fn supplementary_groups(user: &str, gid: libc::gid_t) -> Result<Vec<libc::gid_t>, HarissaError> {
+    let name = CString::new(user).map_err(|_| HarissaError::Usage(format!("Invalid user name '{}'", user)))?;
+    let mut count: libc::c_int = 64;
+
+    loop {
+        let mut groups = vec![0 as libc::gid_t; count as usize];
+        let capacity = count;
+        // SAFETY: groups has room for `count` entries, and getgrouplist updates count
+        let found = unsafe { libc::getgrouplist(name.as_ptr(), gid, groups.as_mut_ptr(), &mut count) };
+
+        if found >= 0 {
+            groups.truncate(count as usize);
+            return Ok(groups);
+        }
+
+        if count <= capacity {
+            return Err(HarissaError::System(format!("Failed to look up groups of user '{}'", user)));
+        }
+    }
+}
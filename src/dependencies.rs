@@ -0,0 +1,228 @@
+// Ordering applications by the apps they start after (`start --after`)
+use std::collections::HashMap;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::app_spec::AppSpec;
+use crate::error::HarissaError;
+use crate::launcher::{self, is_running};
+use crate::notice::Notice;
+
+/// How long a freshly started dependency must stay up before its dependents are started
+const READY_DELAY: Duration = Duration::from_secs(1);
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long stopping an application waits for it to exit before stopping the apps it runs after
+const EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Order applications so each one comes after the applications it depends on.
+/// Dependencies outside of `apps` are ignored here; they must already be running.
+/// Returns indexes into `apps`, or an error describing the first cycle found.
+pub fn start_order(apps: &[(String, AppSpec)]) -> Result<Vec<usize>, HarissaError> {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Mark {
+        Unvisited,
+        InProgress,
+        Done,
+    }
+
+    fn visit(index: usize, apps: &[(String, AppSpec)], indexes: &HashMap<&str, usize>, marks: &mut [Mark], path: &mut Vec<usize>, order: &mut Vec<usize>) -> Result<(), HarissaError> {
+        match marks[index] {
+            Mark::Done => return Ok(()),
+            Mark::InProgress => {
+                // The cycle is the part of the path starting at this app
+                let start = path.iter().position(|&i| i == index).unwrap_or(0);
+                let mut cycle: Vec<&str> = path[start..].iter().map(|&i| apps[i].0.as_str()).collect();
+                cycle.push(&apps[index].0);
+                return Err(HarissaError::Failed(format!("Dependency cycle between applications: {}", cycle.join(" -> "))));
+            },
+            Mark::Unvisited => {},
+        }
+
+        marks[index] = Mark::InProgress;
+        path.push(index);
+        for dependency in &apps[index].1.after {
+            if let Some(&dependency_index) = indexes.get(dependency.as_str()) {
+                visit(dependency_index, apps, indexes, marks, path, order)?;
+            }
+        }
+        path.pop();
+        marks[index] = Mark::Done;
+        order.push(index);
+        Ok(())
+    }
+
+    let indexes: HashMap<&str, usize> = apps.iter().enumerate().map(|(i, (name, _))| (name.as_str(), i)).collect();
+    let mut marks = vec![Mark::Unvisited; apps.len()];
+    let mut order = Vec::with_capacity(apps.len());
+
+    for index in 0..apps.len() {
+        visit(index, apps, &indexes, &mut marks, &mut Vec::new(), &mut order)?;
+    }
+
+    Ok(order)
+}
+
+/// Order applications so each one comes before the applications it depends on, the reverse of
+/// `start_order`. A cycle is no reason to refuse to stop anything, so the order is then kept.
+pub fn stop_order(apps: &[(String, AppSpec)]) -> Vec<usize> {
+    match start_order(apps) {
+        Ok(mut order) => {
+            order.reverse();
+            order
+        },
+        Err(_) => (0..apps.len()).collect(),
+    }
+}
+
+/// Wait for a terminated application to exit. Returns false if it is still running after a while.
+pub fn wait_exited(pid: u32) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < EXIT_TIMEOUT {
+        if !is_running(pid) {
+            return true;
+        }
+        thread::sleep(READY_POLL_INTERVAL);
+    }
+    !is_running(pid)
+}
+
+/// Make sure the applications an app starts after are already running
+pub fn check_running(harissa_dir: &Path, name: &str, spec: &AppSpec) -> Result<(), HarissaError> {
+    if spec.after.is_empty() {
+        return Ok(());
+    }
+
+    let running = launcher::running_app_names(harissa_dir)?;
+    for dependency in &spec.after {
+        if dependency == name {
+            return Err(HarissaError::Usage(format!("{} cannot start after itself", name)));
+        }
+        if !running.contains(dependency) {
+            return Err(HarissaError::Failed(format!("{} starts after {}, which is not running. Start {} first.", name, dependency, dependency)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Tracks the applications of one ordered start so dependents wait until their dependencies are ready
+#[derive(Debug, Default)]
+pub struct StartTracker {
+    /// Started in this run and not yet known to be ready
+    starting: HashMap<String, u32>,
+    ready: Vec<String>,
+    failed: Vec<String>,
+}
+
+impl StartTracker {
+    /// Record an application that was already running before this run
+    pub fn running(&mut self, name: &str) {
+        self.ready.push(name.to_string());
+    }
+
+    pub fn started(&mut self, name: &str, pid: u32) {
+        self.starting.insert(name.to_string(), pid);
+    }
+
+    pub fn failed(&mut self, name: &str) {
+        self.failed.push(name.to_string());
+    }
+
+    /// Wait for every dependency of an application to be ready.
+    /// Fails with the reason when a dependency failed, exited or is not running at all.
+    pub fn wait_for_dependencies(&mut self, spec: &AppSpec, notify: &dyn Fn(Notice)) -> Result<(), HarissaError> {
+        for dependency in &spec.after {
+            if self.ready.contains(dependency) {
+                continue;
+            }
+            if self.failed.contains(dependency) {
+                return Err(HarissaError::Failed(format!("dependency {} failed to start", dependency)));
+            }
+
+            let Some(pid) = self.starting.remove(dependency) else {
+                return Err(HarissaError::Failed(format!("dependency {} is not running", dependency)));
+            };
+
+            notify(Notice::Progress(format!("Waiting for {} to be ready...", dependency)));
+            if wait_ready(pid) {
+                self.ready.push(dependency.clone());
+            } else {
+                self.failed.push(dependency.clone());
+                return Err(HarissaError::Failed(format!("dependency {} exited right after starting", dependency)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An application is considered ready once it has kept running for a short while after starting
+fn wait_ready(pid: u32) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < READY_DELAY {
+        if !is_running(pid) {
+            return false;
+        }
+        thread::sleep(READY_POLL_INTERVAL);
+    }
+    is_running(pid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(name: &str, after: &[&str]) -> (String, AppSpec) {
+        let spec = AppSpec { after: after.iter().map(|name| name.to_string()).collect(), ..AppSpec::default() };
+        (name.to_string(), spec)
+    }
+
+    fn names(apps: &[(String, AppSpec)], order: &[usize]) -> Vec<String> {
+        order.iter().map(|&index| apps[index].0.clone()).collect()
+    }
+
+    #[test]
+    fn dependencies_start_first() {
+        let apps = vec![app("web", &["api"]), app("api", &["db", "cache"]), app("db", &[]), app("cache", &[])];
+        let order = names(&apps, &start_order(&apps).unwrap());
+        assert_eq!(order.len(), 4);
+
+        let position = |name: &str| order.iter().position(|app| app == name).unwrap();
+        assert!(position("db") < position("api"));
+        assert!(position("cache") < position("api"));
+        assert!(position("api") < position("web"));
+    }
+
+    #[test]
+    fn independent_apps_keep_their_order() {
+        let apps = vec![app("b", &[]), app("a", &[]), app("c", &[])];
+        assert_eq!(start_order(&apps).unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn dependencies_outside_the_set_are_ignored() {
+        let apps = vec![app("api", &["db"]), app("web", &["api"])];
+        assert_eq!(names(&apps, &start_order(&apps).unwrap()), vec!["api", "web"]);
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let apps = vec![app("a", &["b"]), app("b", &["c"]), app("c", &["a"])];
+        match start_order(&apps) {
+            Err(HarissaError::Failed(message)) => assert!(message.contains("a -> b -> c -> a"), "{}", message),
+            other => panic!("expected a cycle error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dependents_stop_first() {
+        let apps = vec![app("db", &[]), app("web", &["api"]), app("api", &["db"])];
+        assert_eq!(names(&apps, &stop_order(&apps)), vec!["web", "api", "db"]);
+    }
+
+    #[test]
+    fn cycles_still_stop_everything() {
+        let apps = vec![app("a", &["b"]), app("b", &["a"]), app("c", &[])];
+        assert_eq!(stop_order(&apps), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,82 @@
+// Errors of harissa commands and the client API, grouped into categories with stable exit codes
+use std::fmt;
+use std::io;
+use serde_json::{Value, json};
+
+/// An error with a category that scripts can rely on.
+/// Exit codes never change once assigned; new categories get new codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HarissaError {
+    /// The command could not do what was asked, e.g. an application exited right after starting
+    Failed(String),
+    /// Invalid command line arguments or API parameters
+    Usage(String),
+    /// No application, job or file matches what was asked for
+    NotFound(String),
+    /// The current user may not do this
+    PermissionDenied(String),
+    /// A file operation, system call or external tool such as ps failed
+    System(String),
+    /// An application with that name is already running
+    AlreadyExists(String),
+}
+
+impl HarissaError {
+    /// Map an I/O error to its category, keeping the context in the message
+    pub fn io(context: &str, error: io::Error) -> Self {
+        let message = format!("{}: {}", context, error);
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => HarissaError::PermissionDenied(message),
+            io::ErrorKind::NotFound => HarissaError::NotFound(message),
+            _ => HarissaError::System(message),
+        }
+    }
+
+    /// Name of the category in JSON output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HarissaError::Failed(_) => "failed",
+            HarissaError::Usage(_) => "usage",
+            HarissaError::NotFound(_) => "not_found",
+            HarissaError::PermissionDenied(_) => "permission_denied",
+            HarissaError::System(_) => "system",
+            HarissaError::AlreadyExists(_) => "already_exists",
+        }
+    }
+
+    /// Process exit code of the category
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HarissaError::Failed(_) => 1,
+            HarissaError::Usage(_) => 2,
+            HarissaError::NotFound(_) => 3,
+            HarissaError::PermissionDenied(_) => 4,
+            HarissaError::System(_) => 5,
+            HarissaError::AlreadyExists(_) => 6,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            HarissaError::Failed(message)
+            | HarissaError::Usage(message)
+            | HarissaError::NotFound(message)
+            | HarissaError::PermissionDenied(message)
+            | HarissaError::System(message)
+            | HarissaError::AlreadyExists(message) => message,
+        }
+    }
+
+    /// `{"error": {"kind", "exit_code", "message"}}`
+    pub fn to_json(&self) -> Value {
+        json!({ "error": { "kind": self.kind(), "exit_code": self.exit_code(), "message": self.message() } })
+    }
+}
+
+impl fmt::Display for HarissaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for HarissaError {}
@@ -0,0 +1,167 @@
+// Append-only history of what happened to applications, stored as `events.log`
+//
+// Each line holds tab-separated fields: time (RFC 3339), event, app name, PID, details,
+// and the user and working directory of the harissa invocation that recorded it.
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use chrono::{DateTime, Duration, Local};
+use crate::credentials;
+use crate::error::HarissaError;
+
+const EVENTS_FILE: &str = "events.log";
+
+/// Kinds of recorded events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Start,
+    Stop,
+    Reload,
+    /// A restart triggered by a cron schedule
+    Restart,
+    /// An application exited without harissa stopping it
+    Crash,
+    /// A scheduled job finished
+    Exit,
+    /// A signal was sent with `signal`
+    Signal,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Start => "start",
+            EventKind::Stop => "stop",
+            EventKind::Reload => "reload",
+            EventKind::Restart => "restart",
+            EventKind::Crash => "crash",
+            EventKind::Exit => "exit",
+            EventKind::Signal => "signal",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "start" => Some(EventKind::Start),
+            "stop" => Some(EventKind::Stop),
+            "reload" => Some(EventKind::Reload),
+            "restart" => Some(EventKind::Restart),
+            "crash" => Some(EventKind::Crash),
+            "exit" => Some(EventKind::Exit),
+            "signal" => Some(EventKind::Signal),
+            _ => None,
+        }
+    }
+}
+
+/// A single line of the event log
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub time: DateTime<Local>,
+    pub kind: EventKind,
+    pub app: String,
+    pub pid: Option<u32>,
+    pub details: String,
+    pub user: String,
+    pub cwd: String,
+}
+
+/// Append an event to the log. Recording is best effort and never fails the operation itself.
+pub fn record(harissa_dir: &Path, kind: EventKind, app: &str, pid: Option<u32>, details: &str) {
+    let user = credentials::invoking_user();
+    let cwd = env::current_dir()
+        .map(|cwd| cwd.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let line = [
+        Local::now().to_rfc3339(),
+        kind.as_str().to_string(),
+        field(app),
+        pid.map(|pid| pid.to_string()).unwrap_or_default(),
+        field(details),
+        field(&user),
+        field(&cwd),
+    ]
+    .join("\t");
+
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(harissa_dir.join(EVENTS_FILE))
+        .and_then(|mut file| writeln!(file, "{}", line));
+
+    if let Err(e) = result {
+        eprintln!("Warning: failed to record {} event: {}", kind.as_str(), e);
+    }
+}
+
+/// Read every recorded event, oldest first
+pub fn read(harissa_dir: &Path) -> Result<Vec<Event>, HarissaError> {
+    let events_file = harissa_dir.join(EVENTS_FILE);
+    if !events_file.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(&events_file)
+        .map_err(|e| HarissaError::io("Failed to open events file", e))?;
+
+    let mut events = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| HarissaError::io("Failed to read line", e))?;
+        let parts: Vec<&str> = line.split('\t').collect();
+
+        if parts.len() < 7 {
+            // Skip invalid lines
+            continue;
+        }
+
+        let (Ok(time), Some(kind)) = (DateTime::parse_from_rfc3339(parts[0]), EventKind::parse(parts[1])) else {
+            continue;
+        };
+
+        events.push(Event {
+            time: time.with_timezone(&Local),
+            kind,
+            app: parts[2].to_string(),
+            pid: parts[3].parse().ok(),
+            details: parts[4].to_string(),
+            user: parts[5].to_string(),
+            cwd: parts[6].to_string(),
+        });
+    }
+
+    Ok(events)
+}
+
+/// Parse a `--since` value: a duration such as 30s, 15m, 1h, 2d or 1w, or a date and time
+/// such as "2024-05-01" or "2024-05-01 14:30"
+pub fn parse_since(value: &str) -> Result<DateTime<Local>, String> {
+    let value = value.trim();
+    let invalid = || format!("Invalid --since '{}': expected a duration such as 30m, 1h or 2d, or a date such as 2024-05-01", value);
+
+    if let Some(unit) = value.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        let amount: i64 = value[..value.len() - 1].parse().map_err(|_| invalid())?;
+        let duration = match unit {
+            's' => Duration::seconds(amount),
+            'm' => Duration::minutes(amount),
+            'h' => Duration::hours(amount),
+            'd' => Duration::days(amount),
+            'w' => Duration::weeks(amount),
+            _ => return Err(invalid()),
+        };
+        return Ok(Local::now() - duration);
+    }
+
+    let naive = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default()))
+        .map_err(|_| invalid())?;
+
+    naive.and_local_timezone(Local).earliest().ok_or_else(invalid)
+}
+
+/// Keep fields on one line and free of the tab separator
+fn field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
@@ -0,0 +1,114 @@
+// Shell-style wildcard patterns for selecting applications by name
+//
+// `*` matches any run of characters, `?` one character, and `[abc]` or `[a-z]` one of a set
+// (`[!abc]` negates it). A backslash makes the next character literal.
+
+/// Whether a string uses wildcards and should be matched as a pattern rather than a plain name
+pub fn is_pattern(value: &str) -> bool {
+    value.contains(['*', '?', '['])
+}
+
+/// Whether `text` matches `pattern` as a whole
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches_at(&pattern, &text)
+}
+
+fn matches_at(pattern: &[char], text: &[char]) -> bool {
+    let Some((&first, rest)) = pattern.split_first() else {
+        return text.is_empty();
+    };
+
+    match first {
+        '*' => (0..=text.len()).any(|skip| matches_at(rest, &text[skip..])),
+        '?' => !text.is_empty() && matches_at(rest, &text[1..]),
+        '[' => match (text.first(), class_end(rest)) {
+            (Some(&c), Some(end)) => class_matches(&rest[..end], c) && matches_at(&rest[end + 1..], &text[1..]),
+            // An unclosed bracket is an ordinary character
+            (Some(&c), None) => c == '[' && matches_at(rest, &text[1..]),
+            (None, _) => false,
+        },
+        '\\' if !rest.is_empty() => text.first() == Some(&rest[0]) && matches_at(&rest[1..], &text[1..]),
+        c => text.first() == Some(&c) && matches_at(rest, &text[1..]),
+    }
+}
+
+/// Index of the `]` closing a character class; a `]` right after `[` or `[!` is part of the set
+fn class_end(class: &[char]) -> Option<usize> {
+    let start = if class.first() == Some(&'!') { 1 } else { 0 };
+    class.iter().skip(start + 1).position(|&c| c == ']').map(|index| index + start + 1)
+}
+
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, set) = match class.split_first() {
+        Some(('!', set)) => (true, set),
+        _ => (false, class),
+    };
+
+    let mut found = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            found |= set[i] <= c && c <= set[i + 2];
+            i += 3;
+        } else {
+            found |= set[i] == c;
+            i += 1;
+        }
+    }
+
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_patterns() {
+        assert!(is_pattern("api-*"));
+        assert!(is_pattern("worker?"));
+        assert!(is_pattern("web[12]"));
+        assert!(!is_pattern("api"));
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("api-*", "api-1"));
+        assert!(matches("api-*", "api-"));
+        assert!(!matches("api-*", "web-1"));
+        assert!(matches("*-worker", "mail-worker"));
+        assert!(matches("a*b*c", "aXbYc"));
+        assert!(!matches("a*b*c", "aXbY"));
+        assert!(matches("worker?", "worker1"));
+        assert!(!matches("worker?", "worker"));
+        assert!(!matches("worker?", "worker12"));
+    }
+
+    #[test]
+    fn matches_the_whole_name() {
+        assert!(!matches("api", "api-1"));
+        assert!(!matches("pi*", "api"));
+    }
+
+    #[test]
+    fn matches_character_classes() {
+        assert!(matches("web[12]", "web1"));
+        assert!(!matches("web[12]", "web3"));
+        assert!(matches("web[0-9]", "web7"));
+        assert!(!matches("web[!0-9]", "web7"));
+        assert!(matches("web[!0-9]", "webx"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[!]]", "a"));
+    }
+
+    #[test]
+    fn unclosed_bracket_and_escapes_are_literal() {
+        assert!(matches("web[", "web["));
+        assert!(!matches("web[", "web1"));
+        assert!(matches("what\\?", "what?"));
+        assert!(!matches("what\\?", "whats"));
+        assert!(matches("\\*", "*"));
+    }
+}
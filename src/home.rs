@@ -0,0 +1,144 @@
+// The harissa home: the directory holding the state of one harissa instance
+//
+// It is `~/.harissa_apps` unless `HARISSA_HOME` (or the global `--home` option, which sets it)
+// points somewhere else, so that isolated instances can run side by side, e.g. one per project
+// or per test. Processes harissa starts for itself, like the supervisor, get the same home.
+//
+// `--system` selects the system-wide instance in `/var/lib/harissa`. It keeps its logs in
+// `/var/log/harissa` and its control socket in `/run/harissa`, and shares everything with the
+// `harissa` group: directories are group-owned and setgid, and files are created group-writable,
+// so several operators can manage the same applications. Files with commands that root may run
+// are the exception and only writable by their owner; see `app_spec::check_trusted`.
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use crate::credentials;
+use crate::error::HarissaError;
+
+/// Environment variable that selects another harissa home
+pub const HOME_ENV: &str = "HARISSA_HOME";
+const DEFAULT_DIR: &str = ".harissa_apps";
+
+/// Home of the system-wide instance
+pub const SYSTEM_HOME: &str = "/var/lib/harissa";
+const SYSTEM_LOG_DIR: &str = "/var/log/harissa";
+const SYSTEM_RUN_DIR: &str = "/run/harissa";
+/// Group whose members may manage the system-wide applications
+pub const SYSTEM_GROUP: &str = "harissa";
+/// Group-owned, group-writable and setgid, so new files and directories stay in the group
+const SYSTEM_DIR_MODE: u32 = 0o2770;
+
+/// The harissa home of this process, which may not exist yet
+pub fn path() -> Result<PathBuf, HarissaError> {
+    if let Some(home) = custom() {
+        return absolute(&home);
+    }
+
+    let home_dir = env::var("HOME").map_err(|e| HarissaError::System(format!("Could not get HOME directory: {}", e)))?;
+    Ok(Path::new(&home_dir).join(DEFAULT_DIR))
+}
+
+/// The harissa home given by `HARISSA_HOME`, if any
+pub fn custom() -> Option<PathBuf> {
+    env::var_os(HOME_ENV).filter(|home| !home.is_empty()).map(PathBuf::from)
+}
+
+/// Get the harissa home, creating it (and the log directory) if needed
+pub fn harissa_dir() -> Result<PathBuf, HarissaError> {
+    let harissa_dir = path()?;
+
+    if is_system(&harissa_dir) {
+        // Files written by any operator, and by the applications they start, stay writable by the group
+        // SAFETY: umask has no preconditions and cannot fail
+        unsafe { libc::umask(0o007) };
+    }
+
+    create_dir(&harissa_dir)?;
+    create_dir(&log_dir(&harissa_dir))?;
+
+    Ok(harissa_dir)
+}
+
+/// Whether a harissa home is the system-wide one
+pub fn is_system(harissa_dir: &Path) -> bool {
+    harissa_dir == Path::new(SYSTEM_HOME)
+}
+
+/// Directory holding the logs of the applications
+pub fn log_dir(harissa_dir: &Path) -> PathBuf {
+    if is_system(harissa_dir) { PathBuf::from(SYSTEM_LOG_DIR) } else { harissa_dir.to_path_buf() }
+}
+
+/// Directory holding runtime files like the control socket
+pub fn run_dir(harissa_dir: &Path) -> PathBuf {
+    if is_system(harissa_dir) { PathBuf::from(SYSTEM_RUN_DIR) } else { harissa_dir.to_path_buf() }
+}
+
+/// Path of an application's log, for stream "out" or "err"
+pub fn log_path(harissa_dir: &Path, name: &str, stream: &str) -> PathBuf {
+    log_dir(harissa_dir).join(format!("{}.{}.log", name, stream))
+}
+
+/// The ID of the `harissa` group, if it exists
+pub fn system_group() -> Option<libc::gid_t> {
+    credentials::group_id(SYSTEM_GROUP).ok()
+}
+
+/// Check that this process may use the system-wide instance: root and members of the `harissa` group can
+pub fn check_system_access() -> Result<(), HarissaError> {
+    if credentials::is_root() {
+        return Ok(());
+    }
+
+    let Some(gid) = system_group() else {
+        return Err(HarissaError::PermissionDenied(format!("System-wide mode needs the '{}' group; create it with 'groupadd {}' and add the operators to it",
+            SYSTEM_GROUP, SYSTEM_GROUP)));
+    };
+
+    // SAFETY: getuid and getegid have no preconditions and cannot fail
+    let (uid, egid) = unsafe { (libc::getuid(), libc::getegid()) };
+    if egid == gid || credentials::user_in_group(uid, gid) {
+        Ok(())
+    } else {
+        Err(HarissaError::PermissionDenied(format!("System-wide mode is only available to root and members of the '{}' group", SYSTEM_GROUP)))
+    }
+}
+
+/// Create a directory of the harissa home if it does not exist yet. Directories of the
+/// system-wide instance are given to the `harissa` group, which needs root the first time.
+pub fn create_dir(dir: &Path) -> Result<(), HarissaError> {
+    if dir.exists() {
+        return Ok(());
+    }
+
+    let system_dirs = [SYSTEM_HOME, SYSTEM_LOG_DIR, SYSTEM_RUN_DIR].map(Path::new);
+    fs::create_dir_all(dir).map_err(|e| match e.kind() {
+        io::ErrorKind::PermissionDenied if system_dirs.iter().any(|system_dir| dir.starts_with(system_dir)) =>
+            HarissaError::PermissionDenied(format!("Failed to create {}: {}; run a 'harissa --system' command as root once to set it up", dir.display(), e)),
+        _ => HarissaError::io(&format!("Failed to create {}", dir.display()), e),
+    })?;
+
+    if system_dirs.contains(&dir) {
+        if let Some(gid) = system_group() {
+            std::os::unix::fs::chown(dir, None, Some(gid))
+                .map_err(|e| HarissaError::io(&format!("Failed to give {} to group '{}'", dir.display(), SYSTEM_GROUP), e))?;
+        }
+        fs::set_permissions(dir, fs::Permissions::from_mode(SYSTEM_DIR_MODE))
+            .map_err(|e| HarissaError::io(&format!("Failed to set permissions of {}", dir.display()), e))?;
+    }
+
+    Ok(())
+}
+
+/// Resolve a relative home against the current directory, so child processes started
+/// elsewhere use the same one
+pub fn absolute(home: &Path) -> Result<PathBuf, HarissaError> {
+    if home.is_absolute() {
+        return Ok(home.to_path_buf());
+    }
+
+    let current_dir = env::current_dir().map_err(|e| HarissaError::io("Could not get current directory", e))?;
+    Ok(current_dir.join(home))
+}
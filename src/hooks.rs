@@ -0,0 +1,289 @@
+// Lifecycle hooks: shell commands run around an application's start and stop, and when it crashes
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use chrono::Local;
+use crate::app_spec::AppSpec;
+use crate::credentials;
+use crate::error::HarissaError;
+use crate::home;
+use crate::notice::Notice;
+use crate::signals;
+
+/// Hook options accepted by `start`
+pub const HOOK_OPTIONS: [&str; 5] = ["--pre-start", "--post-start", "--pre-stop", "--post-stop", "--on-crash"];
+
+/// How long a hook may run before it is killed, so that a hanging hook cannot block a start or stop
+#[cfg(not(test))]
+const HOOK_TIMEOUT: Duration = Duration::from_secs(300);
+#[cfg(test)]
+const HOOK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The points in an application's lifecycle that can run a hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// Before the application starts; a failure aborts the start
+    PreStart,
+    PostStart,
+    /// Before harissa stops the application
+    PreStop,
+    PostStop,
+    /// When the supervisor notices the application exited without harissa stopping it
+    OnCrash,
+}
+
+impl Hook {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Hook::PreStart => "pre-start",
+            Hook::PostStart => "post-start",
+            Hook::PreStop => "pre-stop",
+            Hook::PostStop => "post-stop",
+            Hook::OnCrash => "on-crash",
+        }
+    }
+}
+
+/// The hook commands of an application
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    pub pre_start: Option<String>,
+    pub post_start: Option<String>,
+    pub pre_stop: Option<String>,
+    pub post_stop: Option<String>,
+    pub on_crash: Option<String>,
+}
+
+impl Hooks {
+    /// The command to run for a hook, if one is set
+    pub fn get(&self, hook: Hook) -> Option<&str> {
+        match hook {
+            Hook::PreStart => self.pre_start.as_deref(),
+            Hook::PostStart => self.post_start.as_deref(),
+            Hook::PreStop => self.pre_stop.as_deref(),
+            Hook::PostStop => self.post_stop.as_deref(),
+            Hook::OnCrash => self.on_crash.as_deref(),
+        }
+    }
+
+    /// Set a hook from one of the `HOOK_OPTIONS` command-line flags
+    pub fn set_option(&mut self, option: &str, command: &str) -> Result<(), String> {
+        if command.trim().is_empty() {
+            return Err(format!("{} requires a command", option));
+        }
+
+        let command = Some(command.to_string());
+        match option {
+            "--pre-start" => self.pre_start = command,
+            "--post-start" => self.post_start = command,
+            "--pre-stop" => self.pre_stop = command,
+            "--post-stop" => self.post_stop = command,
+            "--on-crash" => self.on_crash = command,
+            _ => return Err(format!("Unknown hook option: {}", option)),
+        }
+        Ok(())
+    }
+
+    /// The hooks as (option, command) pairs, in the form accepted by `set_option`
+    pub fn to_options(&self) -> Vec<(&'static str, String)> {
+        [
+            ("--pre-start", &self.pre_start),
+            ("--post-start", &self.post_start),
+            ("--pre-stop", &self.pre_stop),
+            ("--post-stop", &self.post_stop),
+            ("--on-crash", &self.on_crash),
+        ]
+        .into_iter()
+        .filter_map(|(option, command)| command.as_ref().map(|command| (option, command.clone())))
+        .collect()
+    }
+}
+
+/// Run an application's hook, if it has one, through `sh -c` with the app's working directory,
+/// environment and user. Its output is appended to the app's log files.
+/// Fails if the hook cannot be run, exits with a non-zero code or runs longer than `HOOK_TIMEOUT`,
+/// in which case it is killed together with the processes it started.
+pub fn run(harissa_dir: &Path, name: &str, spec: &AppSpec, hook: Hook, pid: Option<u32>) -> Result<(), HarissaError> {
+    let Some(hook_command) = spec.hooks.get(hook) else {
+        return Ok(());
+    };
+
+    let stdout_log = home::log_path(harissa_dir, name, "out");
+    let stderr_log = home::log_path(harissa_dir, name, "err");
+
+    let open_log = |path: &PathBuf| OpenOptions::new().create(true).append(true).open(path);
+    let mut stdout_file = open_log(&stdout_log)
+        .map_err(|e| HarissaError::io(&format!("Failed to open stdout log file {}", stdout_log.display()), e))?;
+    let stderr_file = open_log(&stderr_log)
+        .map_err(|e| HarissaError::io(&format!("Failed to open stderr log file {}", stderr_log.display()), e))?;
+
+    let _ = writeln!(stdout_file, "[harissa] {} Running {} hook: {}", Local::now().format("%Y-%m-%d %H:%M:%S"), hook.as_str(), hook_command);
+
+    let child_stdout = stdout_file.try_clone()
+        .map_err(|e| HarissaError::io("Failed to open stdout log file", e))?;
+
+    let mut command = Command::new("sh");
+    command
+        .args(["-c", hook_command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(child_stdout))
+        .stderr(Stdio::from(stderr_file))
+        // Its own process group, so a timeout also kills what it started
+        .process_group(0);
+
+    if let Some(cwd) = &spec.cwd {
+        command.current_dir(cwd);
+    }
+    if !spec.env.is_empty() {
+        command.env_clear().envs(spec.env.iter().map(|(name, value)| (name, value)));
+    }
+    command.env("HARISSA_APP_NAME", name).env("HARISSA_HOOK", hook.as_str());
+    if let Some(pid) = pid {
+        command.env("HARISSA_APP_PID", pid.to_string());
+    }
+
+    // Hooks run as the application's user, like the application itself
+    if let Some(credentials) = credentials::resolve(spec.user.as_deref(), spec.group.as_deref())? {
+        credentials.apply(&mut command);
+    }
+
+    let failed_to_run = |e| HarissaError::System(format!("Failed to run {} hook of {}: {}", hook.as_str(), name, e));
+    let mut child = command.spawn().map_err(failed_to_run)?;
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait().map_err(failed_to_run)? {
+            Some(status) => break Some(status),
+            None if started.elapsed() >= HOOK_TIMEOUT => {
+                let _ = signals::send_to_group(child.id(), libc::SIGKILL);
+                let _ = child.wait();
+                break None;
+            },
+            None => thread::sleep(Duration::from_millis(50)),
+        }
+    };
+
+    let outcome = match status.map(|status| status.code()) {
+        Some(Some(code)) => format!("exited with code {}", code),
+        Some(None) => "was terminated by a signal".to_string(),
+        None => format!("timed out after {} seconds and was killed", HOOK_TIMEOUT.as_secs()),
+    };
+    let _ = writeln!(stdout_file, "[harissa] {} {} hook {}", Local::now().format("%Y-%m-%d %H:%M:%S"), hook.as_str(), outcome);
+
+    if !status.is_some_and(|status| status.success()) {
+        return Err(HarissaError::Failed(format!("{} hook of {} {}", hook.as_str(), name, outcome)));
+    }
+
+    Ok(())
+}
+
+/// Run a hook whose failure does not stop the operation, reporting the failure as a warning
+pub fn run_or_warn(harissa_dir: &Path, name: &str, spec: &AppSpec, hook: Hook, pid: Option<u32>, notify: &dyn Fn(Notice)) {
+    if let Err(e) = run(harissa_dir, name, spec, hook, pid) {
+        notify(Notice::Warning(e.to_string()));
+    }
+}
+
+/// Record that the exit of this process of an application is accounted for: harissa is about
+/// to stop it, or its crash was already handled. The supervisor ignores such exits.
+pub fn mark_exit_handled(harissa_dir: &Path, name: &str, pid: &str) {
+    let _ = std::fs::write(exit_marker_path(harissa_dir, name), pid);
+}
+
+/// Whether the exit of this process of an application is accounted for
+pub fn exit_handled(harissa_dir: &Path, name: &str, pid: &str) -> bool {
+    std::fs::read_to_string(exit_marker_path(harissa_dir, name))
+        .map(|marked| marked.trim() == pid)
+        .unwrap_or(false)
+}
+
+/// Forget the exit marker of an application that is no longer tracked
+pub fn clear_exit_marker(harissa_dir: &Path, name: &str) {
+    let _ = std::fs::remove_file(exit_marker_path(harissa_dir, name));
+}
+
+fn exit_marker_path(harissa_dir: &Path, name: &str) -> PathBuf {
+    harissa_dir.join(format!("{}.exited", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::fs;
+    use std::process;
+    use crate::process_identity;
+
+    /// A temporary harissa home, also the working directory of the hooks
+    fn home(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("harissa-hooks-test-{}-{}", process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn spec(dir: &Path, option: &str, command: &str) -> AppSpec {
+        let mut spec = AppSpec { cwd: Some(dir.to_path_buf()), ..AppSpec::default() };
+        spec.hooks.set_option(option, command).unwrap();
+        spec
+    }
+
+    #[test]
+    fn hooks_run_in_the_apps_directory_and_log_to_its_logs() {
+        let dir = home("success");
+        let spec = spec(&dir, "--post-start", "echo $HARISSA_APP_NAME $HARISSA_HOOK $HARISSA_APP_PID > hook.env; echo started");
+
+        run(&dir, "api", &spec, Hook::PostStart, Some(42)).unwrap();
+        // Hooks that are not set do nothing
+        run(&dir, "api", &spec, Hook::PreStop, Some(42)).unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join("hook.env")).unwrap(), "api post-start 42\n");
+        let log = fs::read_to_string(home::log_path(&dir, "api", "out")).unwrap();
+        assert!(log.contains("Running post-start hook") && log.contains("started\n") && log.contains("post-start hook exited with code 0"), "{}", log);
+        assert!(!log.contains("pre-stop"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failing_hooks_report_their_exit_code() {
+        let dir = home("failure");
+        let spec = spec(&dir, "--post-stop", "echo cleaning up; echo disk full >&2; exit 3");
+
+        let error = run(&dir, "api", &spec, Hook::PostStop, None).unwrap_err();
+        assert_eq!(error.kind(), "failed");
+        assert_eq!(error.to_string(), "post-stop hook of api exited with code 3");
+        let log = fs::read_to_string(home::log_path(&dir, "api", "out")).unwrap();
+        assert!(log.contains("cleaning up\n") && log.contains("post-stop hook exited with code 3"), "{}", log);
+        assert_eq!(fs::read_to_string(home::log_path(&dir, "api", "err")).unwrap(), "disk full\n");
+
+        // Where a failure does not stop the operation, it becomes a warning
+        let notices = RefCell::new(Vec::new());
+        run_or_warn(&dir, "api", &spec, Hook::PostStop, None, &|notice| notices.borrow_mut().push(notice));
+        assert_eq!(notices.into_inner(), vec![Notice::Warning("post-stop hook of api exited with code 3".to_string())]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn hanging_hooks_are_killed_with_what_they_started() {
+        let dir = home("timeout");
+        let spec = spec(&dir, "--pre-stop", "sleep 300 & echo $! > sleep.pid; wait");
+
+        let started = Instant::now();
+        let error = run(&dir, "api", &spec, Hook::PreStop, Some(42)).unwrap_err();
+        assert!(started.elapsed() < HOOK_TIMEOUT + Duration::from_secs(5));
+        assert_eq!(error.to_string(), format!("pre-stop hook of api timed out after {} seconds and was killed", HOOK_TIMEOUT.as_secs()));
+        let log = fs::read_to_string(home::log_path(&dir, "api", "out")).unwrap();
+        assert!(log.contains("pre-stop hook timed out"), "{}", log);
+
+        let sleep: u32 = fs::read_to_string(dir.join("sleep.pid")).unwrap().trim().parse().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while process_identity::of(sleep).is_some() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert!(process_identity::of(sleep).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
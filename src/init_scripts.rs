@@ -0,0 +1,275 @@
+// Boot-time integration: systemd units, OpenRC and SysV init scripts that run `harissa resurrect`
+// at boot and `harissa stop all --yes` at shutdown, so applications stop in dependency order
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use crate::home;
+
+/// Init systems harissa can generate startup artifacts for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitSystem {
+    /// A system-wide systemd service running as the target user
+    Systemd,
+    /// A systemd user service (`systemctl --user`)
+    SystemdUser,
+    OpenRc,
+    SysV,
+}
+
+impl InitSystem {
+    /// Parse the value of `--init`
+    pub fn parse(value: &str, user_mode: bool) -> Result<Self, String> {
+        match value {
+            "systemd" if user_mode => Ok(InitSystem::SystemdUser),
+            "systemd" => Ok(InitSystem::Systemd),
+            "openrc" | "sysv" if user_mode => Err(format!("--user is only supported with systemd, not {}", value)),
+            "openrc" => Ok(InitSystem::OpenRc),
+            "sysv" => Ok(InitSystem::SysV),
+            _ => Err(format!("Unknown init system '{}': expected systemd, openrc or sysv", value)),
+        }
+    }
+
+    /// Detect the init system of this machine
+    pub fn detect(user_mode: bool) -> Self {
+        if Path::new("/run/systemd/system").exists() {
+            if user_mode { InitSystem::SystemdUser } else { InitSystem::Systemd }
+        } else if Path::new("/run/openrc").exists() || Path::new("/sbin/openrc-run").exists() {
+            InitSystem::OpenRc
+        } else {
+            InitSystem::SysV
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InitSystem::Systemd => "systemd",
+            InitSystem::SystemdUser => "systemd (user)",
+            InitSystem::OpenRc => "openrc",
+            InitSystem::SysV => "sysv",
+        }
+    }
+
+    /// Whether installing needs root
+    pub fn is_system_wide(&self) -> bool {
+        *self != InitSystem::SystemdUser
+    }
+}
+
+/// Whose applications are started at boot, and by which harissa binary
+#[derive(Debug, Clone)]
+pub struct StartupTarget {
+    pub user: String,
+    pub home: PathBuf,
+    pub harissa_path: PathBuf,
+    /// A harissa home other than `~/.harissa_apps`, passed on as `HARISSA_HOME`
+    pub harissa_home: Option<PathBuf>,
+}
+
+/// A generated startup file plus the commands that enable and disable it
+#[derive(Debug, Clone)]
+pub struct InitArtifact {
+    pub path: PathBuf,
+    pub contents: String,
+    pub executable: bool,
+    pub enable_commands: Vec<Vec<String>>,
+    pub disable_commands: Vec<Vec<String>>,
+}
+
+/// Generate the startup artifact for an init system
+pub fn generate(init: InitSystem, target: &StartupTarget) -> InitArtifact {
+    let service = format!("harissa-{}", target.user);
+
+    match init {
+        InitSystem::Systemd => InitArtifact {
+            path: PathBuf::from(format!("/etc/systemd/system/{}.service", service)),
+            contents: systemd_unit(target, true),
+            executable: false,
+            enable_commands: vec![
+                command(&["systemctl", "daemon-reload"]),
+                command(&["systemctl", "enable", &format!("{}.service", service)]),
+            ],
+            disable_commands: vec![
+                command(&["systemctl", "disable", &format!("{}.service", service)]),
+                command(&["systemctl", "daemon-reload"]),
+            ],
+        },
+        InitSystem::SystemdUser => InitArtifact {
+            path: target.home.join(".config/systemd/user/harissa.service"),
+            contents: systemd_unit(target, false),
+            executable: false,
+            enable_commands: vec![
+                command(&["systemctl", "--user", "daemon-reload"]),
+                command(&["systemctl", "--user", "enable", "harissa.service"]),
+            ],
+            disable_commands: vec![
+                command(&["systemctl", "--user", "disable", "harissa.service"]),
+                command(&["systemctl", "--user", "daemon-reload"]),
+            ],
+        },
+        InitSystem::OpenRc => InitArtifact {
+            path: PathBuf::from(format!("/etc/init.d/{}", service)),
+            contents: openrc_script(target),
+            executable: true,
+            enable_commands: vec![command(&["rc-update", "add", &service, "default"])],
+            disable_commands: vec![command(&["rc-update", "del", &service, "default"])],
+        },
+        InitSystem::SysV => {
+            let (enable, disable) = if Path::new("/usr/sbin/update-rc.d").exists() || Path::new("/sbin/update-rc.d").exists() {
+                (command(&["update-rc.d", &service, "defaults"]), command(&["update-rc.d", "-f", &service, "remove"]))
+            } else {
+                (command(&["chkconfig", "--add", &service]), command(&["chkconfig", "--del", &service]))
+            };
+
+            InitArtifact {
+                path: PathBuf::from(format!("/etc/init.d/{}", service)),
+                contents: sysv_script(target, &service),
+                executable: true,
+                enable_commands: vec![enable],
+                disable_commands: vec![disable],
+            }
+        },
+    }
+}
+
+fn command(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|part| part.to_string()).collect()
+}
+
+fn systemd_unit(target: &StartupTarget, system_wide: bool) -> String {
+    let mut unit = String::new();
+    let _ = writeln!(unit, "[Unit]");
+    let _ = writeln!(unit, "Description=Harissa applications for {}", target.user);
+    let _ = writeln!(unit, "After=network.target");
+    let _ = writeln!(unit);
+    let _ = writeln!(unit, "[Service]");
+    let _ = writeln!(unit, "Type=oneshot");
+    let _ = writeln!(unit, "RemainAfterExit=yes");
+    if system_wide {
+        let _ = writeln!(unit, "User={}", target.user);
+    }
+    let _ = writeln!(unit, "Environment={}", systemd_quote(&format!("HOME={}", target.home.display())));
+    if let Some(harissa_home) = &target.harissa_home {
+        let _ = writeln!(unit, "Environment={}", systemd_quote(&format!("{}={}", home::HOME_ENV, harissa_home.display())));
+    }
+    let _ = writeln!(unit, "ExecStart={} resurrect", systemd_quote(&target.harissa_path.to_string_lossy()));
+    // '-': having nothing left to stop is not a failure
+    let _ = writeln!(unit, "ExecStop=-{} stop all --yes", systemd_quote(&target.harissa_path.to_string_lossy()));
+    let _ = writeln!(unit);
+    let _ = writeln!(unit, "[Install]");
+    let _ = writeln!(unit, "WantedBy={}", if system_wide { "multi-user.target" } else { "default.target" });
+    unit
+}
+
+/// The shell command that runs a harissa command (e.g. `resurrect`) as the target user
+fn harissa_shell_command(target: &StartupTarget, harissa_command: &str) -> String {
+    let harissa_home = match &target.harissa_home {
+        Some(harissa_home) => format!("{}={} ", home::HOME_ENV, shell_quote(&harissa_home.to_string_lossy())),
+        None => String::new(),
+    };
+    let command = format!(
+        "HOME={} {}{} {}",
+        shell_quote(&target.home.to_string_lossy()),
+        harissa_home,
+        shell_quote(&target.harissa_path.to_string_lossy()),
+        harissa_command,
+    );
+
+    if target.user == "root" {
+        command
+    } else {
+        format!("su -s /bin/sh -c {} {}", shell_quote(&command), shell_quote(&target.user))
+    }
+}
+
+fn openrc_script(target: &StartupTarget) -> String {
+    let mut script = String::new();
+    let _ = writeln!(script, "#!/sbin/openrc-run");
+    let _ = writeln!(script);
+    let _ = writeln!(script, "description=\"Harissa applications for {}\"", target.user);
+    let _ = writeln!(script);
+    let _ = writeln!(script, "depend() {{");
+    let _ = writeln!(script, "    need net");
+    let _ = writeln!(script, "    after firewall");
+    let _ = writeln!(script, "}}");
+    let _ = writeln!(script);
+    let _ = writeln!(script, "start() {{");
+    let _ = writeln!(script, "    ebegin \"Starting harissa applications for {}\"", target.user);
+    let _ = writeln!(script, "    {}", harissa_shell_command(target, "resurrect"));
+    let _ = writeln!(script, "    eend $?");
+    let _ = writeln!(script, "}}");
+    let _ = writeln!(script);
+    let _ = writeln!(script, "stop() {{");
+    let _ = writeln!(script, "    ebegin \"Stopping harissa applications for {}\"", target.user);
+    let _ = writeln!(script, "    {} || true", harissa_shell_command(target, "stop all --yes"));
+    let _ = writeln!(script, "    eend 0");
+    let _ = writeln!(script, "}}");
+    script
+}
+
+fn sysv_script(target: &StartupTarget, service: &str) -> String {
+    let mut script = String::new();
+    let _ = writeln!(script, "#!/bin/sh");
+    let _ = writeln!(script, "### BEGIN INIT INFO");
+    let _ = writeln!(script, "# Provides:          {}", service);
+    let _ = writeln!(script, "# Required-Start:    $local_fs $remote_fs $network");
+    let _ = writeln!(script, "# Required-Stop:     $local_fs $remote_fs $network");
+    let _ = writeln!(script, "# Default-Start:     2 3 4 5");
+    let _ = writeln!(script, "# Default-Stop:      0 1 6");
+    let _ = writeln!(script, "# Short-Description: Harissa applications for {}", target.user);
+    let _ = writeln!(script, "# Description:       Starts the applications saved with 'harissa save'.");
+    let _ = writeln!(script, "### END INIT INFO");
+    let _ = writeln!(script);
+    let _ = writeln!(script, "do_start() {{");
+    let _ = writeln!(script, "    echo \"Starting harissa applications for {}\"", target.user);
+    let _ = writeln!(script, "    {}", harissa_shell_command(target, "resurrect"));
+    let _ = writeln!(script, "}}");
+    let _ = writeln!(script);
+    let _ = writeln!(script, "do_stop() {{");
+    let _ = writeln!(script, "    echo \"Stopping harissa applications for {}\"", target.user);
+    let _ = writeln!(script, "    {} || true", harissa_shell_command(target, "stop all --yes"));
+    let _ = writeln!(script, "}}");
+    let _ = writeln!(script);
+    let _ = writeln!(script, "case \"$1\" in");
+    let _ = writeln!(script, "    start)");
+    let _ = writeln!(script, "        do_start");
+    let _ = writeln!(script, "        ;;");
+    let _ = writeln!(script, "    stop)");
+    let _ = writeln!(script, "        do_stop");
+    let _ = writeln!(script, "        ;;");
+    let _ = writeln!(script, "    restart|force-reload)");
+    let _ = writeln!(script, "        do_stop");
+    let _ = writeln!(script, "        do_start");
+    let _ = writeln!(script, "        ;;");
+    let _ = writeln!(script, "    status)");
+    let _ = writeln!(script, "        ;;");
+    let _ = writeln!(script, "    *)");
+    let _ = writeln!(script, "        echo \"Usage: $0 {{start|stop|restart|force-reload|status}}\"");
+    let _ = writeln!(script, "        exit 3");
+    let _ = writeln!(script, "        ;;");
+    let _ = writeln!(script, "esac");
+    let _ = writeln!(script);
+    let _ = writeln!(script, "exit 0");
+    script
+}
+
+/// Quote a value for a POSIX shell, leaving values without special characters as they are
+pub fn shell_quote(value: &str) -> String {
+    let is_plain = !value.is_empty()
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c));
+
+    if is_plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Quote a value for a systemd unit setting
+pub fn systemd_quote(value: &str) -> String {
+    // '%' starts a specifier in unit files
+    let value = value.replace('%', "%%");
+    if value.chars().any(|c| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value
+    }
+}
@@ -0,0 +1,223 @@
+// Launching applications from their spec, shared by start, reload and resurrect
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use crate::app_ids;
+use crate::app_spec::{self, AppSpec};
+use crate::cgroup;
+use crate::credentials;
+use crate::error::HarissaError;
+use crate::events::{self, EventKind};
+use crate::home;
+use crate::hooks::{self, Hook};
+use crate::notice::Notice;
+use crate::process_identity;
+use crate::signals;
+use crate::supervisor::{self, ScheduleEntry, ScheduleKind};
+
+/// What to do with existing log files when launching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogMode {
+    /// Start with empty logs (a new application)
+    Truncate,
+    /// Keep writing after the existing output (a restart)
+    Append,
+}
+
+/// Spawn an application in the background with its log files, cgroup, attributes and credentials.
+/// Returns the PID of the detached process.
+pub fn spawn_app(harissa_dir: &Path, name: &str, spec: &AppSpec, log_mode: LogMode, notify: &dyn Fn(Notice)) -> Result<u32, HarissaError> {
+    if spec.argv.is_empty() {
+        return Err(HarissaError::Failed(format!("No command stored for {}", name)));
+    }
+
+    // Resolve the identity to run as up front so a bad --user fails before anything is created
+    let credentials = credentials::resolve(spec.user.as_deref(), spec.group.as_deref())?;
+
+    // Prepare log files
+    let stdout_log = home::log_path(harissa_dir, name, "out");
+    let stderr_log = home::log_path(harissa_dir, name, "err");
+
+    let open_log = |path: &Path| match log_mode {
+        LogMode::Truncate => File::create(path),
+        LogMode::Append => OpenOptions::new().create(true).append(true).open(path),
+    };
+
+    let stdout_file = open_log(&stdout_log)
+        .map_err(|e| HarissaError::io(&format!("Failed to open stdout log file {}", stdout_log.display()), e))?;
+    let stderr_file = open_log(&stderr_log)
+        .map_err(|e| HarissaError::io(&format!("Failed to open stderr log file {}", stderr_log.display()), e))?;
+
+    if let Some(credentials) = &credentials {
+        credentials.chown(&stdout_log)?;
+        credentials.chown(&stderr_log)?;
+    }
+
+    // Give the app its own cgroup; without cgroup delegation it simply runs unconfined
+    let app_cgroup = match cgroup::create_app_cgroup(harissa_dir, name, &spec.limits) {
+        Ok(app_cgroup) => Some(app_cgroup),
+        Err(e) => {
+            if !spec.limits.is_empty() {
+                notify(Notice::Warning(format!("resource limits not applied: {}", e)));
+            }
+            None
+        }
+    };
+
+    let mut command = Command::new(&spec.argv[0]);
+    command
+        .args(&spec.argv[1..])
+        .stdout(Stdio::from(stdout_file))
+        .stderr(Stdio::from(stderr_file))
+        // Its own process group, so `signal --group` reaches its children and nothing else
+        .process_group(0);
+
+    if let Some(cwd) = &spec.cwd {
+        command.current_dir(cwd);
+    }
+    if !spec.env.is_empty() {
+        command.env_clear().envs(spec.env.iter().map(|(name, value)| (name, value)));
+    }
+
+    // Joining the cgroup comes first, while the child still has the rights to move itself
+    let app_cgroup = app_cgroup.filter(|app_cgroup| match cgroup::join_on_spawn(&mut command, app_cgroup) {
+        Ok(()) => true,
+        Err(e) => {
+            notify(Notice::Warning(e));
+            false
+        }
+    });
+
+    spec.attributes.apply(&mut command)?;
+    if let Some(credentials) = &credentials {
+        // Dropping privileges comes last, after the attributes that may need root
+        credentials.apply(&mut command);
+    }
+
+    let child = command
+        .spawn()
+        .map_err(|e| HarissaError::io(&format!("Failed to start command '{}'", spec.argv[0]), e))?;
+
+    let pid = child.id();
+
+    if let Some(app_cgroup) = &app_cgroup {
+        // A process that exited right away has left its cgroup already
+        if !cgroup::contains(app_cgroup, pid) && is_running(pid) {
+            notify(Notice::Warning(format!("process {} could not join cgroup {}", pid, app_cgroup.display())));
+        } else if !spec.limits.is_empty() {
+            notify(Notice::Progress(format!("Resource limits applied via cgroup {}", app_cgroup.display())));
+        }
+    }
+
+    // Detach the child process - we don't need to wait for it
+    std::mem::forget(child);
+
+    // Remember which process this is, so a later process with a recycled PID is not mistaken for it.
+    // One that could not be recorded would never be signalled, so it must not be left running;
+    // one that already exited has nothing to be mistaken for.
+    if let Err(e) = process_identity::record(harissa_dir, name, pid)
+        && process_identity::of(pid).is_some()
+    {
+        let _ = signals::send_to_group(pid, libc::SIGKILL);
+        return Err(e);
+    }
+
+    Ok(pid)
+}
+
+/// Start a new application: spawn it, track it in processes.csv, give it an ID, save its spec
+/// and register its scheduled restart. Returns the PID and the ID.
+pub fn start_app(harissa_dir: &Path, name: &str, spec: &AppSpec, notify: &dyn Fn(Notice)) -> Result<(u32, u32), HarissaError> {
+    // The pre-start hook writes into the new, empty logs, so the app must not truncate them again
+    let log_mode = if spec.hooks.pre_start.is_some() {
+        for stream in ["out", "err"] {
+            File::create(home::log_path(harissa_dir, name, stream))
+                .map_err(|e| HarissaError::io("Failed to create log file", e))?;
+        }
+        hooks::run(harissa_dir, name, spec, Hook::PreStart, None)?;
+        LogMode::Append
+    } else {
+        LogMode::Truncate
+    };
+
+    let pid = spawn_app(harissa_dir, name, spec, log_mode, notify)?;
+
+    track_process(harissa_dir, pid, name, &spec.command_line())?;
+    let id = app_ids::assign(harissa_dir, name)?;
+
+    // Remember the launch spec so reload and resurrect start the app the same way
+    app_spec::save(harissa_dir, name, spec)?;
+
+    events::record(harissa_dir, EventKind::Start, name, Some(pid), &spec.command_line());
+    hooks::run_or_warn(harissa_dir, name, spec, Hook::PostStart, Some(pid), notify);
+
+    if let Some(cron) = &spec.cron_restart {
+        supervisor::add_schedule(harissa_dir, ScheduleEntry {
+            kind: ScheduleKind::Restart,
+            name: name.to_string(),
+            cron: cron.clone(),
+            argv: spec.argv.clone(),
+            cwd: spec.cwd.clone(),
+        })?;
+        notify(Notice::Progress(format!("Scheduled restart of {} at \"{}\"", name, cron)));
+        supervisor::ensure_running(harissa_dir, notify)?;
+    }
+
+    // The supervisor watches for crashes of applications with an on-crash hook
+    if spec.hooks.on_crash.is_some() && spec.cron_restart.is_none() {
+        supervisor::ensure_running(harissa_dir, notify)?;
+    }
+
+    Ok((pid, id))
+}
+
+/// Add a process to processes.csv
+pub fn track_process(harissa_dir: &Path, pid: u32, name: &str, command_line: &str) -> Result<(), HarissaError> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(harissa_dir.join("processes.csv"))
+        .map_err(|e| HarissaError::io("Failed to open processes file", e))?;
+
+    writeln!(file, "{},{},{}", pid, name, command_line)
+        .map_err(|e| HarissaError::io("Failed to write to processes file", e))
+}
+
+/// Names of the tracked applications that are still running
+pub fn running_app_names(harissa_dir: &Path) -> Result<Vec<String>, HarissaError> {
+    let processes_file = harissa_dir.join("processes.csv");
+    if !processes_file.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(&processes_file)
+        .map_err(|e| HarissaError::io("Failed to open processes file", e))?;
+
+    let mut names = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| HarissaError::io("Failed to read line", e))?;
+        let parts: Vec<&str> = line.split(',').collect();
+
+        if parts.len() < 3 {
+            continue;
+        }
+
+        if process_identity::is_running(harissa_dir, parts[1], parts[0]) {
+            names.push(parts[1].to_string());
+        }
+    }
+
+    Ok(names)
+}
+
+/// Check whether a process is alive
+pub fn is_running(pid: u32) -> bool {
+    Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "stat="])
+        .output()
+        // Zombies have exited but not been reaped yet
+        .map(|output| output.status.success() && !String::from_utf8_lossy(&output.stdout).trim_start().starts_with('Z'))
+        .unwrap_or(false)
+}
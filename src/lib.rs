@@ -0,0 +1,61 @@
+//! Harissa runs applications in the background and keeps track of them.
+//!
+//! The [`Harissa`] client starts, lists, stops and reloads applications and reads their logs,
+//! working on the same `~/.harissa_apps` directory as the `harissa` command:
+//!
+//! ```no_run
+//! use harissa::{AppRef, AppSpec, Harissa};
+//!
+//! let harissa = Harissa::new()?;
+//! let spec = AppSpec { argv: vec!["python3".into(), "-m".into(), "http.server".into()], ..AppSpec::default() };
+//! let app = harissa.start("http", &spec)?;
+//! println!("started {} with PID {}", app.name, app.pid);
+//!
+//! for status in harissa.list()? {
+//!     println!("{} is {}", status.name, status.state.as_str());
+//! }
+//!
+//! harissa.delete(&AppRef::Name("http".into()))?;
+//! # Ok::<(), harissa::HarissaError>(())
+//! ```
+//!
+//! The library does not print: warnings and progress, such as a failed hook, go to the observer
+//! given to [`Harissa::with_observer`], and are dropped without one.
+//!
+//! The other modules hold the building blocks the command line tool is made of.
+pub mod adopt;
+pub mod api;
+pub mod app_ids;
+pub mod app_spec;
+pub mod app_status;
+pub mod cgroup;
+pub mod client;
+pub mod config;
+pub mod control_socket;
+pub mod credentials;
+pub mod dependencies;
+pub mod error;
+pub mod events;
+pub mod glob;
+pub mod home;
+pub mod hooks;
+pub mod init_scripts;
+pub mod launcher;
+pub mod logs;
+pub mod metrics;
+pub mod namespace;
+pub mod notice;
+pub mod process_attrs;
+pub mod process_identity;
+pub mod signals;
+pub mod procfile;
+pub mod stopped_apps;
+pub mod supervisor;
+pub mod web;
+
+pub use app_spec::AppSpec;
+pub use app_status::{AppState, AppStatus};
+pub use client::{AppHandle, AppRef, Harissa, ReloadedApp, SignalledApp, StartedApp, StopOutcome, StoppedApp};
+pub use error::HarissaError;
+pub use logs::{LogLine, LogStream};
+pub use notice::Notice;
@@ -0,0 +1,132 @@
+// Reading and following the log files of applications
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use crate::home;
+
+/// How often followed log files are checked for new output
+const LOG_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Which log files of an application to read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogStream {
+    Out,
+    Err,
+    Both,
+}
+
+impl LogStream {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "out" => Ok(LogStream::Out),
+            "err" => Ok(LogStream::Err),
+            "both" => Ok(LogStream::Both),
+            other => Err(format!("Unknown stream '{}': expected out, err or both", other)),
+        }
+    }
+}
+
+/// One line of an application's output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    /// "out" or "err"
+    pub stream: &'static str,
+    pub line: String,
+}
+
+/// Log files of an application to read or follow
+#[derive(Debug, Clone)]
+pub struct LogSubscription {
+    pub name: String,
+    /// (stream name, path) of each log file
+    pub files: Vec<(&'static str, PathBuf)>,
+    /// How many existing lines of each file to send first
+    pub lines: usize,
+}
+
+impl LogSubscription {
+    pub fn new(harissa_dir: &Path, name: &str, stream: LogStream, lines: usize) -> Result<Self, String> {
+        let out = ("out", home::log_path(harissa_dir, name, "out"));
+        let err = ("err", home::log_path(harissa_dir, name, "err"));
+        let files = match stream {
+            LogStream::Out => vec![out],
+            LogStream::Err => vec![err],
+            LogStream::Both => vec![out, err],
+        };
+
+        if !files.iter().any(|(_, path)| path.exists()) {
+            return Err(format!("No logs found for '{}'", name));
+        }
+
+        Ok(LogSubscription { name: name.to_string(), files, lines })
+    }
+
+    /// The last `lines` lines of each log file, output before errors
+    pub fn recent(&self) -> Vec<LogLine> {
+        let mut recent = Vec::new();
+        self.send_recent(|line| {
+            recent.push(line.clone());
+            true
+        });
+        recent
+    }
+
+    /// Send the last `lines` lines of each log file, then every new line as it is written.
+    /// Stops when `on_line` returns false, e.g. because the client went away.
+    pub fn follow_logs(&self, mut on_line: impl FnMut(&LogLine) -> bool) {
+        let Some(mut offsets) = self.send_recent(&mut on_line) else {
+            return;
+        };
+        let mut partials = vec![String::new(); offsets.len()];
+
+        loop {
+            thread::sleep(LOG_POLL_INTERVAL);
+
+            for (((stream, path), offset), partial) in self.files.iter().zip(offsets.iter_mut()).zip(partials.iter_mut()) {
+                let Ok(mut file) = File::open(path) else {
+                    continue;
+                };
+
+                // Logs are truncated when an application is started again
+                let length = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                if length < *offset {
+                    *offset = 0;
+                    partial.clear();
+                }
+
+                let mut new_data = Vec::new();
+                if file.seek(SeekFrom::Start(*offset)).is_err() || file.read_to_end(&mut new_data).is_err() {
+                    continue;
+                }
+                *offset += new_data.len() as u64;
+
+                partial.push_str(&String::from_utf8_lossy(&new_data));
+                while let Some(end) = partial.find('\n') {
+                    let line: String = partial.drain(..=end).collect();
+                    if !on_line(&LogLine { stream, line: line.trim_end_matches('\n').to_string() }) {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send the last lines of each file; returns the size of each file, or `None` if `on_line` stopped
+    fn send_recent(&self, mut on_line: impl FnMut(&LogLine) -> bool) -> Option<Vec<u64>> {
+        let mut offsets = Vec::new();
+        for (stream, path) in &self.files {
+            let contents = std::fs::read(path).unwrap_or_default();
+            let text = String::from_utf8_lossy(&contents);
+            let recent: Vec<&str> = text.lines().collect();
+            for line in &recent[recent.len().saturating_sub(self.lines)..] {
+                if !on_line(&LogLine { stream, line: line.to_string() }) {
+                    return None;
+                }
+            }
+            offsets.push(contents.len() as u64);
+        }
+        Some(offsets)
+    }
+}
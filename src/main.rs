@@ -2,26 +2,64 @@ mod commands;
 mod command_handlers;
 
 use std::env;
+use std::path::Path;
 use std::process;
 use commands::CommandRegistry;
+use harissa::error::HarissaError;
+use harissa::home;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     
     // Skip the program name (args[0])
-    let command_args = if args.len() > 1 {
+    let mut command_args = if args.len() > 1 {
         args[1..].to_vec()
     } else {
         Vec::new()
     };
     
-    let registry = CommandRegistry::new();
+    let mut json_errors = false;
+    let result = apply_global_options(&mut command_args, &mut json_errors)
+        .and_then(|()| CommandRegistry::new().execute(&command_args));
     
-    match registry.execute(&command_args) {
+    match result {
         Ok(()) => {},
         Err(e) => {
-            eprintln!("Error: {}", e);
-            process::exit(1);
+            if json_errors {
+                eprintln!("{}", e.to_json());
+            } else {
+                eprintln!("Error: {}", e);
+            }
+            process::exit(e.exit_code());
         }
     }
 }
+
+/// Remove the global options, which come before the command name, and apply them
+fn apply_global_options(args: &mut Vec<String>, json_errors: &mut bool) -> Result<(), HarissaError> {
+    while let Some(option) = args.first() {
+        match option.as_str() {
+            "--json-errors" => {
+                *json_errors = true;
+                args.remove(0);
+            },
+            "--home" => {
+                let dir = args.get(1)
+                    .ok_or_else(|| HarissaError::Usage("--home requires a directory".to_string()))?;
+                let dir = home::absolute(Path::new(dir))?;
+                // Commands and the processes they start, like the supervisor, read the home from here.
+                // Nothing else runs yet, so changing the environment is safe.
+                unsafe { env::set_var(home::HOME_ENV, dir) };
+                args.drain(..2);
+            },
+            "--system" => {
+                home::check_system_access()?;
+                // As for --home
+                unsafe { env::set_var(home::HOME_ENV, home::SYSTEM_HOME) };
+                args.remove(0);
+            },
+            _ => break,
+        }
+    }
+    Ok(())
+}
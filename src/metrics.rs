@@ -0,0 +1,250 @@
+// Prometheus text exposition of application status for `serve-metrics`
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use crate::app_status::{self, AppState, AppStatus};
+use crate::error::HarissaError;
+
+const STATES: [AppState; 4] = [AppState::Online, AppState::Paused, AppState::Errored, AppState::Stopped];
+/// How long a client may take to send its request before the connection is dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A per-application metric
+struct Metric {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    value: fn(&AppStatus) -> String,
+}
+
+/// Render the status of every application in the Prometheus text format. Series are labelled by
+/// application only, so they continue across reloads; the PID is a metric of its own.
+pub fn render(apps: &[AppStatus]) -> String {
+    let mut output = String::new();
+
+    let metrics = [
+        Metric { name: "harissa_app_cpu_percent", kind: "gauge", help: "CPU usage in percent of one CPU", value: |app| format!("{:.2}", app.cpu_percent) },
+        Metric { name: "harissa_app_memory_bytes", kind: "gauge", help: "Memory used by the application in bytes", value: |app| app.memory_bytes.to_string() },
+        Metric { name: "harissa_app_uptime_seconds", kind: "gauge", help: "Seconds since the application's process started", value: |app| app.uptime.as_secs().to_string() },
+        Metric { name: "harissa_app_restarts_total", kind: "counter", help: "Reloads and scheduled restarts since the application was started", value: |app| app.restarts.to_string() },
+        Metric { name: "harissa_app_healthy", kind: "gauge", help: "1 when the application's process is running and not suspended", value: |app| u8::from(app.is_healthy()).to_string() },
+    ];
+
+    for metric in metrics {
+        let _ = writeln!(output, "# HELP {} {}", metric.name, metric.help);
+        let _ = writeln!(output, "# TYPE {} {}", metric.name, metric.kind);
+        for app in apps {
+            let _ = writeln!(output, "{}{{app=\"{}\"}} {}", metric.name, escape_label(&app.name), (metric.value)(app));
+        }
+    }
+
+    let _ = writeln!(output, "# HELP harissa_app_pid PID of the application's process, for applications that have one");
+    let _ = writeln!(output, "# TYPE harissa_app_pid gauge");
+    for app in apps {
+        if let Ok(pid) = app.pid.parse::<u32>() {
+            let _ = writeln!(output, "harissa_app_pid{{app=\"{}\"}} {}", escape_label(&app.name), pid);
+        }
+    }
+
+    let _ = writeln!(output, "# HELP harissa_app_status 1 for the current state of the application (online, paused, errored or stopped)");
+    let _ = writeln!(output, "# TYPE harissa_app_status gauge");
+    for app in apps {
+        for state in STATES {
+            let _ = writeln!(output, "harissa_app_status{{app=\"{}\",state=\"{}\"}} {}", escape_label(&app.name), state.as_str(), u8::from(app.state == state));
+        }
+    }
+
+    let _ = writeln!(output, "# HELP harissa_apps Number of tracked applications");
+    let _ = writeln!(output, "# TYPE harissa_apps gauge");
+    let _ = writeln!(output, "harissa_apps {}", apps.len());
+
+    output
+}
+
+/// Serve `/metrics` on the listener until the process is stopped.
+/// Each connection gets its own thread, and each request collects fresh data.
+pub fn serve(listener: TcpListener, harissa_dir: &Path) -> Result<(), HarissaError> {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        let harissa_dir = harissa_dir.to_path_buf();
+        thread::spawn(move || {
+            if let Err(e) = handle(stream, &harissa_dir) {
+                eprintln!("Failed to answer metrics request: {}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, harissa_dir: &Path) -> Result<(), String> {
+    // A client that connects and sends nothing must not keep its thread forever
+    stream.set_read_timeout(Some(READ_TIMEOUT))
+        .map_err(|e| format!("Failed to set read timeout: {}", e))?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)
+        .map_err(|e| format!("Failed to read request: {}", e))?;
+
+    // Read the headers so closing the connection does not reset it before the client reads the response
+    let mut header = String::new();
+    while reader.read_line(&mut header).map(|read| read > 0).unwrap_or(false) && header.trim() != "" {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let (status, content_type, body) = match (method, path.split('?').next().unwrap_or("")) {
+        ("GET", "/metrics") => match app_status::collect(harissa_dir) {
+            Ok(apps) => ("200 OK", "text/plain; version=0.0.4; charset=utf-8", render(&apps)),
+            Err(e) => ("500 Internal Server Error", "text/plain; charset=utf-8", format!("{}\n", e)),
+        },
+        ("GET", "/") => ("200 OK", "text/html; charset=utf-8", "<html><body><a href=\"/metrics\">Metrics</a></body></html>\n".to_string()),
+        ("GET", _) => ("404 Not Found", "text/plain; charset=utf-8", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain; charset=utf-8", "Method not allowed\n".to_string()),
+    };
+
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, content_type, body.len(), body)
+        .map_err(|e| format!("Failed to write response: {}", e))
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Read;
+    use std::net::SocketAddr;
+    use std::process;
+    use crate::app_spec::AppSpec;
+    use crate::client::{AppRef, Harissa};
+
+    fn app(name: &str, pid: &str, state: AppState) -> AppStatus {
+        AppStatus {
+            id: Some(1),
+            pid: pid.to_string(),
+            name: name.to_string(),
+            command: "sleep 300".to_string(),
+            state,
+            cpu_percent: 12.345,
+            memory_bytes: 2048,
+            memory_percent: None,
+            uptime: Duration::from_secs(90),
+            restarts: 3,
+        }
+    }
+
+    #[test]
+    fn renders_app_metrics_labelled_by_name() {
+        let output = render(&[app("api", "1234", AppState::Online), app("worker", "-", AppState::Stopped)]);
+        let lines: Vec<&str> = output.lines().collect();
+
+        for expected in [
+            "# TYPE harissa_app_cpu_percent gauge",
+            "harissa_app_cpu_percent{app=\"api\"} 12.35",
+            "harissa_app_memory_bytes{app=\"api\"} 2048",
+            "harissa_app_uptime_seconds{app=\"api\"} 90",
+            "# TYPE harissa_app_restarts_total counter",
+            "harissa_app_restarts_total{app=\"api\"} 3",
+            "harissa_app_healthy{app=\"api\"} 1",
+            "harissa_app_healthy{app=\"worker\"} 0",
+            "harissa_app_pid{app=\"api\"} 1234",
+            "harissa_app_status{app=\"api\",state=\"online\"} 1",
+            "harissa_app_status{app=\"api\",state=\"stopped\"} 0",
+            "harissa_app_status{app=\"worker\",state=\"stopped\"} 1",
+            "harissa_apps 2",
+        ] {
+            assert!(lines.contains(&expected), "missing line {:?} in\n{}", expected, output);
+        }
+    }
+
+    #[test]
+    fn pid_is_only_a_metric_of_its_own() {
+        let output = render(&[app("api", "1234", AppState::Online), app("worker", "-", AppState::Stopped)]);
+        assert!(!output.contains("pid=\""), "{}", output);
+        assert!(!output.contains("harissa_app_pid{app=\"worker\"}"), "{}", output);
+    }
+
+    #[test]
+    fn every_sample_has_help_and_type() {
+        let output = render(&[app("api", "1234", AppState::Online)]);
+        for line in output.lines().filter(|line| !line.starts_with('#')) {
+            let name = line.split(['{', ' ']).next().unwrap();
+            assert!(output.contains(&format!("# HELP {} ", name)), "no HELP for {}", name);
+            assert!(output.contains(&format!("# TYPE {} ", name)), "no TYPE for {}", name);
+        }
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        let output = render(&[app("qa/\"odd\\name\"", "1", AppState::Errored)]);
+        assert!(output.contains("harissa_apps 1"));
+        assert!(output.contains("harissa_app_healthy{app=\"qa/\\\"odd\\\\name\\\"\"} 0"), "{}", output);
+    }
+
+    #[test]
+    fn renders_no_samples_without_apps() {
+        let output = render(&[]);
+        assert!(output.lines().filter(|line| !line.starts_with('#')).eq(["harissa_apps 0"]));
+    }
+
+    /// Send a request to the server and return the status code and body
+    fn get(address: SocketAddr, method: &str, target: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nAccept: text/plain\r\n\r\n", method, target).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.split_whitespace().nth(1).unwrap().parse().unwrap(), body.to_string())
+    }
+
+    #[test]
+    fn serves_fresh_metrics_over_http() {
+        let dir = std::env::temp_dir().join(format!("harissa-metrics-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let serve_dir = dir.clone();
+        thread::spawn(move || serve(listener, &serve_dir));
+
+        let (status, body) = get(address, "GET", "/metrics");
+        assert_eq!(status, 200);
+        assert!(body.lines().any(|line| line == "harissa_apps 0"), "{}", body);
+
+        // Each scrape sees the applications as they are now
+        let harissa = Harissa::with_dir(&dir);
+        let app = harissa.start("api", &AppSpec { argv: vec!["sleep".into(), "300".into()], ..AppSpec::default() }).unwrap();
+        let (status, body) = get(address, "GET", "/metrics?format=text");
+        let _ = harissa.delete(&AppRef::All);
+        // SAFETY: waitpid has no memory-safety preconditions
+        unsafe { libc::waitpid(app.pid as libc::pid_t, std::ptr::null_mut(), 0) };
+        assert_eq!(status, 200);
+        let lines: Vec<&str> = body.lines().collect();
+        assert!(lines.contains(&"harissa_apps 1"), "{}", body);
+        assert!(lines.contains(&format!("harissa_app_pid{{app=\"api\"}} {}", app.pid).as_str()), "{}", body);
+        assert!(lines.contains(&"harissa_app_status{app=\"api\",state=\"online\"} 1"), "{}", body);
+
+        assert_eq!(get(address, "GET", "/").0, 200);
+        assert_eq!(get(address, "GET", "/elsewhere"), (404, "Not found\n".to_string()));
+        assert_eq!(get(address, "POST", "/metrics").0, 405);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,33 @@
+// Namespaces keep several environments, e.g. staging and QA, apart on one host
+//
+// An application in a namespace is tracked under the qualified name `<namespace>/<name>`, so its
+// spec and logs live in `~/.harissa_apps/<namespace>/`. Applications without a namespace keep
+// their plain name and files in `~/.harissa_apps`.
+
+/// Separates the namespace from the name in qualified names
+pub const SEPARATOR: char = '/';
+
+/// `<namespace>/<name>`, or the name itself without a namespace
+pub fn qualify(namespace: Option<&str>, name: &str) -> String {
+    match namespace {
+        Some(namespace) => format!("{}{}{}", namespace, SEPARATOR, name),
+        None => name.to_string(),
+    }
+}
+
+/// The namespace (if any) and name of a qualified name
+pub fn split(qualified: &str) -> (Option<&str>, &str) {
+    match qualified.split_once(SEPARATOR) {
+        Some((namespace, name)) => (Some(namespace), name),
+        None => (None, qualified),
+    }
+}
+
+/// Namespaces become directory names, so only letters, digits, `-`, `_` and `.` are allowed
+pub fn validate(namespace: &str) -> Result<(), String> {
+    let valid_chars = namespace.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if namespace.is_empty() || namespace.starts_with('.') || !valid_chars {
+        return Err(format!("Invalid namespace '{}': use letters, digits, '-', '_' and '.', not starting with '.'", namespace));
+    }
+    Ok(())
+}
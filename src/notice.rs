@@ -0,0 +1,23 @@
+// Warnings and progress reported while an operation runs
+//
+// The library never prints them itself: the `harissa` command prints them as they come, and
+// other callers can pass an observer to `Harissa::with_observer`.
+use std::fmt;
+
+/// Something harissa reports without failing the operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notice {
+    /// Part of the operation went wrong, e.g. a hook failed or limits could not be applied
+    Warning(String),
+    /// What harissa is doing, e.g. waiting for a dependency or starting the supervisor
+    Progress(String),
+}
+
+impl fmt::Display for Notice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Notice::Warning(message) => write!(f, "Warning: {}", message),
+            Notice::Progress(message) => f.write_str(message),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+// POSIX resource limits and process attributes applied to a child before exec
+use std::ffi::CString;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use crate::error::HarissaError;
+
+/// Process attribute options accepted by `start`
+pub const ATTRIBUTE_OPTIONS: [&str; 6] = ["--nofile", "--nproc", "--core", "--nice", "--umask", "--oom-score-adj"];
+
+/// The type of the resource argument of setrlimit: glibc declares it as an enum, other C
+/// libraries such as musl as an int, and the `RLIMIT_*` constants have the matching type
+#[cfg(target_env = "gnu")]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(not(target_env = "gnu"))]
+type RlimitResource = libc::c_int;
+
+/// A soft/hard resource limit pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rlimit {
+    pub soft: libc::rlim_t,
+    pub hard: libc::rlim_t,
+}
+
+/// Resource limits and attributes to set on a child process
+#[derive(Debug, Clone, Default)]
+pub struct ProcessAttributes {
+    pub nofile: Option<Rlimit>,
+    pub nproc: Option<Rlimit>,
+    pub core: Option<Rlimit>,
+    pub nice: Option<i32>,
+    pub umask: Option<u32>,
+    pub oom_score_adj: Option<i32>,
+}
+
+impl ProcessAttributes {
+    /// Check whether no attributes were requested
+    pub fn is_empty(&self) -> bool {
+        self.nofile.is_none() && self.nproc.is_none() && self.core.is_none()
+            && self.nice.is_none() && self.umask.is_none() && self.oom_score_adj.is_none()
+    }
+
+    /// Set an attribute from one of the `ATTRIBUTE_OPTIONS` command-line flags
+    pub fn set_option(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match option {
+            "--nofile" => self.nofile = Some(parse_rlimit(option, value)?),
+            "--nproc" => self.nproc = Some(parse_rlimit(option, value)?),
+            "--core" => self.core = Some(parse_rlimit(option, value)?),
+            "--nice" => {
+                let nice: i32 = value.parse().map_err(|_| format!("Invalid --nice '{}': expected a number", value))?;
+                if !(-20..=19).contains(&nice) {
+                    return Err(format!("Invalid --nice '{}': must be between -20 and 19", value));
+                }
+                self.nice = Some(nice);
+            },
+            "--umask" => {
+                let umask = u32::from_str_radix(value, 8)
+                    .map_err(|_| format!("Invalid --umask '{}': expected an octal mode such as 022", value))?;
+                if umask > 0o777 {
+                    return Err(format!("Invalid --umask '{}': must be at most 777", value));
+                }
+                self.umask = Some(umask);
+            },
+            "--oom-score-adj" => {
+                let score: i32 = value.parse().map_err(|_| format!("Invalid --oom-score-adj '{}': expected a number", value))?;
+                if !(-1000..=1000).contains(&score) {
+                    return Err(format!("Invalid --oom-score-adj '{}': must be between -1000 and 1000", value));
+                }
+                self.oom_score_adj = Some(score);
+            },
+            _ => return Err(format!("Unknown process attribute option: {}", option)),
+        }
+        Ok(())
+    }
+
+    /// The attributes as (option, value) pairs, in the form accepted by `set_option`
+    pub fn to_options(&self) -> Vec<(&'static str, String)> {
+        let mut options = Vec::new();
+        if let Some(limit) = self.nofile {
+            options.push(("--nofile", format_rlimit(limit)));
+        }
+        if let Some(limit) = self.nproc {
+            options.push(("--nproc", format_rlimit(limit)));
+        }
+        if let Some(limit) = self.core {
+            options.push(("--core", format_rlimit(limit)));
+        }
+        if let Some(nice) = self.nice {
+            options.push(("--nice", nice.to_string()));
+        }
+        if let Some(umask) = self.umask {
+            options.push(("--umask", format!("{:03o}", umask)));
+        }
+        if let Some(score) = self.oom_score_adj {
+            options.push(("--oom-score-adj", score.to_string()));
+        }
+        options
+    }
+
+    /// Arrange for the attributes to be applied in the child between fork and exec
+    pub fn apply(&self, command: &mut Command) -> Result<(), HarissaError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let attributes = self.clone();
+        // Prepared here because the child may not allocate between fork and exec
+        let oom_score_adj = match self.oom_score_adj {
+            Some(score) => Some(CString::new(score.to_string()).map_err(|e| HarissaError::Failed(e.to_string()))?),
+            None => None,
+        };
+
+        // SAFETY: the closure only makes async-signal-safe system calls and does not allocate
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(limit) = attributes.nofile {
+                    set_rlimit(libc::RLIMIT_NOFILE, limit)?;
+                }
+                if let Some(limit) = attributes.nproc {
+                    set_rlimit(libc::RLIMIT_NPROC, limit)?;
+                }
+                if let Some(limit) = attributes.core {
+                    set_rlimit(libc::RLIMIT_CORE, limit)?;
+                }
+                if let Some(nice) = attributes.nice
+                    && libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+                if let Some(umask) = attributes.umask {
+                    libc::umask(umask as libc::mode_t);
+                }
+                if let Some(score) = &oom_score_adj {
+                    write_oom_score_adj(score)?;
+                }
+                Ok(())
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse "N", "soft:hard" or "unlimited" into a resource limit
+fn parse_rlimit(option: &str, value: &str) -> Result<Rlimit, String> {
+    let parse_one = |part: &str| -> Result<libc::rlim_t, String> {
+        if part == "unlimited" {
+            return Ok(libc::RLIM_INFINITY);
+        }
+        part.parse().map_err(|_| format!("Invalid {} '{}': expected a number, soft:hard or 'unlimited'", option, value))
+    };
+
+    let limit = match value.split_once(':') {
+        Some((soft, hard)) => Rlimit { soft: parse_one(soft)?, hard: parse_one(hard)? },
+        None => {
+            let both = parse_one(value)?;
+            Rlimit { soft: both, hard: both }
+        }
+    };
+
+    if limit.soft > limit.hard {
+        return Err(format!("Invalid {} '{}': soft limit exceeds hard limit", option, value));
+    }
+
+    Ok(limit)
+}
+
+fn format_rlimit(limit: Rlimit) -> String {
+    let format_one = |value: libc::rlim_t| {
+        if value == libc::RLIM_INFINITY { "unlimited".to_string() } else { value.to_string() }
+    };
+
+    if limit.soft == limit.hard {
+        format_one(limit.soft)
+    } else {
+        format!("{}:{}", format_one(limit.soft), format_one(limit.hard))
+    }
+}
+
+fn set_rlimit(resource: RlimitResource, limit: Rlimit) -> io::Result<()> {
+    let rlimit = libc::rlimit { rlim_cur: limit.soft, rlim_max: limit.hard };
+    // SAFETY: rlimit is a valid, initialised struct
+    if unsafe { libc::setrlimit(resource, &rlimit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn write_oom_score_adj(score: &CString) -> io::Result<()> {
+    // SAFETY: both strings are NUL-terminated and the descriptor is closed before returning
+    unsafe {
+        let fd = libc::open(c"/proc/self/oom_score_adj".as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let bytes = score.as_bytes();
+        let written = libc::write(fd, bytes.as_ptr() as *const libc::c_void, bytes.len());
+        let error = io::Error::last_os_error();
+        libc::close(fd);
+        if written < 0 {
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rlimits() {
+        let limit = |soft, hard| Ok(Rlimit { soft, hard });
+        let unlimited = libc::RLIM_INFINITY;
+        let cases = [
+            ("1024", limit(1024, 1024)),
+            ("0", limit(0, 0)),
+            ("1024:4096", limit(1024, 4096)),
+            ("unlimited", limit(unlimited, unlimited)),
+            ("1024:unlimited", limit(1024, unlimited)),
+            ("4096:1024", Err(())),
+            ("unlimited:1024", Err(())),
+            ("-1", Err(())),
+            ("18446744073709551616", Err(())),
+            ("1024:", Err(())),
+            (":1024", Err(())),
+            ("1:2:3", Err(())),
+            ("infinity", Err(())),
+            ("", Err(())),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(parse_rlimit("--nofile", value).map_err(|_| ()), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn rlimits_round_trip_through_specs() {
+        for value in ["1024", "1024:4096", "unlimited", "0:unlimited"] {
+            assert_eq!(format_rlimit(parse_rlimit("--core", value).unwrap()), value);
+        }
+    }
+}
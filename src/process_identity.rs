@@ -0,0 +1,185 @@
+// Telling a tracked process apart from an unrelated one that got the same PID later
+//
+// PIDs are reused after a reboot or when they wrap around, so when an application is spawned
+// harissa records when its process started, which cgroups it is in and which executable it runs,
+// in `~/.harissa_apps/identities.tsv` (one `name<TAB>pid<TAB>start<TAB>cgroups<TAB>exe` line per
+// application). A tracked PID without a recorded identity, or whose process started at another
+// time or sits in other cgroups, is treated as not running, so it is never signalled.
+// Start time and cgroups survive exec; the executable does not, since applications may exec into
+// another program, so it is informational.
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::app_spec;
+use crate::error::HarissaError;
+
+const IDENTITIES_FILE: &str = "identities.tsv";
+/// Appended by the kernel to the executable path when the file was replaced, e.g. by a deploy
+pub const DELETED_SUFFIX: &str = " (deleted)";
+
+/// What identifies a process besides its PID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessIdentity {
+    /// Start time in clock ticks since the epoch, so it differs across reboots
+    pub start_time: u64,
+    /// The contents of `/proc/<pid>/cgroup`; unknown for identities recorded by older versions
+    pub cgroups: Option<String>,
+    /// Unknown when the process belongs to another user
+    pub exe: Option<PathBuf>,
+}
+
+/// The identity of a live process, or None if there is no such process or it is a zombie
+pub fn of(pid: u32) -> Option<ProcessIdentity> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name in parentheses may contain spaces, so fields are counted after it
+    let fields: Vec<&str> = stat.get(stat.rfind(')')? + 1..)?.split_whitespace().collect();
+    // fields[0] is the state (field 3 of proc(5)) and fields[19] the start time after boot (field 22)
+    if *fields.first()? == "Z" {
+        return None;
+    }
+    let ticks_after_boot: u64 = fields.get(19)?.parse().ok()?;
+
+    let boot_time: u64 = fs::read_to_string("/proc/stat").ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+    // SAFETY: sysconf has no preconditions
+    let ticks_per_second = u64::try_from(unsafe { libc::sysconf(libc::_SC_CLK_TCK) }).ok()?;
+
+    Some(ProcessIdentity {
+        start_time: boot_time * ticks_per_second + ticks_after_boot,
+        cgroups: fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok().map(|cgroups| cgroups.trim_end().to_string()),
+        exe: executable(pid),
+    })
+}
+
+fn executable(pid: u32) -> Option<PathBuf> {
+    let exe = fs::read_link(format!("/proc/{}/exe", pid)).ok()?;
+    let exe = exe.to_string_lossy();
+    Some(PathBuf::from(exe.strip_suffix(DELETED_SUFFIX).unwrap_or(&exe)))
+}
+
+fn identities_path(harissa_dir: &Path) -> PathBuf {
+    harissa_dir.join(IDENTITIES_FILE)
+}
+
+/// (name, PID, identity) of every recorded process
+fn load(harissa_dir: &Path) -> Vec<(String, String, ProcessIdentity)> {
+    let Ok(contents) = fs::read_to_string(identities_path(harissa_dir)) else {
+        return Vec::new();
+    };
+
+    contents.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.splitn(5, '\t').collect();
+            let name = fields.first()?.to_string();
+            let pid = fields.get(1)?.to_string();
+            let start_time = fields.get(2)?.parse().ok()?;
+            // Older versions wrote `name<TAB>pid<TAB>start<TAB>exe`, without the cgroups
+            let (cgroups, exe) = match fields.len() {
+                5 => (Some(app_spec::unescape(fields[3])).filter(|cgroups| !cgroups.is_empty()), fields.get(4)),
+                _ => (None, fields.get(3)),
+            };
+            let exe = exe.filter(|exe| !exe.is_empty()).map(PathBuf::from);
+            Some((name, pid, ProcessIdentity { start_time, cgroups, exe }))
+        })
+        .collect()
+}
+
+fn save(harissa_dir: &Path, identities: &[(String, String, ProcessIdentity)]) -> Result<(), HarissaError> {
+    let mut contents = String::new();
+    for (name, pid, identity) in identities {
+        let cgroups = app_spec::escape(identity.cgroups.as_deref().unwrap_or_default());
+        let exe = identity.exe.as_ref().map(|exe| exe.to_string_lossy().into_owned()).unwrap_or_default();
+        contents.push_str(&format!("{}\t{}\t{}\t{}\t{}\n", name, pid, identity.start_time, cgroups, exe));
+    }
+
+    let path = identities_path(harissa_dir);
+    fs::write(&path, contents)
+        .map_err(|e| HarissaError::io(&format!("Failed to write {}", path.display()), e))
+}
+
+/// Record the identity of an application's new process, replacing that of its previous one
+pub fn record(harissa_dir: &Path, name: &str, pid: u32) -> Result<(), HarissaError> {
+    let identity = of(pid).ok_or_else(|| HarissaError::Failed(format!("Process {} of {} exited right after starting", pid, name)))?;
+
+    let mut identities = load(harissa_dir);
+    identities.retain(|(recorded, _, _)| recorded != name);
+    identities.push((name.to_string(), pid.to_string(), identity));
+    save(harissa_dir, &identities)
+}
+
+/// Forget the identity of an application that is no longer tracked (best effort)
+pub fn forget(harissa_dir: &Path, name: &str) {
+    let mut identities = load(harissa_dir);
+    let count = identities.len();
+    identities.retain(|(recorded, _, _)| recorded != name);
+    if identities.len() != count {
+        let _ = save(harissa_dir, &identities);
+    }
+}
+
+/// Whether the process an application was started as still runs under the PID tracked for it.
+/// A PID without a recorded identity cannot be told apart from a recycled one, so it is not.
+pub fn is_running(harissa_dir: &Path, name: &str, pid: &str) -> bool {
+    let Ok(pid_number) = pid.parse::<u32>() else {
+        return false;
+    };
+    let Some(current) = of(pid_number) else {
+        return false;
+    };
+
+    let recorded = load(harissa_dir).into_iter()
+        .find(|(recorded_name, recorded_pid, _)| recorded_name == name && recorded_pid == pid)
+        .map(|(_, _, identity)| identity);
+
+    // A process keeps its start time and cgroups across exec, so an executable that changed since
+    // (e.g. a shell that ran `exec node server.js`) is the same process running another program
+    recorded.is_some_and(|recorded| {
+        recorded.start_time == current.start_time
+            && (recorded.cgroups.is_none() || recorded.cgroups == current.cgroups)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{self, Command};
+
+    #[test]
+    fn a_pid_counts_only_with_a_matching_identity() {
+        let dir = std::env::temp_dir().join(format!("harissa-identity-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut child = Command::new("sleep").arg("300").spawn().unwrap();
+        let pid = child.id().to_string();
+
+        // Tracked without an identity, the PID could belong to anything
+        assert!(!is_running(&dir, "api", &pid));
+
+        record(&dir, "api", child.id()).unwrap();
+        assert!(is_running(&dir, "api", &pid));
+        assert!(!is_running(&dir, "web", &pid));
+
+        let line = fs::read_to_string(identities_path(&dir)).unwrap();
+        let fields: Vec<&str> = line.trim_end().splitn(5, '\t').collect();
+        let with_cgroups = |cgroups: &str| format!("api\t{}\t{}\t{}\t{}\n", pid, fields[2], cgroups, fields[4]);
+
+        // Another process that got the PID would sit in other cgroups, or have started at another time
+        fs::write(identities_path(&dir), with_cgroups("0::/elsewhere")).unwrap();
+        assert!(!is_running(&dir, "api", &pid));
+        let started_earlier = format!("api\t{}\t{}\t{}\t{}\n", pid, fields[2].parse::<u64>().unwrap() - 1, fields[3], fields[4]);
+        fs::write(identities_path(&dir), started_earlier).unwrap();
+        assert!(!is_running(&dir, "api", &pid));
+
+        // Lines of older versions have no cgroups, so only the start time is checked
+        fs::write(identities_path(&dir), format!("api\t{}\t{}\t{}\n", pid, fields[2], fields[4])).unwrap();
+        assert!(is_running(&dir, "api", &pid));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(!is_running(&dir, "api", &pid));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
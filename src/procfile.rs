@@ -0,0 +1,236 @@
+// Procfile and .env parsing for `start --procfile`
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::error::HarissaError;
+
+/// Port given to the first instance of the first process type; like foreman, each
+/// process type gets its own block of 100 ports
+pub const BASE_PORT: u32 = 5000;
+
+/// One `type: command` line of a Procfile
+#[derive(Debug, Clone)]
+pub struct ProcfileEntry {
+    pub process_type: String,
+    pub command: String,
+}
+
+/// Parse a Procfile, skipping blank lines and comments
+pub fn parse_procfile(path: &Path) -> Result<Vec<ProcfileEntry>, HarissaError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| HarissaError::io(&format!("Failed to read Procfile {}", path.display()), e))?;
+
+    let mut entries = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((process_type, command)) = line.split_once(':') else {
+            return Err(HarissaError::Usage(format!("Invalid Procfile line {}: expected '<type>: <command>'", number + 1)));
+        };
+
+        let process_type = process_type.trim();
+        let command = command.trim();
+        if process_type.is_empty() || !process_type.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(HarissaError::Usage(format!("Invalid process type '{}' on Procfile line {}", process_type, number + 1)));
+        }
+        if command.is_empty() {
+            return Err(HarissaError::Usage(format!("Missing command for '{}' on Procfile line {}", process_type, number + 1)));
+        }
+
+        entries.push(ProcfileEntry { process_type: process_type.to_string(), command: command.to_string() });
+    }
+
+    if entries.is_empty() {
+        return Err(HarissaError::Usage(format!("No processes found in Procfile {}", path.display())));
+    }
+
+    Ok(entries)
+}
+
+/// Parse a .env file of `KEY=value` lines; a missing file means no variables
+pub fn parse_env_file(path: &Path) -> Result<Vec<(String, String)>, HarissaError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = fs::read_to_string(path)
+        .map_err(|e| HarissaError::io(&format!("Failed to read {}", path.display()), e))?;
+
+    let mut variables = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            // Skip invalid lines
+            continue;
+        };
+
+        let value = value.trim();
+        let value = if value.len() >= 2
+            && ((value.starts_with('"') && value.ends_with('"')) || (value.starts_with('\'') && value.ends_with('\'')))
+        {
+            &value[1..value.len() - 1]
+        } else {
+            value
+        };
+
+        variables.push((key.trim().to_string(), value.to_string()));
+    }
+
+    Ok(variables)
+}
+
+/// Parse a formation such as "web=2,worker=1" or "all=2,web=3" into instance counts per process type
+pub fn parse_formation(formation: &str) -> Result<HashMap<String, usize>, String> {
+    let mut counts = HashMap::new();
+
+    for part in formation.split(',').filter(|part| !part.trim().is_empty()) {
+        let (process_type, count) = part.split_once('=')
+            .ok_or_else(|| format!("Invalid formation '{}': expected <type>=<count>", part))?;
+        let count: usize = count.trim().parse()
+            .map_err(|_| format!("Invalid instance count in formation '{}'", part))?;
+        counts.insert(process_type.trim().to_string(), count);
+    }
+
+    Ok(counts)
+}
+
+/// Number of instances to start for a process type (one unless the formation says otherwise)
+pub fn instance_count(formation: &HashMap<String, usize>, process_type: &str) -> usize {
+    formation.get(process_type)
+        .or_else(|| formation.get("all"))
+        .copied()
+        .unwrap_or(1)
+}
+
+/// The `sh -c` script running a Procfile command. Simple commands are exec'd so the PID is the
+/// app itself, except when they start with a variable assignment (`web: PORT=5000 bundle exec
+/// ...`), which `exec` would take for the command to run.
+pub fn shell_script(command: &str) -> String {
+    let is_simple = !command.contains([';', '&', '|', '\n']);
+    let first_word = command.split_whitespace().next().unwrap_or("");
+    let is_assignment = first_word.split_once('=').is_some_and(|(name, _)| {
+        name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    });
+
+    if is_simple && !is_assignment {
+        format!("exec {}", command)
+    } else {
+        command.to_string()
+    }
+}
+
+/// Replace $PORT and ${PORT} in a command, leaving longer names such as $PORT_NAME alone
+pub fn expand_port(command: &str, port: u32) -> String {
+    let command = command.replace("${PORT}", &port.to_string());
+    let mut expanded = String::with_capacity(command.len());
+    let mut rest = command.as_str();
+    while let Some(start) = rest.find("$PORT") {
+        let after = &rest[start + "$PORT".len()..];
+        expanded.push_str(&rest[..start]);
+        if after.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+            expanded.push_str("$PORT");
+        } else {
+            expanded.push_str(&port.to_string());
+        }
+        rest = after;
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::process;
+
+    /// Write `contents` to a file of its own under the temp directory
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("harissa-procfile-test-{}-{}", process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn parses_procfile_entries() {
+        let path = write_temp("valid", "# processes\nweb: bundle exec rails s -p $PORT\n\nworker:  python worker.py --queue a:b\n");
+        let entries = parse_procfile(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].process_type, "web");
+        assert_eq!(entries[0].command, "bundle exec rails s -p $PORT");
+        // Only the first colon separates the type from the command
+        assert_eq!(entries[1].process_type, "worker");
+        assert_eq!(entries[1].command, "python worker.py --queue a:b");
+    }
+
+    #[test]
+    fn rejects_invalid_procfiles() {
+        for (name, contents) in [
+            ("no-colon", "web bundle exec rails s\n"),
+            ("bad-type", "web server: ./server\n"),
+            ("no-command", "web:\n"),
+            ("empty", "# nothing here\n"),
+        ] {
+            let path = write_temp(name, contents);
+            let result = parse_procfile(&path);
+            fs::remove_file(&path).unwrap();
+            assert!(matches!(result, Err(HarissaError::Usage(_))), "{} was accepted", name);
+        }
+    }
+
+    #[test]
+    fn parses_env_files() {
+        let path = write_temp("env", "# settings\nexport DATABASE_URL=\"postgres://localhost/app\"\nSECRET='a b'\nDEBUG=1\ninvalid line\n");
+        let variables = parse_env_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(variables, vec![
+            ("DATABASE_URL".to_string(), "postgres://localhost/app".to_string()),
+            ("SECRET".to_string(), "a b".to_string()),
+            ("DEBUG".to_string(), "1".to_string()),
+        ]);
+        assert!(parse_env_file(Path::new("/nonexistent/.env")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parses_formations() {
+        let formation = parse_formation("web=2, worker=0,all=3").unwrap();
+        assert_eq!(instance_count(&formation, "web"), 2);
+        assert_eq!(instance_count(&formation, "worker"), 0);
+        assert_eq!(instance_count(&formation, "clock"), 3);
+        assert_eq!(instance_count(&parse_formation("").unwrap(), "web"), 1);
+
+        assert!(parse_formation("web").is_err());
+        assert!(parse_formation("web=two").is_err());
+    }
+
+    #[test]
+    fn expands_port() {
+        assert_eq!(expand_port("rails s -p $PORT", 5000), "rails s -p 5000");
+        assert_eq!(expand_port("serve --port=${PORT}", 5100), "serve --port=5100");
+        assert_eq!(expand_port("echo $PORT_NAME $PORT", 5000), "echo $PORT_NAME 5000");
+    }
+
+    #[test]
+    fn execs_simple_commands_only() {
+        assert_eq!(shell_script("bundle exec puma"), "exec bundle exec puma");
+        assert_eq!(shell_script("PORT=5000 bundle exec puma"), "PORT=5000 bundle exec puma");
+        assert_eq!(shell_script("_X=1 ./server"), "_X=1 ./server");
+        assert_eq!(shell_script("./migrate && ./server"), "./migrate && ./server");
+        assert_eq!(shell_script("./server | tee out"), "./server | tee out");
+        // Only a valid variable name before '=' makes an assignment
+        assert_eq!(shell_script("./server --port=5000"), "exec ./server --port=5000");
+        assert_eq!(shell_script("1X=2 ./server"), "exec 1X=2 ./server");
+    }
+}
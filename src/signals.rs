@@ -0,0 +1,127 @@
+// Sending signals to applications, e.g. SIGHUP to reload their configuration
+//
+// Signals are given by name, with or without the `SIG` prefix and in any case (`SIGHUP`, `hup`),
+// or by number (`1`), and are sent with kill(2).
+use std::io;
+use crate::error::HarissaError;
+
+/// Signals known by name, as (name without `SIG`, number)
+const SIGNALS: [(&str, libc::c_int); 31] = [
+    ("HUP", libc::SIGHUP), ("INT", libc::SIGINT), ("QUIT", libc::SIGQUIT), ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP), ("ABRT", libc::SIGABRT), ("IOT", libc::SIGIOT), ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE), ("KILL", libc::SIGKILL), ("USR1", libc::SIGUSR1), ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2), ("PIPE", libc::SIGPIPE), ("ALRM", libc::SIGALRM), ("TERM", libc::SIGTERM),
+    ("STKFLT", libc::SIGSTKFLT), ("CHLD", libc::SIGCHLD), ("CONT", libc::SIGCONT), ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP), ("TTIN", libc::SIGTTIN), ("TTOU", libc::SIGTTOU), ("URG", libc::SIGURG),
+    ("XCPU", libc::SIGXCPU), ("XFSZ", libc::SIGXFSZ), ("VTALRM", libc::SIGVTALRM), ("PROF", libc::SIGPROF),
+    ("WINCH", libc::SIGWINCH), ("IO", libc::SIGIO), ("SYS", libc::SIGSYS),
+];
+
+/// Parse a signal name like `SIGHUP` or `usr1`, or a signal number
+pub fn parse(signal: &str) -> Result<libc::c_int, String> {
+    if let Ok(number) = signal.parse::<libc::c_int>() {
+        let max = libc::SIGRTMAX();
+        return if (1..=max).contains(&number) {
+            Ok(number)
+        } else {
+            Err(format!("Invalid signal number {}: expected 1 to {}", number, max))
+        };
+    }
+
+    let upper = signal.to_ascii_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS.iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, number)| *number)
+        .ok_or_else(|| format!("Unknown signal '{}', e.g. use SIGHUP, SIGUSR1 or a number", signal))
+}
+
+/// The name of a signal, e.g. "SIGHUP", or its number for signals without one
+pub fn name(signal: libc::c_int) -> String {
+    match SIGNALS.iter().find(|(_, number)| *number == signal) {
+        Some((name, _)) => format!("SIG{}", name),
+        None => signal.to_string(),
+    }
+}
+
+/// Send a signal to a process
+pub fn send(pid: u32, signal: libc::c_int) -> Result<(), HarissaError> {
+    let pid = libc::pid_t::try_from(pid).map_err(|_| HarissaError::Usage(format!("Invalid PID {}", pid)))?;
+    // SAFETY: kill has no memory safety preconditions
+    let result = unsafe { libc::kill(pid, signal) };
+    check(result, &format!("process {}", pid))
+}
+
+/// Send a signal to every process in the process group led by `pid`. Fails unless the process
+/// leads its own group, so the signal never reaches processes that harissa did not start.
+pub fn send_to_group(pid: u32, signal: libc::c_int) -> Result<(), HarissaError> {
+    let pid = libc::pid_t::try_from(pid).map_err(|_| HarissaError::Usage(format!("Invalid PID {}", pid)))?;
+    // SAFETY: getpgid has no memory safety preconditions
+    let group = unsafe { libc::getpgid(pid) };
+    check(group, &format!("process {}", pid))?;
+    if group != pid {
+        return Err(HarissaError::Failed(format!("Process {} does not lead its own process group; reload it to give it one", pid)));
+    }
+
+    // SAFETY: kill has no memory safety preconditions
+    let result = unsafe { libc::kill(-group, signal) };
+    check(result, &format!("process group {}", group))
+}
+
+/// Turn the result of a system call into the error kinds used by the CLI
+fn check(result: libc::c_int, target: &str) -> Result<(), HarissaError> {
+    if result >= 0 {
+        return Ok(());
+    }
+
+    let error = io::Error::last_os_error();
+    let message = format!("Failed to signal {}: {}", target, error);
+    Err(match error.raw_os_error() {
+        Some(libc::ESRCH) => HarissaError::NotFound(message),
+        Some(libc::EPERM) => HarissaError::PermissionDenied(message),
+        _ => HarissaError::System(message),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_signal_names_and_numbers() {
+        let max = libc::SIGRTMAX();
+        let cases = [
+            ("SIGHUP".to_string(), Ok(libc::SIGHUP)),
+            ("HUP".to_string(), Ok(libc::SIGHUP)),
+            ("hup".to_string(), Ok(libc::SIGHUP)),
+            ("SigUsr1".to_string(), Ok(libc::SIGUSR1)),
+            ("usr2".to_string(), Ok(libc::SIGUSR2)),
+            ("SIGTERM".to_string(), Ok(libc::SIGTERM)),
+            ("IOT".to_string(), Ok(libc::SIGABRT)),
+            ("1".to_string(), Ok(libc::SIGHUP)),
+            ("9".to_string(), Ok(libc::SIGKILL)),
+            (max.to_string(), Ok(max)),
+            ("0".to_string(), Err(())),
+            ("-9".to_string(), Err(())),
+            ((max + 1).to_string(), Err(())),
+            ("SIG".to_string(), Err(())),
+            ("SIGSIGHUP".to_string(), Err(())),
+            ("SIG9".to_string(), Err(())),
+            ("NOPE".to_string(), Err(())),
+            ("".to_string(), Err(())),
+        ];
+        for (signal, expected) in cases {
+            assert_eq!(parse(&signal).map_err(|_| ()), expected, "{}", signal);
+        }
+    }
+
+    #[test]
+    fn names_signals() {
+        assert_eq!(name(libc::SIGHUP), "SIGHUP");
+        assert_eq!(name(libc::SIGABRT), "SIGABRT");
+        assert_eq!(name(libc::SIGRTMIN()), libc::SIGRTMIN().to_string());
+        for (signal, _) in SIGNALS {
+            assert_eq!(parse(&name(parse(signal).unwrap())).unwrap(), parse(signal).unwrap());
+        }
+    }
+}
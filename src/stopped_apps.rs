@@ -0,0 +1,55 @@
+// Applications paused with `stop`, which keep their spec, ID and logs so `start <name>` can
+// start them again
+//
+// Stored in `~/.harissa_apps/stopped.txt`, one qualified name per line. Stopped applications
+// have no entry in processes.csv.
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::error::HarissaError;
+
+const STOPPED_FILE: &str = "stopped.txt";
+
+fn stopped_path(harissa_dir: &Path) -> PathBuf {
+    harissa_dir.join(STOPPED_FILE)
+}
+
+/// Names of the stopped applications; a missing file means there are none
+pub fn load(harissa_dir: &Path) -> Result<Vec<String>, HarissaError> {
+    let path = stopped_path(harissa_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| HarissaError::io(&format!("Failed to read {}", path.display()), e))?;
+    Ok(contents.lines().filter(|line| !line.is_empty()).map(str::to_string).collect())
+}
+
+fn save(harissa_dir: &Path, names: &[String]) -> Result<(), HarissaError> {
+    let contents: String = names.iter().map(|name| format!("{}\n", name)).collect();
+
+    let path = stopped_path(harissa_dir);
+    fs::write(&path, contents)
+        .map_err(|e| HarissaError::io(&format!("Failed to write {}", path.display()), e))
+}
+
+/// Mark an application as stopped
+pub fn add(harissa_dir: &Path, name: &str) -> Result<(), HarissaError> {
+    let mut names = load(harissa_dir)?;
+    if !names.iter().any(|stopped| stopped == name) {
+        names.push(name.to_string());
+        save(harissa_dir, &names)?;
+    }
+    Ok(())
+}
+
+/// Forget that an application is stopped, because it runs again or was deleted
+pub fn remove(harissa_dir: &Path, name: &str) -> Result<(), HarissaError> {
+    let mut names = load(harissa_dir)?;
+    let count = names.len();
+    names.retain(|stopped| stopped != name);
+    if names.len() != count {
+        save(harissa_dir, &names)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,214 @@
+use chrono::{Datelike, Timelike};
+
+const MONTH_NAMES: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A parsed five-field cron expression (minute hour day-of-month month day-of-week)
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    // Standard cron semantics: when both day fields are restricted, either one may match.
+    // As in Vixie cron, a field starting with '*' (such as "*/2") is unrestricted.
+    day_of_month_any: bool,
+    day_of_week_any: bool,
+}
+
+impl CronSchedule {
+    /// Parse a cron expression such as "0 3 * * *", "*/15 9-17 * * mon-fri" or "@daily"
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expanded = match expression.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("Invalid cron expression '{}': expected 5 fields, found {}", expression, fields.len()));
+        }
+
+        let parse = |field: &str, min: u32, max: u32, names: &[&str], name_offset: u32| {
+            parse_field(field, min, max, names, name_offset)
+                .map_err(|e| format!("Invalid cron expression '{}': {}", expression, e))
+        };
+
+        let minutes = parse(fields[0], 0, 59, &[], 0)?;
+        let hours = parse(fields[1], 0, 23, &[], 0)?;
+        let days_of_month = parse(fields[2], 1, 31, &[], 0)?;
+        let months = parse(fields[3], 1, 12, &MONTH_NAMES, 1)?;
+        let mut days_of_week = parse(fields[4], 0, 7, &WEEKDAY_NAMES, 0)?;
+
+        // Both 0 and 7 mean Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(CronSchedule {
+            minutes,
+            hours,
+            days_of_month,
+            months,
+            days_of_week,
+            day_of_month_any: fields[2].starts_with('*'),
+            day_of_week_any: fields[4].starts_with('*'),
+        })
+    }
+
+    /// Check whether the schedule fires during the minute containing `time`
+    pub fn matches<T: Datelike + Timelike>(&self, time: &T) -> bool {
+        let has = |set: u64, value: u32| set & (1 << value) != 0;
+
+        if !has(self.minutes, time.minute()) || !has(self.hours, time.hour()) || !has(self.months, time.month()) {
+            return false;
+        }
+
+        let day_of_month = has(self.days_of_month, time.day());
+        let day_of_week = has(self.days_of_week, time.weekday().num_days_from_sunday());
+
+        if self.day_of_month_any || self.day_of_week_any {
+            day_of_month && day_of_week
+        } else {
+            day_of_month || day_of_week
+        }
+    }
+}
+
+/// Parse one cron field into a bitset of allowed values
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], name_offset: u32) -> Result<u64, String> {
+    let mut set = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err("step must be greater than zero".to_string());
+                }
+                (range, step)
+            },
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max, names, name_offset)?, parse_value(end, min, max, names, name_offset)?)
+        } else {
+            let value = parse_value(range, min, max, names, name_offset)?;
+            // "5/10" means every 10 starting at 5
+            (value, if part.contains('/') { max } else { value })
+        };
+
+        if start > end {
+            return Err(format!("invalid range '{}'", range));
+        }
+
+        let mut value = start;
+        while value <= end {
+            set |= 1 << value;
+            value += step;
+        }
+    }
+
+    Ok(set)
+}
+
+/// Parse a single numeric or named cron value
+fn parse_value(value: &str, min: u32, max: u32, names: &[&str], name_offset: u32) -> Result<u32, String> {
+    let lower = value.to_lowercase();
+    if let Some(index) = names.iter().position(|name| *name == lower) {
+        return Ok(index as u32 + name_offset);
+    }
+
+    let number: u32 = value.parse().map_err(|_| format!("invalid value '{}'", value))?;
+    if number < min || number > max {
+        return Err(format!("value {} out of range {}-{}", number, min, max));
+    }
+
+    Ok(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn matches_minute_and_hour() {
+        let schedule = CronSchedule::parse("30 3 * * *").unwrap();
+        assert!(schedule.matches(&at(2024, 5, 1, 3, 30)));
+        assert!(!schedule.matches(&at(2024, 5, 1, 3, 31)));
+        assert!(!schedule.matches(&at(2024, 5, 1, 4, 30)));
+    }
+
+    #[test]
+    fn parses_steps_ranges_lists_and_names() {
+        let schedule = CronSchedule::parse("*/15 9-17 * jan,jun mon-fri").unwrap();
+        // 2024-06-03 is a Monday, 2024-06-01 a Saturday
+        assert!(schedule.matches(&at(2024, 6, 3, 9, 45)));
+        assert!(!schedule.matches(&at(2024, 6, 3, 9, 50)));
+        assert!(!schedule.matches(&at(2024, 6, 3, 18, 0)));
+        assert!(!schedule.matches(&at(2024, 6, 1, 10, 0)));
+        assert!(!schedule.matches(&at(2024, 7, 1, 10, 0)));
+    }
+
+    #[test]
+    fn expands_shortcuts() {
+        let daily = CronSchedule::parse("@daily").unwrap();
+        assert!(daily.matches(&at(2024, 2, 29, 0, 0)));
+        assert!(!daily.matches(&at(2024, 2, 29, 0, 1)));
+
+        // 2024-06-02 is a Sunday
+        let weekly = CronSchedule::parse("@weekly").unwrap();
+        assert!(weekly.matches(&at(2024, 6, 2, 0, 0)));
+        assert!(!weekly.matches(&at(2024, 6, 3, 0, 0)));
+    }
+
+    #[test]
+    fn seven_is_sunday() {
+        let schedule = CronSchedule::parse("0 0 * * 7").unwrap();
+        assert!(schedule.matches(&at(2024, 6, 2, 0, 0)));
+    }
+
+    #[test]
+    fn either_day_field_matches_when_both_are_restricted() {
+        // The 1st of the month or any Monday
+        let schedule = CronSchedule::parse("0 0 1 * mon").unwrap();
+        assert!(schedule.matches(&at(2024, 6, 1, 0, 0)));
+        assert!(schedule.matches(&at(2024, 6, 3, 0, 0)));
+        assert!(!schedule.matches(&at(2024, 6, 4, 0, 0)));
+    }
+
+    #[test]
+    fn starred_day_field_with_step_counts_as_unrestricted() {
+        // Odd days of the month that are also Mondays, not odd days or Mondays
+        let schedule = CronSchedule::parse("0 0 */2 * mon").unwrap();
+        assert!(schedule.matches(&at(2024, 6, 17, 0, 0)));
+        assert!(!schedule.matches(&at(2024, 6, 10, 0, 0)));
+        assert!(!schedule.matches(&at(2024, 6, 5, 0, 0)));
+
+        let schedule = CronSchedule::parse("0 0 1 * */2").unwrap();
+        assert!(!schedule.matches(&at(2024, 6, 2, 0, 0)));
+        assert!(!schedule.matches(&at(2024, 7, 1, 0, 0)));
+        assert!(schedule.matches(&at(2024, 9, 1, 0, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(CronSchedule::parse("0 3 * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 * foo *").is_err());
+    }
+}
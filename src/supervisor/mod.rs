@@ -0,0 +1,480 @@
+// Background supervisor: runs cron-scheduled restarts and scheduled jobs, and on-crash hooks
+mod cron;
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::env;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use chrono::Local;
+use crate::app_spec;
+use crate::error::HarissaError;
+use crate::events::{self, EventKind};
+use crate::home;
+use crate::hooks::{self, Hook};
+use crate::notice::Notice;
+use crate::process_identity;
+
+pub use cron::CronSchedule;
+
+const SCHEDULES_FILE: &str = "schedules.tsv";
+const SUPERVISOR_PID_FILE: &str = "supervisor.pid";
+const SUPERVISOR_LOG_FILE: &str = "supervisor.log";
+
+/// How often the supervisor checks applications with an on-crash hook
+const CRASH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What the supervisor does when a schedule fires
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleKind {
+    /// Restart a tracked application through `reload`
+    Restart,
+    /// Launch a short-lived job and record its output and exit code
+    Job,
+}
+
+impl ScheduleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduleKind::Restart => "restart",
+            ScheduleKind::Job => "job",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "restart" => Some(ScheduleKind::Restart),
+            "job" => Some(ScheduleKind::Job),
+            _ => None,
+        }
+    }
+}
+
+/// A single line of the schedules file
+#[derive(Debug, Clone)]
+pub struct ScheduleEntry {
+    pub kind: ScheduleKind,
+    pub name: String,
+    pub cron: String,
+    /// The job to run, or the command of the restarted application (for display only)
+    pub argv: Vec<String>,
+    /// Where the job runs; the supervisor's own directory if none was recorded
+    pub cwd: Option<PathBuf>,
+}
+
+impl ScheduleEntry {
+    /// The command line as shown in `schedule` and in the job's log
+    pub fn command_line(&self) -> String {
+        self.argv.join(" ")
+    }
+
+    /// Parse a line of the schedules file:
+    /// `kind<TAB>name<TAB>cron<TAB>cwd<TAB>arg<TAB>arg...` with values escaped like in specs.
+    /// Lines written by older versions end with the whole command instead, split at whitespace.
+    fn parse(line: &str) -> Option<Self> {
+        let parts: Vec<&str> = line.split('\t').collect();
+        if parts.len() < 4 {
+            return None;
+        }
+
+        let kind = ScheduleKind::parse(parts[0])?;
+        let (cwd, argv) = if parts.len() == 4 {
+            (None, parts[3].split_whitespace().map(str::to_string).collect())
+        } else {
+            let cwd = Some(parts[3]).filter(|cwd| !cwd.is_empty()).map(|cwd| PathBuf::from(app_spec::unescape(cwd)));
+            (cwd, parts[4..].iter().map(|arg| app_spec::unescape(arg)).collect())
+        };
+
+        Some(ScheduleEntry { kind, name: parts[1].to_string(), cron: parts[2].to_string(), argv, cwd })
+    }
+
+    fn to_line(&self) -> String {
+        let cwd = self.cwd.as_ref().map(|cwd| app_spec::escape(&cwd.to_string_lossy())).unwrap_or_default();
+        let mut fields = vec![self.kind.as_str().to_string(), self.name.clone(), self.cron.clone(), cwd];
+        fields.extend(self.argv.iter().map(|arg| app_spec::escape(arg)));
+        fields.join("\t")
+    }
+}
+
+/// Read all schedules. Entries are tab separated because cron expressions contain commas.
+pub fn read_schedules(harissa_dir: &Path) -> Result<Vec<ScheduleEntry>, HarissaError> {
+    let schedules_file = harissa_dir.join(SCHEDULES_FILE);
+    if !schedules_file.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(&schedules_file)
+        .map_err(|e| HarissaError::io("Failed to open schedules file", e))?;
+
+    let mut entries = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| HarissaError::io("Failed to read line", e))?;
+        // Invalid lines are skipped
+        entries.extend(ScheduleEntry::parse(&line));
+    }
+
+    Ok(entries)
+}
+
+/// Overwrite the schedules file with the given entries
+pub fn write_schedules(harissa_dir: &Path, entries: &[ScheduleEntry]) -> Result<(), HarissaError> {
+    // Scheduled jobs may run as root, so the file is only writable by whoever wrote it
+    let mut file = app_spec::create_trusted_file(harissa_dir, &harissa_dir.join(SCHEDULES_FILE))
+        .map_err(|e| HarissaError::io("Failed to open schedules file for writing", e))?;
+
+    for entry in entries {
+        writeln!(file, "{}", entry.to_line())
+            .map_err(|e| HarissaError::io("Failed to write to schedules file", e))?;
+    }
+
+    Ok(())
+}
+
+/// Add a schedule, replacing any existing schedule of the same kind for the same name
+pub fn add_schedule(harissa_dir: &Path, entry: ScheduleEntry) -> Result<(), HarissaError> {
+    CronSchedule::parse(&entry.cron).map_err(HarissaError::Usage)?;
+
+    let mut entries = read_schedules(harissa_dir)?;
+    entries.retain(|existing| !(existing.kind == entry.kind && existing.name == entry.name));
+    entries.push(entry);
+
+    write_schedules(harissa_dir, &entries)
+}
+
+/// Remove all schedules of the given kind for a name, returning how many were removed
+pub fn remove_schedules(harissa_dir: &Path, kind: ScheduleKind, name: &str) -> Result<usize, HarissaError> {
+    let mut entries = read_schedules(harissa_dir)?;
+    let before = entries.len();
+    entries.retain(|entry| !(entry.kind == kind && entry.name == name));
+
+    let removed = before - entries.len();
+    if removed > 0 {
+        write_schedules(harissa_dir, &entries)?;
+    }
+
+    Ok(removed)
+}
+
+/// Start the supervisor in the background unless it is already running
+pub fn ensure_running(harissa_dir: &Path, notify: &dyn Fn(Notice)) -> Result<(), HarissaError> {
+    if let Some(pid) = running_supervisor_pid(harissa_dir) {
+        notify(Notice::Progress(format!("Supervisor already running with PID {}", pid)));
+        return Ok(());
+    }
+
+    let harissa_path = env::current_exe()
+        .map_err(|e| HarissaError::io("Failed to get harissa executable path", e))?;
+
+    let log_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(harissa_dir.join(SUPERVISOR_LOG_FILE))
+        .map_err(|e| HarissaError::io("Failed to open supervisor log file", e))?;
+    let err_file = log_file.try_clone()
+        .map_err(|e| HarissaError::io("Failed to open supervisor log file", e))?;
+
+    let child = Command::new(harissa_path)
+        .arg("supervise")
+        .env(home::HOME_ENV, harissa_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(log_file))
+        .stderr(Stdio::from(err_file))
+        .spawn()
+        .map_err(|e| HarissaError::io("Failed to start supervisor", e))?;
+
+    let pid = child.id();
+
+    // Detach the supervisor - it keeps running after we exit
+    std::mem::forget(child);
+
+    std::fs::write(harissa_dir.join(SUPERVISOR_PID_FILE), pid.to_string())
+        .map_err(|e| HarissaError::io("Failed to write supervisor PID file", e))?;
+
+    notify(Notice::Progress(format!("Started supervisor with PID {}", pid)));
+    Ok(())
+}
+
+/// PID of the supervisor recorded in the PID file, if that process is still alive
+fn running_supervisor_pid(harissa_dir: &Path) -> Option<String> {
+    let pid = std::fs::read_to_string(harissa_dir.join(SUPERVISOR_PID_FILE)).ok()?;
+    let pid = pid.trim().to_string();
+
+    let check_process = Command::new("ps")
+        .args(["-p", &pid, "-o", "pid="])
+        .output()
+        .ok()?;
+
+    if check_process.status.success() { Some(pid) } else { None }
+}
+
+/// Run the supervisor loop until there is nothing left to schedule or watch
+pub fn run(harissa_dir: &Path) -> Result<(), HarissaError> {
+    println!("[{}] Supervisor started", Local::now().format("%Y-%m-%d %H:%M:%S"));
+
+    // Jobs still running from a previous tick are not launched again
+    let running_jobs: Arc<Mutex<HashSet<String>>> = Arc::new(Mutex::new(HashSet::new()));
+    let mut last_minute = Local::now().timestamp() / 60;
+
+    loop {
+        thread::sleep(CRASH_CHECK_INTERVAL);
+
+        let watched_apps = check_crashes(harissa_dir)?;
+
+        // Schedules run once at the start of every minute
+        let now = Local::now();
+        if now.timestamp() / 60 == last_minute {
+            continue;
+        }
+        last_minute = now.timestamp() / 60;
+
+        let entries = read_schedules(harissa_dir)?;
+        let jobs_trusted = app_spec::check_trusted(&harissa_dir.join(SCHEDULES_FILE));
+
+        if entries.is_empty() && watched_apps == 0 && running_jobs.lock().map(|jobs| jobs.is_empty()).unwrap_or(true) {
+            println!("[{}] Nothing left to supervise, exiting", now.format("%Y-%m-%d %H:%M:%S"));
+            let _ = std::fs::remove_file(harissa_dir.join(SUPERVISOR_PID_FILE));
+            return Ok(());
+        }
+
+        for entry in entries {
+            let schedule = match CronSchedule::parse(&entry.cron) {
+                Ok(schedule) => schedule,
+                Err(e) => {
+                    eprintln!("Skipping schedule for {}: {}", entry.name, e);
+                    continue;
+                }
+            };
+
+            if !schedule.matches(&now) {
+                continue;
+            }
+
+            match entry.kind {
+                ScheduleKind::Restart => restart_app(harissa_dir, &entry),
+                ScheduleKind::Job => match &jobs_trusted {
+                    Ok(()) => run_job(harissa_dir, &entry, &running_jobs),
+                    Err(e) => eprintln!("Not running scheduled job {}: {}", entry.name, e),
+                },
+            }
+        }
+    }
+}
+
+/// Run the on-crash hook of tracked applications that exited without harissa stopping them.
+/// Returns how many applications with an on-crash hook are still running.
+fn check_crashes(harissa_dir: &Path) -> Result<usize, HarissaError> {
+    let processes_file = harissa_dir.join("processes.csv");
+    if !processes_file.exists() {
+        return Ok(0);
+    }
+
+    let file = File::open(&processes_file)
+        .map_err(|e| HarissaError::io("Failed to open processes file", e))?;
+
+    let mut watched_apps = 0;
+    for line in io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| HarissaError::io("Failed to read line", e))?;
+        let parts: Vec<&str> = line.split(',').collect();
+
+        if parts.len() < 3 {
+            continue;
+        }
+
+        let (pid, name) = (parts[0], parts[1]);
+        let Ok(spec) = app_spec::load_trusted(harissa_dir, name) else {
+            continue;
+        };
+        if spec.hooks.on_crash.is_none() {
+            continue;
+        }
+
+        if process_identity::is_running(harissa_dir, name, pid) {
+            watched_apps += 1;
+            continue;
+        }
+
+        if hooks::exit_handled(harissa_dir, name, pid) {
+            continue;
+        }
+
+        // Mark the crash as handled first so the hook runs only once
+        hooks::mark_exit_handled(harissa_dir, name, pid);
+        println!("[{}] {} ({}) exited unexpectedly", Local::now().format("%Y-%m-%d %H:%M:%S"), name, pid);
+        events::record(harissa_dir, EventKind::Crash, name, pid.parse().ok(), "exited without being stopped");
+
+        let harissa_dir = harissa_dir.to_path_buf();
+        let name = name.to_string();
+        let pid = pid.parse().ok();
+        thread::spawn(move || hooks::run_or_warn(&harissa_dir, &name, &spec, Hook::OnCrash, pid, &log_notice));
+    }
+
+    Ok(watched_apps)
+}
+
+/// The supervisor writes notices into its log, like everything else it reports
+fn log_notice(notice: Notice) {
+    println!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), notice);
+}
+
+/// Restart a tracked application by invoking `harissa reload <name>`
+fn restart_app(harissa_dir: &Path, entry: &ScheduleEntry) {
+    println!("[{}] Scheduled restart of {}", Local::now().format("%Y-%m-%d %H:%M:%S"), entry.name);
+    events::record(harissa_dir, EventKind::Restart, &entry.name, None, &format!("cron schedule \"{}\"", entry.cron));
+
+    let result = env::current_exe()
+        .map_err(|e| e.to_string())
+        .and_then(|harissa_path| {
+            Command::new(harissa_path)
+                .args(["reload", &entry.name])
+                .env(home::HOME_ENV, harissa_dir)
+                .status()
+                .map_err(|e| e.to_string())
+        });
+
+    match result {
+        Ok(status) if status.success() => {},
+        Ok(status) => eprintln!("Scheduled restart of {} failed with {}", entry.name, status),
+        Err(e) => eprintln!("Failed to run scheduled restart of {}: {}", entry.name, e),
+    }
+}
+
+/// Launch a scheduled job and record its output and exit code in the app's log files
+fn run_job(harissa_dir: &Path, entry: &ScheduleEntry, running_jobs: &Arc<Mutex<HashSet<String>>>) {
+    if let Ok(mut jobs) = running_jobs.lock()
+        && !jobs.insert(entry.name.clone())
+    {
+        println!("[{}] Job {} is still running, skipping this run", Local::now().format("%Y-%m-%d %H:%M:%S"), entry.name);
+        return;
+    }
+
+    let harissa_dir = harissa_dir.to_path_buf();
+    let entry = entry.clone();
+    let running_jobs = Arc::clone(running_jobs);
+
+    thread::spawn(move || {
+        if let Err(e) = run_job_to_completion(&harissa_dir, &entry) {
+            eprintln!("Scheduled job {} failed: {}", entry.name, e);
+        }
+
+        if let Ok(mut jobs) = running_jobs.lock() {
+            jobs.remove(&entry.name);
+        }
+    });
+}
+
+fn run_job_to_completion(harissa_dir: &Path, entry: &ScheduleEntry) -> Result<(), HarissaError> {
+    let Some((program, args)) = entry.argv.split_first() else {
+        return Err(HarissaError::Usage("Empty command".to_string()));
+    };
+
+    let stdout_log = home::log_path(harissa_dir, &entry.name, "out");
+    let stderr_log = home::log_path(harissa_dir, &entry.name, "err");
+
+    let mut stdout_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&stdout_log)
+        .map_err(|e| HarissaError::io("Failed to open stdout log file", e))?;
+
+    let stderr_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&stderr_log)
+        .map_err(|e| HarissaError::io("Failed to open stderr log file", e))?;
+
+    writeln!(stdout_file, "[harissa] {} Running scheduled job: {}", Local::now().format("%Y-%m-%d %H:%M:%S"), entry.command_line())
+        .map_err(|e| HarissaError::io("Failed to write to stdout log file", e))?;
+
+    let child_stdout = stdout_file.try_clone()
+        .map_err(|e| HarissaError::io("Failed to open stdout log file", e))?;
+
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(child_stdout))
+        .stderr(Stdio::from(stderr_file));
+    if let Some(cwd) = &entry.cwd {
+        command.current_dir(cwd);
+    }
+    let status = command.status();
+
+    let outcome = match status {
+        Ok(status) => match status.code() {
+            Some(code) => format!("exited with code {}", code),
+            None => "was terminated by a signal".to_string(),
+        },
+        Err(e) => format!("failed to start: {}", e),
+    };
+
+    writeln!(stdout_file, "[harissa] {} Scheduled job {}", Local::now().format("%Y-%m-%d %H:%M:%S"), outcome)
+        .map_err(|e| HarissaError::io("Failed to write to stdout log file", e))?;
+
+    println!("[{}] Job {} {}", Local::now().format("%Y-%m-%d %H:%M:%S"), entry.name, outcome);
+    events::record(harissa_dir, EventKind::Exit, &entry.name, None, &format!("scheduled job {}", outcome));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process;
+
+    fn job(argv: &[&str], cwd: Option<&Path>) -> ScheduleEntry {
+        ScheduleEntry {
+            kind: ScheduleKind::Job,
+            name: "backup".to_string(),
+            cron: "0 3 * * 1,5".to_string(),
+            argv: argv.iter().map(|arg| arg.to_string()).collect(),
+            cwd: cwd.map(Path::to_path_buf),
+        }
+    }
+
+    #[test]
+    fn schedule_lines_keep_arguments_apart() {
+        let entry = job(&["sh", "-c", "echo a  b\t> out\nexit 3", "C:\\temp"], Some(Path::new("/srv/my app")));
+        let line = entry.to_line();
+        assert!(!line.contains('\n'));
+
+        let parsed = ScheduleEntry::parse(&line).unwrap();
+        assert_eq!(parsed.kind, ScheduleKind::Job);
+        assert_eq!((parsed.name.as_str(), parsed.cron.as_str()), ("backup", "0 3 * * 1,5"));
+        assert_eq!(parsed.argv, entry.argv);
+        assert_eq!(parsed.cwd, entry.cwd);
+
+        let without_cwd = ScheduleEntry::parse(&job(&["./backup.sh"], None).to_line()).unwrap();
+        assert_eq!((without_cwd.argv, without_cwd.cwd), (vec!["./backup.sh".to_string()], None));
+    }
+
+    #[test]
+    fn reads_schedule_lines_of_older_versions() {
+        let parsed = ScheduleEntry::parse("restart\tapi\t*/5 * * * *\tnode  server.js --port 80").unwrap();
+        assert_eq!(parsed.kind, ScheduleKind::Restart);
+        assert_eq!(parsed.argv, vec!["node", "server.js", "--port", "80"]);
+        assert_eq!(parsed.cwd, None);
+
+        assert!(ScheduleEntry::parse("job\tbackup\t* * * * *").is_none());
+        assert!(ScheduleEntry::parse("cleanup\tbackup\t* * * * *\t/tmp\trm").is_none());
+    }
+
+    #[test]
+    fn jobs_run_in_their_directory_with_their_arguments() {
+        let dir = env::temp_dir().join(format!("harissa-supervisor-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("backup.sh"), "echo \"$1\" > result\n").unwrap();
+
+        run_job_to_completion(&dir, &job(&["sh", "./backup.sh", "a b  c"], Some(&dir))).unwrap();
+        let result = fs::read_to_string(dir.join("result"));
+        let log = fs::read_to_string(home::log_path(&dir, "backup", "out")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result.unwrap(), "a b  c\n");
+        assert!(log.contains("Scheduled job exited with code 0"), "{}", log);
+    }
+}
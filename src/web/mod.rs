@@ -0,0 +1,294 @@
+// HTTP REST API and dashboard for `harissa web`
+//
+// Routes (all under /api need the token as `Authorization: Bearer <token>`):
+//   GET  /                          the dashboard
+//   POST /api/sessions              a single-use session token for the log stream
+//   GET  /api/apps                  status of every application
+//   POST /api/apps                  start an application (same JSON as the socket API's `start`)
+//   POST /api/apps/<name>/stop      stop an application, keeping it to be started again
+//   POST /api/apps/<name>/start     start a stopped application again
+//   POST /api/apps/<name>/reload    reload an application
+//   DELETE /api/apps/<name>         stop an application and forget it
+//   GET  /api/apps/<name>/logs      server-sent events with log lines (?stream=out|err|both&lines=N);
+//                                   browsers cannot set headers on EventSource, so it also takes ?session=
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::{Value, json};
+use crate::api::{self, ApiError};
+use crate::config;
+use crate::error::HarissaError;
+
+const DASHBOARD: &str = include_str!("dashboard.html");
+/// Largest request line and headers accepted, to keep a client from exhausting memory
+const MAX_HEADER_SIZE: usize = 16 * 1024;
+/// Largest request body accepted, for the same reason
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+/// How long a client may stay silent, or leave a response unread, before its connection is closed
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a session token may wait before it is used to open a log stream
+const SESSION_LIFETIME: Duration = Duration::from_secs(30);
+
+/// A parsed HTTP request
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// The API token, and the session tokens handed out to holders of it.
+/// A session token only opens one log stream, so one that ends up in a URL or an access log is
+/// worthless by the time anyone reads it.
+struct Auth {
+    token: String,
+    sessions: Mutex<HashMap<String, Instant>>,
+}
+
+impl Auth {
+    /// Whether the request carries the API token
+    fn authorized(&self, request: &Request) -> bool {
+        let given = request.headers.get("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+
+        // Compare every byte so the time taken does not reveal how much of the token matched
+        given.is_some_and(|given| {
+            given.len() == self.token.len() && given.bytes().zip(self.token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+        })
+    }
+
+    /// Hand out a new session token, forgetting the expired ones
+    fn new_session(&self) -> Result<String, HarissaError> {
+        let session = config::generate_token()?;
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|_, issued| issued.elapsed() < SESSION_LIFETIME);
+        sessions.insert(session.clone(), Instant::now());
+        Ok(session)
+    }
+
+    /// Whether the request carries a live session token in `?session=`, which is used up
+    fn take_session(&self, request: &Request) -> bool {
+        let Some(session) = request.query.get("session") else {
+            return false;
+        };
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.remove(session).is_some_and(|issued| issued.elapsed() < SESSION_LIFETIME)
+    }
+}
+
+/// Serve the API and dashboard until the process is stopped; each connection gets its own thread
+pub fn serve(listener: TcpListener, harissa_dir: &Path, token: &str) -> Result<(), HarissaError> {
+    let auth = Arc::new(Auth { token: token.to_string(), sessions: Mutex::new(HashMap::new()) });
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        let harissa_dir = harissa_dir.to_path_buf();
+        let auth = Arc::clone(&auth);
+        thread::spawn(move || {
+            if let Err(e) = handle(stream, &harissa_dir, &auth) {
+                eprintln!("Failed to answer request: {}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, harissa_dir: &Path, auth: &Auth) -> Result<(), String> {
+    // Without these an idle client would hold its thread forever
+    stream.set_read_timeout(Some(IO_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+        .map_err(|e| format!("Failed to set timeouts: {}", e))?;
+
+    let request = match read_request(&stream)? {
+        Some(request) => request,
+        None => return respond(&mut stream, "400 Bad Request", "application/json", &json!({ "error": "Malformed request" }).to_string()),
+    };
+
+    if request.method == "GET" && request.path == "/" {
+        return respond(&mut stream, "200 OK", "text/html; charset=utf-8", DASHBOARD);
+    }
+
+    if !request.path.starts_with("/api/") {
+        return respond_json(&mut stream, "404 Not Found", &json!({ "error": "Not found" }));
+    }
+
+    let segments: Vec<String> = request.path.trim_start_matches("/api/").split('/').map(percent_decode).collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    let log_stream = matches!((request.method.as_str(), segments.as_slice()), ("GET", ["apps", _, "logs"]));
+    let authorized = auth.authorized(&request) || (log_stream && auth.take_session(&request));
+    if !authorized {
+        return respond_json(&mut stream, "401 Unauthorized", &json!({ "error": "Missing or invalid token" }));
+    }
+
+    // ?namespace= scopes every route to one namespace
+    let scoped = |mut params: Value| {
+        if let Some(namespace) = request.query.get("namespace") {
+            params["namespace"] = Value::from(namespace.as_str());
+        }
+        params
+    };
+
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["sessions"]) => auth.new_session()
+            .map(|session| json!({ "session": session, "expires_in": SESSION_LIFETIME.as_secs() }))
+            .map_err(ApiError::from),
+        ("GET", ["apps"]) => api::call(harissa_dir, "list", &scoped(json!({}))),
+        ("POST", ["apps"]) => match serde_json::from_slice::<Value>(&request.body) {
+            Ok(params) if params.is_object() => api::call(harissa_dir, "start", &scoped(params)),
+            Ok(_) => Err(ApiError::invalid_params("Expected a JSON object")),
+            Err(e) => Err(ApiError::invalid_params(format!("Invalid JSON: {}", e))),
+        },
+        ("POST", ["apps", name, "stop"]) => api::call(harissa_dir, "stop", &scoped(json!({ "name": name }))),
+        ("POST", ["apps", name, "start"]) => api::call(harissa_dir, "start", &scoped(json!({ "name": name }))),
+        ("DELETE", ["apps", name]) => api::call(harissa_dir, "delete", &scoped(json!({ "name": name }))),
+        ("POST", ["apps", name, "reload"]) => api::call(harissa_dir, "reload", &scoped(json!({ "name": name }))),
+        ("GET", ["apps", name, "logs"]) => {
+            let mut params = scoped(json!({ "name": name }));
+            for key in ["stream", "lines"] {
+                if let Some(value) = request.query.get(key) {
+                    params[key] = value.parse::<u64>().map(Value::from).unwrap_or_else(|_| Value::from(value.as_str()));
+                }
+            }
+            return stream_logs(&mut stream, harissa_dir, &params);
+        },
+        _ => return respond_json(&mut stream, "404 Not Found", &json!({ "error": "Not found" })),
+    };
+
+    match result {
+        Ok(result) => respond_json(&mut stream, "200 OK", &result),
+        Err(error) => respond_json(&mut stream, error_status(&error), &json!({ "error": error.message, "kind": error.kind })),
+    }
+}
+
+/// Stream log lines as server-sent events until the client disconnects
+fn stream_logs(stream: &mut TcpStream, harissa_dir: &Path, params: &Value) -> Result<(), String> {
+    let subscription = match api::log_subscription(harissa_dir, params) {
+        Ok(subscription) => subscription,
+        Err(error) => return respond_json(stream, error_status(&error), &json!({ "error": error.message, "kind": error.kind })),
+    };
+
+    write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n")
+        .map_err(|e| format!("Failed to write response: {}", e))?;
+
+    subscription.follow_logs(|log| {
+        let data = json!({ "stream": log.stream, "line": log.line });
+        write!(stream, "event: log\ndata: {}\n\n", data)
+            .and_then(|_| stream.flush())
+            .is_ok()
+    });
+
+    Ok(())
+}
+
+fn error_status(error: &ApiError) -> &'static str {
+    match (error.code, error.kind) {
+        (ApiError::INVALID_PARAMS, _) => "400 Bad Request",
+        (ApiError::METHOD_NOT_FOUND, _) | (_, "not_found") => "404 Not Found",
+        (_, "permission_denied") => "403 Forbidden",
+        (_, "already_exists") => "409 Conflict",
+        (_, "system") => "500 Internal Server Error",
+        _ => "422 Unprocessable Entity",
+    }
+}
+
+/// Read a request; `None` if it is not valid HTTP or too large
+fn read_request(stream: &TcpStream) -> Result<Option<Request>, String> {
+    let mut reader = BufReader::new(stream);
+    let mut header_budget = MAX_HEADER_SIZE;
+
+    let Some(request_line) = read_header_line(&mut reader, &mut header_budget)? else {
+        return Ok(None);
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+
+    let mut headers = HashMap::new();
+    loop {
+        let Some(header) = read_header_line(&mut reader, &mut header_budget)? else {
+            return Ok(None);
+        };
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length: usize = headers.get("content-length").and_then(|length| length.parse().ok()).unwrap_or(0);
+    if length > MAX_BODY_SIZE {
+        return Ok(None);
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)
+        .map_err(|e| format!("Failed to read request body: {}", e))?;
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (percent_decode(key), percent_decode(value)))
+        .collect();
+
+    Ok(Some(Request { method: method.to_string(), path: path.to_string(), query, headers, body }))
+}
+
+/// Read one line of the request line and headers, out of what is left of `budget`;
+/// `None` if the budget runs out or the client closes the connection first
+fn read_header_line(reader: &mut BufReader<&TcpStream>, budget: &mut usize) -> Result<Option<String>, String> {
+    let mut line = String::new();
+    let read = reader.by_ref().take(*budget as u64).read_line(&mut line)
+        .map_err(|e| format!("Failed to read request: {}", e))?;
+    *budget -= read;
+    Ok(line.ends_with('\n').then_some(line))
+}
+
+fn respond_json(stream: &mut TcpStream, status: &str, body: &Value) -> Result<(), String> {
+    respond(stream, status, "application/json", &body.to_string())
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> Result<(), String> {
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, content_type, body.len(), body)
+        .map_err(|e| format!("Failed to write response: {}", e))
+}
+
+/// Decode %XX escapes and '+' in a URL component
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    },
+                    None => decoded.push(b'%'),
+                }
+            },
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
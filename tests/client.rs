@@ -0,0 +1,320 @@
+// Start, stop, delete and resurrect flows of the client, each in a harissa home of its own
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::{self, Command, Output};
+use std::sync::{Arc, Mutex};
+use harissa::{AppRef, AppSpec, AppState, AppStatus, Harissa, HarissaError, Notice, StopOutcome};
+
+/// A temporary harissa home whose applications are deleted when the test ends, even on failure.
+struct TestHome {
+    harissa: Harissa,
+}
+
+impl TestHome {
+    fn new(test: &str) -> Self {
+        let dir = env::temp_dir().join(format!("harissa-test-{}-{}", process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TestHome { harissa: Harissa::with_dir(dir) }
+    }
+
+    fn dir(&self) -> &Path {
+        self.harissa.dir()
+    }
+
+    /// Run the harissa command on this home
+    fn cli(&self, args: &[&str]) -> Output {
+        let output = Command::new(env!("CARGO_BIN_EXE_harissa"))
+            .args(args)
+            .env("HARISSA_HOME", self.dir())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "harissa {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr));
+        output
+    }
+
+    fn status(&self, name: &str) -> AppStatus {
+        self.harissa.list().unwrap().into_iter()
+            .find(|app| app.name == name)
+            .unwrap_or_else(|| panic!("{} is not listed", name))
+    }
+}
+
+impl Drop for TestHome {
+    fn drop(&mut self) {
+        let _ = self.harissa.delete(&AppRef::All);
+        let _ = fs::remove_dir_all(self.dir());
+    }
+}
+
+fn sleeper() -> AppSpec {
+    AppSpec { argv: vec!["sleep".into(), "300".into()], ..AppSpec::default() }
+}
+
+/// Kill a process behind harissa's back and wait until it is gone
+fn crash(pid: u32) {
+    // SAFETY: kill and waitpid have no memory-safety preconditions
+    unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGKILL);
+        // Apps started by this process are its children, so it has to reap them itself
+        libc::waitpid(pid as libc::pid_t, std::ptr::null_mut(), 0);
+    }
+}
+
+fn reap(pid: u32) {
+    // SAFETY: waitpid has no memory-safety preconditions; WNOHANG keeps it from blocking
+    unsafe {
+        libc::waitpid(pid as libc::pid_t, std::ptr::null_mut(), libc::WNOHANG);
+    }
+}
+
+#[test]
+fn start_stop_start_and_delete() {
+    let home = TestHome::new("lifecycle");
+    let harissa = &home.harissa;
+
+    let app = harissa.start("api", &sleeper()).unwrap();
+    assert_eq!(app.id, 1);
+    assert_eq!(app.name, "api");
+    // The spec holds the environment of whoever started the app
+    let spec_mode = fs::metadata(home.dir().join("api.spec")).unwrap().permissions().mode();
+    assert_eq!(spec_mode & 0o777, 0o600);
+    let status = home.status("api");
+    assert_eq!(status.state, AppState::Online);
+    assert_eq!(status.pid, app.pid.to_string());
+
+    // Stopping keeps the application, with its ID, to be started again
+    let stopped = harissa.stop(&AppRef::Name("api".into())).unwrap();
+    assert_eq!(stopped.len(), 1);
+    assert_eq!(stopped[0].outcome, StopOutcome::Stopped);
+    reap(app.pid);
+    let status = home.status("api");
+    assert_eq!(status.state, AppState::Stopped);
+    assert_eq!(status.id, Some(1));
+    assert_eq!(harissa.stopped_names(&AppRef::All).unwrap(), vec!["api"]);
+
+    let started = harissa.start_stopped(&AppRef::Name("api".into())).unwrap();
+    assert_eq!(started.len(), 1);
+    let restarted = started[0].result.as_ref().unwrap();
+    assert_eq!(restarted.id, 1);
+    assert_ne!(restarted.pid, app.pid);
+    assert_eq!(home.status("api").state, AppState::Online);
+    assert!(harissa.stopped_names(&AppRef::All).unwrap().is_empty());
+
+    // Deleting forgets it altogether
+    harissa.delete(&AppRef::Name("api".into())).unwrap();
+    assert!(harissa.list().unwrap().is_empty());
+    assert_eq!(harissa.id_of("api").unwrap(), None);
+}
+
+#[test]
+fn deleting_a_stopped_app_forgets_it() {
+    let home = TestHome::new("delete-stopped");
+    let harissa = &home.harissa;
+
+    let app = harissa.start("api", &sleeper()).unwrap();
+    harissa.stop(&AppRef::All).unwrap();
+    reap(app.pid);
+
+    let deleted = harissa.delete(&AppRef::Name("api".into())).unwrap();
+    assert_eq!(deleted.len(), 1);
+    assert!(harissa.list().unwrap().is_empty());
+    assert!(matches!(harissa.start_stopped(&AppRef::All), Err(HarissaError::NotFound(_))));
+}
+
+#[test]
+fn names_are_unique_and_unknown_apps_are_not_found() {
+    let home = TestHome::new("errors");
+    let harissa = &home.harissa;
+
+    harissa.start("api", &sleeper()).unwrap();
+    assert!(matches!(harissa.start("api", &sleeper()), Err(HarissaError::AlreadyExists(_))));
+    assert!(matches!(harissa.stop(&AppRef::Name("web".into())), Err(HarissaError::NotFound(_))));
+    assert!(matches!(harissa.delete(&AppRef::Name("web".into())), Err(HarissaError::NotFound(_))));
+    assert!(matches!(harissa.start("empty", &AppSpec::default()), Err(HarissaError::Usage(_))));
+}
+
+#[test]
+fn ids_survive_a_crash_and_a_restart() {
+    let home = TestHome::new("crash");
+    let harissa = &home.harissa;
+
+    let api = harissa.start("api", &sleeper()).unwrap();
+    let web = harissa.start("web", &sleeper()).unwrap();
+    assert_eq!((api.id, web.id), (1, 2));
+
+    crash(api.pid);
+    assert_eq!(home.status("api").state, AppState::Errored);
+
+    // Starting an app whose process is gone replaces its entry and keeps its ID
+    let restarted = harissa.start("api", &sleeper()).unwrap();
+    assert_eq!(restarted.id, 1);
+    assert_eq!(harissa.id_of("web").unwrap(), Some(2));
+    assert_eq!(harissa.list().unwrap().iter().filter(|app| app.name == "api").count(), 1);
+
+    // New applications never reuse an ID
+    let worker = harissa.start("worker", &sleeper()).unwrap();
+    assert_eq!(worker.id, 3);
+}
+
+#[test]
+fn resurrect_restarts_saved_apps_with_their_ids() {
+    let home = TestHome::new("resurrect");
+    let harissa = &home.harissa;
+
+    let db = harissa.start("db", &sleeper()).unwrap();
+    let api_spec = AppSpec { after: vec!["db".into()], ..sleeper() };
+    let api = harissa.start("api", &api_spec).unwrap();
+    home.cli(&["save"]);
+
+    // As after a reboot: the processes are gone but still tracked
+    crash(db.pid);
+    crash(api.pid);
+    assert!(harissa.list().unwrap().iter().all(|app| app.state == AppState::Errored));
+
+    home.cli(&["resurrect"]);
+
+    let apps = harissa.list().unwrap();
+    assert_eq!(apps.len(), 2);
+    for (name, id, old_pid) in [("db", 1, db.pid), ("api", 2, api.pid)] {
+        let status = home.status(name);
+        assert_eq!(status.state, AppState::Online, "{} is not running", name);
+        assert_eq!(status.id, Some(id));
+        assert_ne!(status.pid, old_pid.to_string());
+    }
+    assert_eq!(harissa.names(&AppRef::All).unwrap().len(), 2);
+}
+
+#[test]
+fn dependents_stop_before_their_dependencies() {
+    let home = TestHome::new("stop-order");
+    let harissa = &home.harissa;
+
+    harissa.start("db", &sleeper()).unwrap();
+    harissa.start("api", &AppSpec { after: vec!["db".into()], ..sleeper() }).unwrap();
+    harissa.start("web", &AppSpec { after: vec!["api".into()], ..sleeper() }).unwrap();
+
+    let stopped = harissa.stop(&AppRef::All).unwrap();
+    let order: Vec<&str> = stopped.iter().map(|app| app.name.as_str()).collect();
+    assert_eq!(order, vec!["web", "api", "db"]);
+    assert!(stopped.iter().all(|app| app.outcome == StopOutcome::Stopped));
+}
+
+#[test]
+fn selectors_and_namespaces() {
+    let home = TestHome::new("select");
+    let harissa = &home.harissa;
+
+    harissa.start("api-1", &sleeper()).unwrap();
+    harissa.start("api-2", &sleeper()).unwrap();
+    let qa = Harissa::with_dir(home.dir()).in_namespace("qa").unwrap();
+    let qa_app = qa.start("api-1", &sleeper()).unwrap();
+    assert_eq!(qa_app.name, "qa/api-1");
+
+    let mut names = harissa.names(&AppRef::parse("api-*")).unwrap();
+    names.sort();
+    assert_eq!(names, vec!["api-1", "api-2"]);
+    assert_eq!(harissa.names(&AppRef::parse("/api-2$/")).unwrap(), vec!["api-2"]);
+    assert_eq!(harissa.names(&AppRef::parse("3")).unwrap(), vec!["qa/api-1"]);
+
+    // A namespaced client only sees its own applications, by their short names
+    assert_eq!(qa.names(&AppRef::All).unwrap(), vec!["qa/api-1"]);
+    assert_eq!(qa.id_of("api-1").unwrap(), Some(3));
+    qa.delete(&AppRef::All).unwrap();
+    assert_eq!(harissa.list().unwrap().len(), 2);
+    assert!(home.dir().join("qa").is_dir());
+}
+
+#[test]
+fn apps_that_exec_into_another_program_are_still_tracked() {
+    let home = TestHome::new("exec");
+    let harissa = &home.harissa;
+
+    let spec = AppSpec { argv: vec!["sh".into(), "-c".into(), "sleep 0.2; exec sleep 300".into()], ..AppSpec::default() };
+    let app = harissa.start("api", &spec).unwrap();
+    let exe = || fs::read_link(format!("/proc/{}/exe", app.pid)).unwrap();
+    let shell = exe();
+    while exe() == shell {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+
+    assert_eq!(home.status("api").state, AppState::Online);
+    let stopped = harissa.stop(&AppRef::Name("api".into())).unwrap();
+    assert_eq!(stopped[0].outcome, StopOutcome::Stopped);
+    reap(app.pid);
+}
+
+#[test]
+fn processes_without_a_recorded_identity_are_never_signalled() {
+    let home = TestHome::new("unverified");
+    let harissa = &home.harissa;
+
+    // An entry tracked without an identity, whose PID now belongs to an unrelated process
+    let mut unrelated = Command::new("sleep").arg("300").spawn().unwrap();
+    fs::write(home.dir().join("processes.csv"), format!("{},api,sleep 300\n", unrelated.id())).unwrap();
+
+    assert_eq!(home.status("api").state, AppState::Errored);
+    let stopped = harissa.stop(&AppRef::Name("api".into())).unwrap();
+    assert_eq!(stopped[0].outcome, StopOutcome::NotRunning);
+    assert!(unrelated.try_wait().unwrap().is_none());
+
+    unrelated.kill().unwrap();
+    unrelated.wait().unwrap();
+}
+
+#[test]
+fn starting_fails_when_the_identity_cannot_be_recorded() {
+    let home = TestHome::new("unrecorded");
+    fs::create_dir(home.dir().join("identities.tsv")).unwrap();
+
+    let spec = AppSpec { argv: vec!["sleep".into(), "299".into()], ..AppSpec::default() };
+    assert!(home.harissa.start("api", &spec).is_err());
+    assert!(home.harissa.list().unwrap().is_empty());
+
+    // The process was killed rather than left running untracked
+    let left_running = || fs::read_dir("/proc").unwrap().filter_map(Result::ok).any(|entry| {
+        let path = entry.path();
+        fs::read(path.join("cmdline")).is_ok_and(|cmdline| cmdline == b"sleep\x00299\x00")
+            && fs::read_to_string(path.join("stat")).is_ok_and(|stat| !stat.contains(") Z "))
+    });
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while left_running() && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    assert!(!left_running());
+}
+
+#[test]
+fn warnings_go_to_the_observer() {
+    let home = TestHome::new("observer");
+    let notices = Arc::new(Mutex::new(Vec::new()));
+    let observed = Arc::clone(&notices);
+    let harissa = Harissa::with_dir(home.dir()).with_observer(move |notice| observed.lock().unwrap().push(notice));
+
+    let mut spec = sleeper();
+    spec.hooks.post_start = Some("exit 3".to_string());
+    let app = harissa.start("api", &spec).unwrap();
+    harissa.stop(&AppRef::All).unwrap();
+    reap(app.pid);
+
+    assert_eq!(*notices.lock().unwrap(), vec![Notice::Warning("post-start hook of api exited with code 3".to_string())]);
+}
+
+#[test]
+fn events_record_the_real_user_not_the_environment() {
+    let home = TestHome::new("events");
+    let output = Command::new(env!("CARGO_BIN_EXE_harissa"))
+        .args(["start", "sleep", "300", "-n", "api"])
+        .env("HARISSA_HOME", home.dir())
+        .env("USER", "mallory")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let whoami = Command::new("id").arg("-un").output().unwrap();
+    let events = harissa::events::read(home.dir()).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].user, String::from_utf8_lossy(&whoami.stdout).trim());
+}
@@ -0,0 +1,107 @@
+// Generated startup files, compared with the expected ones in tests/fixtures/startup
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use harissa::init_scripts::{self, InitSystem, StartupTarget};
+
+fn target(user: &str, harissa_home: Option<&str>) -> StartupTarget {
+    StartupTarget {
+        user: user.to_string(),
+        home: if user == "root" { PathBuf::from("/root") } else { Path::new("/home").join(user) },
+        harissa_path: PathBuf::from("/usr/local/bin/harissa"),
+        harissa_home: harissa_home.map(PathBuf::from),
+    }
+}
+
+fn command(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|part| part.to_string()).collect()
+}
+
+#[test]
+fn systemd_unit() {
+    let artifact = init_scripts::generate(InitSystem::Systemd, &target("alice", None));
+    assert_eq!(artifact.contents, include_str!("fixtures/startup/systemd.service"));
+    assert_eq!(artifact.path, Path::new("/etc/systemd/system/harissa-alice.service"));
+    assert!(!artifact.executable);
+    assert_eq!(artifact.enable_commands, vec![
+        command(&["systemctl", "daemon-reload"]),
+        command(&["systemctl", "enable", "harissa-alice.service"]),
+    ]);
+}
+
+#[test]
+fn systemd_unit_quotes_a_custom_home() {
+    let artifact = init_scripts::generate(InitSystem::Systemd, &target("bob", Some("/srv/harissa apps/100%")));
+    assert_eq!(artifact.contents, include_str!("fixtures/startup/systemd-custom-home.service"));
+}
+
+#[test]
+fn systemd_user_unit() {
+    let artifact = init_scripts::generate(InitSystem::SystemdUser, &target("alice", None));
+    assert_eq!(artifact.contents, include_str!("fixtures/startup/systemd-user.service"));
+    assert_eq!(artifact.path, Path::new("/home/alice/.config/systemd/user/harissa.service"));
+    assert_eq!(artifact.disable_commands, vec![
+        command(&["systemctl", "--user", "disable", "harissa.service"]),
+        command(&["systemctl", "--user", "daemon-reload"]),
+    ]);
+}
+
+#[test]
+fn openrc_script() {
+    let artifact = init_scripts::generate(InitSystem::OpenRc, &target("alice", Some("/srv/harissa apps")));
+    assert_eq!(artifact.contents, include_str!("fixtures/startup/openrc"));
+    assert_eq!(artifact.path, Path::new("/etc/init.d/harissa-alice"));
+    assert!(artifact.executable);
+    assert_eq!(artifact.enable_commands, vec![command(&["rc-update", "add", "harissa-alice", "default"])]);
+}
+
+#[test]
+fn sysv_script() {
+    // The enable commands depend on whether this machine has update-rc.d or chkconfig
+    let artifact = init_scripts::generate(InitSystem::SysV, &target("alice", None));
+    assert_eq!(artifact.contents, include_str!("fixtures/startup/sysv"));
+    assert_eq!(artifact.path, Path::new("/etc/init.d/harissa-alice"));
+    assert!(artifact.executable);
+}
+
+#[test]
+fn sysv_script_for_root_runs_harissa_directly() {
+    let artifact = init_scripts::generate(InitSystem::SysV, &target("root", Some("/srv/it's here")));
+    assert_eq!(artifact.contents, include_str!("fixtures/startup/sysv-root"));
+}
+
+#[test]
+fn shell_scripts_are_valid() {
+    for init in [InitSystem::OpenRc, InitSystem::SysV] {
+        for target in [target("alice", Some("/srv/it's \"here\" $HOME")), target("root", None)] {
+            let contents = init_scripts::generate(init, &target).contents;
+            let status = Command::new("sh").args(["-n", "-c", &contents]).status().unwrap();
+            assert!(status.success(), "{} script does not parse:\n{}", init.as_str(), contents);
+        }
+    }
+}
+
+#[test]
+fn parses_init_system_names() {
+    assert_eq!(InitSystem::parse("systemd", false), Ok(InitSystem::Systemd));
+    assert_eq!(InitSystem::parse("systemd", true), Ok(InitSystem::SystemdUser));
+    assert_eq!(InitSystem::parse("openrc", false), Ok(InitSystem::OpenRc));
+    assert_eq!(InitSystem::parse("sysv", false), Ok(InitSystem::SysV));
+    assert!(InitSystem::parse("sysv", true).is_err());
+    assert!(InitSystem::parse("upstart", false).is_err());
+}
+
+#[test]
+fn quotes_for_the_shell() {
+    assert_eq!(init_scripts::shell_quote("/usr/bin/harissa"), "/usr/bin/harissa");
+    assert_eq!(init_scripts::shell_quote("a b"), "'a b'");
+    assert_eq!(init_scripts::shell_quote("it's"), "'it'\\''s'");
+    assert_eq!(init_scripts::shell_quote(""), "''");
+}
+
+#[test]
+fn quotes_for_systemd() {
+    assert_eq!(init_scripts::systemd_quote("HOME=/home/alice"), "HOME=/home/alice");
+    assert_eq!(init_scripts::systemd_quote("HOME=/home/a b"), "\"HOME=/home/a b\"");
+    assert_eq!(init_scripts::systemd_quote("100%"), "100%%");
+    assert_eq!(init_scripts::systemd_quote("say \"hi\""), "\"say \\\"hi\\\"\"");
+}
@@ -0,0 +1,205 @@
+// Routing and authentication of `harissa web`, served on a free port of 127.0.0.1
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::Duration;
+use serde_json::{Value, json};
+use harissa::{AppRef, Harissa};
+
+const TOKEN: &str = "0123456789abcdef";
+
+/// A server on a temporary harissa home; its applications are deleted when the test ends.
+struct TestServer {
+    address: SocketAddr,
+    dir: PathBuf,
+}
+
+impl TestServer {
+    fn start(test: &str) -> Self {
+        let dir = env::temp_dir().join(format!("harissa-web-test-{}-{}", process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let serve_dir = dir.clone();
+        thread::spawn(move || harissa::web::serve(listener, &serve_dir, TOKEN));
+
+        TestServer { address, dir }
+    }
+
+    fn connect(&self) -> TcpStream {
+        let stream = TcpStream::connect(self.address).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        stream
+    }
+
+    /// Send a request and return the status code and body
+    fn request(&self, method: &str, target: &str, token: Option<&str>, body: Option<&Value>) -> (u16, String) {
+        let mut stream = self.connect();
+        let body = body.map(Value::to_string).unwrap_or_default();
+        let authorization = token.map(|token| format!("Authorization: Bearer {}\r\n", token)).unwrap_or_default();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}", method, target, authorization, body.len(), body).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, body.to_string())
+    }
+
+    fn json(&self, method: &str, target: &str, body: Option<&Value>) -> (u16, Value) {
+        let (status, body) = self.request(method, target, Some(TOKEN), body);
+        (status, serde_json::from_str(&body).unwrap_or_else(|e| panic!("invalid JSON {:?}: {}", body, e)))
+    }
+
+    fn new_session(&self) -> String {
+        let (status, session) = self.json("POST", "/api/sessions", None);
+        assert_eq!(status, 200);
+        session["session"].as_str().unwrap().to_string()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = Harissa::with_dir(&self.dir).delete(&AppRef::All);
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn serves_the_dashboard_without_a_token() {
+    let server = TestServer::start("dashboard");
+    let (status, body) = server.request("GET", "/", None, None);
+    assert_eq!(status, 200);
+    assert!(body.contains("<html"));
+}
+
+#[test]
+fn api_needs_the_token_in_the_header() {
+    let server = TestServer::start("auth");
+
+    assert_eq!(server.request("GET", "/api/apps", None, None).0, 401);
+    assert_eq!(server.request("GET", "/api/apps", Some("0123456789abcdeX"), None).0, 401);
+    assert_eq!(server.request("GET", "/api/apps", Some("0123"), None).0, 401);
+    // The token is not accepted in the URL, where it would end up in logs and history
+    assert_eq!(server.request("GET", &format!("/api/apps?token={}", TOKEN), None, None).0, 401);
+    assert_eq!(server.request("POST", "/api/sessions", None, None).0, 401);
+
+    let (status, apps) = server.json("GET", "/api/apps", None);
+    assert_eq!(status, 200);
+    assert_eq!(apps, json!([]));
+}
+
+#[test]
+fn manages_apps() {
+    let server = TestServer::start("apps");
+
+    let (status, started) = server.json("POST", "/api/apps", Some(&json!({ "command": ["sleep", "300"], "name": "api" })));
+    assert_eq!(status, 200, "{}", started);
+    assert_eq!(started["name"], "api");
+    assert_eq!(started["id"], 1);
+
+    let (status, conflict) = server.json("POST", "/api/apps", Some(&json!({ "command": ["sleep", "300"], "name": "api" })));
+    assert_eq!((status, conflict["kind"].as_str()), (409, Some("already_exists")));
+
+    let (_, apps) = server.json("GET", "/api/apps", None);
+    assert_eq!(apps[0]["name"], "api");
+    assert_eq!(apps[0]["state"], "online");
+
+    // Stopping keeps the app, and starting it again keeps its ID
+    assert_eq!(server.json("POST", "/api/apps/api/stop", None).0, 200);
+    let (_, apps) = server.json("GET", "/api/apps", None);
+    assert_eq!(apps[0]["state"], "stopped");
+
+    let (status, started) = server.json("POST", "/api/apps/api/start", None);
+    assert_eq!(status, 200, "{}", started);
+    assert_eq!(started[0]["id"], 1);
+
+    let (status, reloaded) = server.json("POST", "/api/apps/api/reload", None);
+    assert_eq!(status, 200, "{}", reloaded);
+
+    assert_eq!(server.json("DELETE", "/api/apps/api", None).0, 200);
+    assert_eq!(server.json("GET", "/api/apps", None).1, json!([]));
+    assert_eq!(server.json("DELETE", "/api/apps/api", None).0, 404);
+}
+
+#[test]
+fn rejects_bad_requests_and_unknown_routes() {
+    let server = TestServer::start("routes");
+
+    let (status, _) = server.json("GET", "/api/unknown", None);
+    assert_eq!(status, 404);
+    assert_eq!(server.request("GET", "/elsewhere", None, None).0, 404);
+    assert_eq!(server.json("PUT", "/api/apps", None).0, 404);
+
+    let mut stream = server.connect();
+    write!(stream, "POST /api/apps HTTP/1.1\r\nAuthorization: Bearer {}\r\nContent-Length: 5\r\n\r\n{{bad}}", TOKEN).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+
+    let (status, error) = server.json("POST", "/api/apps", Some(&json!({ "command": [], "name": "api" })));
+    assert_eq!((status, error["kind"].as_str()), (400, Some("usage")));
+
+    // Without a command, a name selects stopped applications to start again
+    let (status, error) = server.json("POST", "/api/apps", Some(&json!({ "name": "api" })));
+    assert_eq!((status, error["kind"].as_str()), (404, Some("not_found")));
+}
+
+#[test]
+fn closes_idle_connections_and_oversized_headers() {
+    let server = TestServer::start("limits");
+
+    // A client that never sends its request is dropped once the server's timeout runs out
+    let mut stream = server.connect();
+    stream.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    assert!(response.is_empty());
+
+    // A request line that does not end within the header limit is refused without waiting for the rest
+    let mut stream = server.connect();
+    let target = "a".repeat(16 * 1024 - "GET /".len());
+    write!(stream, "GET /{}", target).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+}
+
+#[test]
+fn log_stream_takes_a_single_use_session() {
+    let server = TestServer::start("logs");
+    let command = json!({ "command": ["sh", "-c", "echo hello from the app; exec sleep 300"], "name": "api" });
+    assert_eq!(server.json("POST", "/api/apps", Some(&command)).0, 200);
+
+    let session = server.new_session();
+    // A session only opens a log stream
+    assert_eq!(server.request("GET", &format!("/api/apps?session={}", session), None, None).0, 401);
+
+    let mut stream = server.connect();
+    write!(stream, "GET /api/apps/api/logs?lines=10&session={} HTTP/1.1\r\n\r\n", session).unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).unwrap();
+    assert!(status_line.starts_with("HTTP/1.1 200"), "{}", status_line);
+
+    let mut found = false;
+    for line in reader.lines() {
+        let line = line.unwrap();
+        if line.starts_with("data: ") && line.contains("hello from the app") {
+            found = true;
+            break;
+        }
+    }
+    assert!(found);
+
+    // The session was used up by the stream above
+    let target = format!("/api/apps/api/logs?session={}", session);
+    assert_eq!(server.request("GET", &target, None, None).0, 401);
+    assert_eq!(server.request("GET", "/api/apps/api/logs?session=unknown", None, None).0, 401);
+}